@@ -0,0 +1,59 @@
+use qqself_core::import::ImportedEntry;
+
+use super::{parse_datetime, split_names, Importer, Row};
+
+/// ATracker CSV export. Task name is used as the first tag, then ATracker tags follow
+pub struct ATrackerImporter;
+
+impl Importer for ATrackerImporter {
+    fn convert(&self, row: &Row) -> Result<Option<ImportedEntry>, String> {
+        let mut tags = vec![row.get("Task name")?.to_string()];
+        tags.extend(split_names(row.optional("Tag").unwrap_or_default()));
+        let comment = [row.optional("Task description"), row.optional("Note")]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(". ");
+        Ok(Some(ImportedEntry {
+            start: parse_datetime(row.get("Start time")?)?,
+            end: parse_datetime(row.get("End time")?)?,
+            tags,
+            comment: Some(comment).filter(|v| !v.is_empty()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qqself_core::import::TagMapping;
+
+    use crate::importers::import;
+
+    use super::*;
+
+    #[test]
+    fn atracker() {
+        let csv =
+            "Task name,Task description,Start time,End time,Duration,Duration in hours,Note,Tag
+Running,,2023-07-03 07:00,2023-07-03 07:45,00:45,0.75,Easy pace,
+Work,Office,2023-07-03 09:00,2023-07-03 17:00,08:00,8,,meetings
+Work,,2023-07-03 25:00,2023-07-03 26:00,01:00,1,,
+";
+        let got = import(csv.as_bytes(), &ATrackerImporter, &TagMapping::default());
+        assert_eq!(got.unwrap_err().len(), 1);
+
+        let csv: String = csv.lines().take(3).map(|v| format!("{v}\n")).collect();
+        let got: Vec<_> = import(csv.as_bytes(), &ATrackerImporter, &TagMapping::default())
+            .unwrap()
+            .iter()
+            .map(|v| v.serialize(true, true))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                "2023-07-03 07:00 07:45 running. Easy pace",
+                "2023-07-03 09:00 17:00 work. meetings. Office",
+            ]
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use qqself_core::import::ImportedEntry;
+
+use super::{parse_datetime, split_names, Importer, Row};
+
+/// CSV with user defined columns. Tags column may contain multiple comma separated names
+pub struct GenericImporter {
+    pub start_column: String,
+    pub end_column: String,
+    pub tags_column: String,
+    pub comment_column: String,
+}
+
+impl Importer for GenericImporter {
+    fn convert(&self, row: &Row) -> Result<Option<ImportedEntry>, String> {
+        Ok(Some(ImportedEntry {
+            start: parse_datetime(row.get(&self.start_column)?)?,
+            end: parse_datetime(row.get(&self.end_column)?)?,
+            tags: split_names(row.get(&self.tags_column)?).collect(),
+            comment: row.optional(&self.comment_column).map(|v| v.to_string()),
+        }))
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::Read;
+
+use qqself_core::{
+    date_time::datetime::DateTime,
+    import::{ImportedEntry, TagMapping},
+    record::Entry,
+};
+
+pub mod atracker;
+pub mod generic;
+pub mod toggl;
+
+/*
+Importers convert CSV exports of other time trackers to qqself entries. Every importer only knows how to read
+its own format, tag mapping and validation are shared and done by `ImportedEntry::to_entry`
+*/
+
+pub trait Importer {
+    /// Converts CSV row to the entry, returns None if the row has to be skipped
+    fn convert(&self, row: &Row) -> Result<Option<ImportedEntry>, String>;
+}
+
+/// CSV row with access to values by column name
+pub struct Row<'a> {
+    headers: &'a csv::StringRecord,
+    record: &'a csv::StringRecord,
+}
+
+impl Row<'_> {
+    /// Value of the column, error if there is no such column
+    pub fn get(&self, column: &str) -> Result<&str, String> {
+        let idx = self
+            .headers
+            .iter()
+            .position(|v| v.trim() == column)
+            .ok_or_else(|| format!("Column {column} not found"))?;
+        Ok(self.record.get(idx).unwrap_or_default().trim())
+    }
+
+    /// Value of the column if column exists and value isn't empty
+    pub fn optional(&self, column: &str) -> Option<&str> {
+        self.get(column).ok().filter(|v| !v.is_empty())
+    }
+}
+
+/// Parses date time in formats YYYY-MM-DD HH:MM or ISO 8601 YYYY-MM-DDTHH:MM, seconds and offsets are ignored
+pub fn parse_datetime(s: &str) -> Result<DateTime, String> {
+    let s = s.trim().replacen('T', " ", 1);
+    s.get(..DateTime::SIZE)
+        .ok_or_else(|| format!("Bad date time {s}, expected YYYY-MM-DD HH:MM"))?
+        .parse()
+}
+
+/// Reads CSV and converts all the rows to entries. All the rows are checked, so errors contain every failed row
+pub fn import(
+    input: impl Read,
+    importer: &dyn Importer,
+    mapping: &TagMapping,
+) -> Result<Vec<Entry>, Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+    let headers = reader
+        .headers()
+        .map_err(|err| vec![format!("Cannot read CSV header: {err}")])?
+        .clone();
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        // Header is the first line, so data starts from the second one
+        let line = idx + 2;
+        let entry = record.map_err(|err| err.to_string()).and_then(|record| {
+            let row = Row {
+                headers: &headers,
+                record: &record,
+            };
+            match importer.convert(&row)? {
+                Some(imported) => imported.to_entry(mapping).map(Some),
+                None => Ok(None),
+            }
+        });
+        match entry {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {}
+            Err(err) => errors.push(format!("Line {line}: {err}")),
+        }
+    }
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Splits comma separated list of names, used for tags columns
+pub fn split_names(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(',')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datetime() {
+        let want: DateTime = "2023-07-03 10:00".parse().unwrap();
+        assert_eq!(parse_datetime("2023-07-03T10:00:00+02:00"), Ok(want));
+        assert_eq!(parse_datetime(" 2023-07-03 10:00:59 "), Ok(want));
+        assert!(parse_datetime("2023-07-03").is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use qqself_core::import::ImportedEntry;
+
+use super::{parse_datetime, split_names, Importer, Row};
+
+/// Toggl Track detailed report CSV export. Project is used as the first tag, then Toggl tags follow
+pub struct TogglImporter;
+
+impl Importer for TogglImporter {
+    fn convert(&self, row: &Row) -> Result<Option<ImportedEntry>, String> {
+        let datetime = |date: &str, time: &str| -> Result<_, String> {
+            parse_datetime(&format!("{} {}", row.get(date)?, row.get(time)?))
+        };
+        let mut tags: Vec<_> = row
+            .optional("Project")
+            .map(|v| v.to_string())
+            .into_iter()
+            .collect();
+        tags.extend(split_names(row.optional("Tags").unwrap_or_default()));
+        Ok(Some(ImportedEntry {
+            start: datetime("Start date", "Start time")?,
+            end: datetime("End date", "End time")?,
+            tags,
+            comment: row.optional("Description").map(|v| v.to_string()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qqself_core::import::TagMapping;
+
+    use crate::importers::import;
+
+    use super::*;
+
+    #[test]
+    fn toggl() {
+        let csv = "User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags,Amount ()
+Alice,alice@example.com,,Client work,,Fixing bugs,No,2023-07-03,10:00:00,2023-07-03,11:30:00,01:30:00,\"Deep focus, Remote\",
+Alice,alice@example.com,,,,reading,No,2023-07-03,12:00:00,2023-07-03,13:00:00,01:00:00,Learning,
+";
+        let mapping = TagMapping::new(&["Client work=work".to_string()]).unwrap();
+        let got: Vec<_> = import(csv.as_bytes(), &TogglImporter, &mapping)
+            .unwrap()
+            .iter()
+            .map(|v| v.serialize(true, true))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                "2023-07-03 10:00 11:30 work. deepfocus. remote. Fixing bugs",
+                "2023-07-03 12:00 13:00 learning. Reading",
+            ]
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::exit,
+};
+
+use qqself_core::{
+    db::{Record, DB},
+    parsing::journal::JournalReader,
+};
+use tracing::error;
+
+/// Reads all the entries from the journal file into the DB, exits if journal file doesn't exist
+pub fn load_db(journal_path: &Path) -> DB {
+    if !journal_path.exists() {
+        error!("Journal file does not exists at {:?}", journal_path);
+        exit(1);
+    }
+    let file = File::open(journal_path).expect("Journal path should point to the openable file");
+    let reader = BufReader::new(file);
+    let mut db = DB::new();
+    let mut journal = JournalReader::default();
+    reader.lines().for_each(|line| {
+        let line = line.expect("Cannot read journal line");
+        let entry = journal
+            .read_line(&line)
+            .unwrap_or_else(|err| panic!("journal should be valid: {err}, line='{line}'"));
+        let Some(entry) = entry else {
+            return; // Skip comments, empty lines and day headers
+        };
+        // Parse the record to see if it's a valid one
+        let record = Record::parse(&entry)
+            .unwrap_or_else(|err| panic!("entry should be valid: {err}, line='{line}'"));
+        db.add(record, false, None);
+    });
+    db
+}
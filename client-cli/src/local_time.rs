@@ -0,0 +1,32 @@
+use qqself_core::date_time::{datetime::DateTime, timestamp::Timestamp};
+
+/// Parses offset like +02:00 or -05:30 to minutes
+pub fn parse_utc_offset(s: &str) -> Option<i32> {
+    let sign = match s.get(0..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = s[1..].split_once(':')?;
+    let hours: i32 = hours.parse().ok().filter(|v| *v <= 14)?;
+    let minutes: i32 = minutes.parse().ok().filter(|v| *v < 60)?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Current time in the time zone with given offset from UTC in minutes. Seconds are truncated as entries have none
+pub fn local_now(utc_offset: i32) -> DateTime {
+    DateTime::from_timestamp(Timestamp::now(), utc_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_offset() {
+        assert_eq!(parse_utc_offset("+02:00"), Some(120));
+        assert_eq!(parse_utc_offset("-05:30"), Some(-330));
+        assert_eq!(parse_utc_offset("02:00"), None);
+        assert_eq!(parse_utc_offset("+02:75"), None);
+    }
+}
@@ -1,16 +1,26 @@
 use clap::{self, Parser};
 use operations::{
+    check::{check, CheckOpts},
     delete::{delete, DeleteOpts},
     download::{download, DownloadOpts},
+    export::{export, ExportOpts},
+    fmt::{fmt, FmtOpts},
+    import::{import, ImportOpts},
     init::{init, InitOpts},
+    rename::{rename, RenameOpts},
     report::{report, ReportOpts},
     upload::{upload, UploadOpts},
+    workout::{workout, WorkoutOpts},
 };
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
 mod http;
+mod importers;
+mod journal;
 mod key_file;
+mod local_time;
 mod operations;
+mod workouts;
 
 #[derive(Parser, Debug)]
 enum Opts {
@@ -19,6 +29,12 @@ enum Opts {
     Download(DownloadOpts),
     Report(ReportOpts),
     Delete(DeleteOpts),
+    Fmt(FmtOpts),
+    Check(CheckOpts),
+    Rename(RenameOpts),
+    Export(ExportOpts),
+    Import(ImportOpts),
+    Workout(WorkoutOpts),
 }
 
 fn main() {
@@ -37,6 +53,12 @@ fn main() {
         Opts::Upload(opts) => upload(opts),
         Opts::Report(opts) => report(opts),
         Opts::Delete(opts) => delete(opts),
+        Opts::Fmt(opts) => fmt(opts),
+        Opts::Check(opts) => check(opts),
+        Opts::Rename(opts) => rename(opts),
+        Opts::Export(opts) => export(opts),
+        Opts::Import(opts) => import(opts),
+        Opts::Workout(opts) => workout(opts),
         Opts::Download(opts) => {
             download(opts);
         }
@@ -0,0 +1,54 @@
+use std::{fs, path::Path, process::exit};
+
+use clap::Parser;
+use qqself_core::parsing::lint::{lint_journal, Severity};
+use tracing::{error, info, warn};
+
+use crate::local_time::{local_now, parse_utc_offset};
+
+#[derive(Parser, Debug)]
+#[command(about = "Check the journal for parsing errors and suspicious entries")]
+pub struct CheckOpts {
+    /// Path to journal file with all the entries
+    #[arg(short, long, default_value = "journal.txt")]
+    journal_path: String,
+
+    /// Offset of local time zone from UTC in format +HH:MM, used to find entries ending in the future
+    #[arg(long, default_value = "+00:00", allow_hyphen_values = true)]
+    utc_offset: String,
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn check(opts: CheckOpts) {
+    let journal_path = Path::new(&opts.journal_path);
+    if !journal_path.exists() {
+        error!("Journal file does not exists at {:?}", journal_path);
+        exit(1);
+    }
+    let Some(utc_offset) = parse_utc_offset(&opts.utc_offset) else {
+        error!(
+            "UTC offset has to be in format +HH:MM, got {}",
+            opts.utc_offset
+        );
+        exit(1);
+    };
+    let input = fs::read_to_string(journal_path).expect("Journal file should be readable");
+    let issues = lint_journal(&input, local_now(utc_offset));
+    for issue in &issues {
+        println!("{}:{}", opts.journal_path, issue);
+    }
+    let errors = issues
+        .iter()
+        .filter(|v| v.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        error!("Journal has {errors} errors and {warnings} warnings");
+        exit(1);
+    }
+    if warnings > 0 {
+        warn!("Journal has {warnings} warnings");
+    } else {
+        info!("Journal is valid");
+    }
+}
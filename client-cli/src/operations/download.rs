@@ -7,6 +7,7 @@ use std::{
 use clap::Parser;
 use qqself_core::{
     api::ApiRequests, date_time::datetime::DateDay, db::Record, encryption::cryptor::Cryptor,
+    parsing::journal::write_journal,
 };
 use rayon::prelude::*;
 use rayon::str::ParallelString;
@@ -62,15 +63,7 @@ fn download_journal(journal_path: &Path, cryptor: Cryptor) -> PathBuf {
         .collect::<Vec<_>>();
 
     info!("Processing entries...");
-    let mut output = String::new();
-    let mut prev_day = None;
-    for entry in entries {
-        if !prev_day.is_some_and(|v| v == entry.date_range().start().date()) {
-            prev_day.replace(entry.date_range().start().date());
-            output.push('\n');
-        }
-        output.push_str(&format!("{}\n", entry.to_string(true, true)));
-    }
+    let output = write_journal(&entries);
     info!("Writing entries to journal {:?} ...", journal_path);
     fs::write(journal_path, output).expect("Failed to write journal file");
     journal_path.to_owned()
@@ -157,14 +150,15 @@ mod tests {
         let content = fs::read_to_string(wrote).unwrap();
         assert_eq!(
             content,
-            "
-2022-10-03 00:00 01:00 foo1
-2022-10-03 00:00 02:00 foo2
-2022-10-03 00:00 03:00 foo3
+            "2022-10-03
+00:00 01:00 foo1
+00:00 02:00 foo2
+00:00 03:00 foo3
 
-2022-10-04 00:00 04:00 foo4
-2022-10-04 00:00 05:00 foo5
-2022-10-04 00:00 05:00 updated. entry revision=2
+2022-10-04
+00:00 04:00 foo4
+00:00 05:00 foo5
+00:00 05:00 updated. entry revision=2
 "
         );
     }
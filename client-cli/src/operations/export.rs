@@ -0,0 +1,72 @@
+use std::{fs, path::Path, process::exit};
+
+use clap::{Parser, ValueEnum};
+use qqself_core::{
+    db::Query,
+    export::{export_rows, to_csv},
+    ical::export_ics,
+};
+use tracing::{error, info};
+
+use crate::journal::load_db;
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+    /// iCalendar events, e.g. for viewing entries in calendar apps
+    Ics,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Export journal entries to CSV or JSON for analysis in spreadsheets or to iCalendar"
+)]
+pub struct ExportOpts {
+    /// Path to journal file with all the entries
+    #[arg(short, long, default_value = "journal.txt")]
+    journal_path: String,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: ExportFormat,
+
+    /// Query to filter the entries, e.g. `run. filter after=2023-07-01`. All the entries are exported by default
+    #[arg(short, long, default_value = "")]
+    query: String,
+
+    /// Path to output file, output is printed if not set
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn export(opts: ExportOpts) {
+    let mut db = load_db(Path::new(&opts.journal_path));
+    let query = match Query::new(&opts.query) {
+        Ok(v) => v,
+        Err(err) => {
+            error!(
+                "Query is invalid: {}",
+                err.diagnostic(&opts.query, false).render(&opts.query)
+            );
+            exit(1);
+        }
+    };
+    db.update_query(query);
+    let rows = export_rows(db.query_results());
+    let output = match opts.format {
+        ExportFormat::Csv => to_csv(&rows),
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&rows).expect("Export rows should be serializable") + "\n"
+        }
+        ExportFormat::Ics => export_ics(db.query_results()),
+    };
+    match opts.output {
+        Some(path) => {
+            fs::write(&path, output).expect("Output file should be writable");
+            info!("Exported {} entries to {:?}", rows.len(), path);
+        }
+        None => print!("{output}"),
+    }
+}
@@ -0,0 +1,57 @@
+use std::{fs, path::Path, process::exit};
+
+use clap::Parser;
+use qqself_core::parsing::journal::format_journal;
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[command(about = "Rewrite the journal file in canonical form with entries sorted by time")]
+pub struct FmtOpts {
+    /// Path to journal file with all the entries
+    #[arg(short, long, default_value = "journal.txt")]
+    journal_path: String,
+
+    /// Don't write the file, only report lines that would be changed. Exits with error if there are any
+    #[arg(short, long)]
+    check: bool,
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn fmt(opts: FmtOpts) {
+    let journal_path = Path::new(&opts.journal_path);
+    if !journal_path.exists() {
+        error!("Journal file does not exists at {:?}", journal_path);
+        exit(1);
+    }
+    let input = fs::read_to_string(journal_path).expect("Journal file should be readable");
+    let formatted = match format_journal(&input) {
+        Ok(v) => v,
+        Err(err) => {
+            error!("Journal is invalid: {err}");
+            exit(1);
+        }
+    };
+    if opts.check {
+        let lines: Vec<_> = input.lines().collect();
+        for line in &formatted.changed_lines {
+            println!("{}:{}: {}", opts.journal_path, line, lines[line - 1]);
+        }
+        if !formatted.changed_lines.is_empty() {
+            error!(
+                "{} lines are not formatted, run fmt without --check to fix",
+                formatted.changed_lines.len()
+            );
+            exit(1);
+        }
+        return;
+    }
+    if formatted.text == input {
+        info!("Journal is already formatted");
+        return;
+    }
+    fs::write(journal_path, formatted.text).expect("Journal file should be writable");
+    info!(
+        "Formatted journal, {} lines changed",
+        formatted.changed_lines.len()
+    );
+}
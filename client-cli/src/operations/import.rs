@@ -0,0 +1,163 @@
+use std::{fs::File, path::Path, process::exit};
+
+use clap::{Parser, ValueEnum};
+use qqself_core::{
+    date_time::datetime::DateDay, db::Record, ical::import_ics, import::TagMapping,
+    parsing::journal::write_journal, record::Entry,
+};
+use tracing::{error, info};
+
+use crate::{
+    importers::{
+        atracker::ATrackerImporter, generic::GenericImporter, import as import_csv,
+        toggl::TogglImporter, Importer,
+    },
+    key_file::KeyFile,
+    operations::upload::upload_entries,
+};
+
+#[derive(Debug, Clone, ValueEnum)]
+enum ImportFormat {
+    /// CSV with columns set by --*-column options
+    Csv,
+    /// Toggl Track detailed report CSV export
+    Toggl,
+    /// ATracker CSV export
+    Atracker,
+    /// iCalendar file, recurring events are expanded between --from and --to days
+    Ics,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Import entries from CSV exports of other time trackers or iCalendar files")]
+pub struct ImportOpts {
+    /// Path to the file to import
+    #[arg(short, long)]
+    input: String,
+
+    /// Format of the imported file
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: ImportFormat,
+
+    /// Mapping of project or tag names to qqself tags, e.g. `Client work=work`. Could be repeated. Unmapped names
+    /// are converted to lowercase tags
+    #[arg(short, long = "map")]
+    mappings: Vec<String>,
+
+    /// Start date time column for csv format
+    #[arg(long, default_value = "start")]
+    start_column: String,
+
+    /// End date time column for csv format
+    #[arg(long, default_value = "end")]
+    end_column: String,
+
+    /// Column with comma separated tags for csv format
+    #[arg(long, default_value = "tags")]
+    tags_column: String,
+
+    /// Comment column for csv format
+    #[arg(long, default_value = "comment")]
+    comment_column: String,
+
+    /// First day of recurring events for ics format, one year ago by default
+    #[arg(long)]
+    from: Option<DateDay>,
+
+    /// Last day of recurring events for ics format, today by default
+    #[arg(long)]
+    to: Option<DateDay>,
+
+    /// Path to journal file to create with imported entries, entries are printed if not set
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Upload imported entries to the server
+    #[arg(short, long)]
+    upload: bool,
+
+    /// Path to key file, used for uploading
+    #[arg(short, long, default_value = "qqself_keys.txt")]
+    keys_path: String,
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn import(opts: ImportOpts) {
+    let mapping = TagMapping::new(&opts.mappings).unwrap_or_else(|err| {
+        error!("{err}");
+        exit(1);
+    });
+    let entries = match opts.format {
+        ImportFormat::Ics => {
+            let input =
+                std::fs::read_to_string(&opts.input).expect("Import file should be readable");
+            let to = opts.to.unwrap_or_else(DateDay::today);
+            let from = opts.from.unwrap_or_else(|| to.remove_days(365));
+            import_ics(&input, &mapping, from, to)
+                .map_err(|errors| errors.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+        }
+        _ => import_csv_file(&opts, &mapping),
+    };
+    let entries = match entries {
+        Ok(v) => v,
+        Err(errors) => {
+            for err in &errors {
+                println!("{}: {}", opts.input, err);
+            }
+            error!("Import failed, {} entries have errors", errors.len());
+            exit(1);
+        }
+    };
+    save_entries(
+        entries,
+        opts.output.as_deref(),
+        opts.upload,
+        &opts.keys_path,
+    );
+}
+
+/// Writes imported entries as a new journal or prints those, optionally uploads entries to the server
+pub(crate) fn save_entries(
+    entries: Vec<Entry>,
+    output: Option<&str>,
+    upload: bool,
+    keys_path: &str,
+) {
+    let mut records: Vec<_> = entries.into_iter().map(Record::Entry).collect();
+    records.sort();
+    let journal = write_journal(&records);
+    match output {
+        Some(path) => {
+            if Path::new(path).exists() {
+                error!("Journal file already exists at {:?}", path);
+                exit(1);
+            }
+            std::fs::write(path, &journal).expect("Journal file should be writable");
+            info!("Imported {} entries to {:?}", records.len(), path);
+        }
+        None => print!("{journal}"),
+    }
+    if upload {
+        info!("Uploading. Reading key file at {:?}", keys_path);
+        let keys = KeyFile::load_from_file(Path::new(keys_path));
+        let entries = records.into_iter().map(|v| v.to_string(true, true));
+        upload_entries(entries, &keys.cryptor());
+        info!("Uploading finished")
+    }
+}
+
+fn import_csv_file(opts: &ImportOpts, mapping: &TagMapping) -> Result<Vec<Entry>, Vec<String>> {
+    let importer: Box<dyn Importer> = match opts.format {
+        ImportFormat::Csv => Box::new(GenericImporter {
+            start_column: opts.start_column.clone(),
+            end_column: opts.end_column.clone(),
+            tags_column: opts.tags_column.clone(),
+            comment_column: opts.comment_column.clone(),
+        }),
+        ImportFormat::Toggl => Box::new(TogglImporter),
+        ImportFormat::Atracker => Box::new(ATrackerImporter),
+        ImportFormat::Ics => unreachable!("iCalendar isn't a CSV format"),
+    };
+    let input = File::open(&opts.input).expect("Import file should be readable");
+    import_csv(input, importer.as_ref(), mapping)
+}
@@ -1,5 +1,11 @@
+pub mod check;
 pub mod delete;
 pub mod download;
+pub mod export;
+pub mod fmt;
+pub mod import;
 pub mod init;
+pub mod rename;
 pub mod report;
 pub mod upload;
+pub mod workout;
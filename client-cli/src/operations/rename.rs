@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use clap::Parser;
+use qqself_core::{date_time::datetime::DateDay, rename::RenameRule};
+use tracing::info;
+
+use crate::{journal::load_db, key_file::KeyFile, operations::upload::upload_entries};
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Rename tags or tag properties in all the entries and upload new revisions to the server"
+)]
+pub struct RenameOpts {
+    /// Rename rule in format `jog=run` for tags or `run.dist=distance` for properties. Could be repeated
+    #[arg(short, long = "rule", required = true)]
+    rules: Vec<RenameRule>,
+
+    /// Path to journal file with all the entries
+    #[arg(short, long, default_value = "journal.txt")]
+    journal_path: String,
+
+    /// Path to key file
+    #[arg(short, long, default_value = "qqself_keys.txt")]
+    keys_path: String,
+
+    /// Id of this device, new revisions are versioned with it to detect concurrent edits from other devices
+    #[arg(long, default_value = "cli")]
+    device: String,
+
+    /// Only show what would be renamed without uploading anything
+    #[arg(short, long)]
+    dry_run: bool,
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn rename(opts: RenameOpts) {
+    let mut db = load_db(Path::new(&opts.journal_path));
+    db.set_device(&opts.device);
+    let renamed = db.rename(&opts.rules, opts.dry_run, Some(DateDay::today()));
+    for v in &renamed {
+        println!(
+            "- {}\n+ {}",
+            v.from.to_string(true, true),
+            v.to.to_string(true, true)
+        );
+    }
+    if opts.dry_run || renamed.is_empty() {
+        info!("{} entries would be renamed", renamed.len());
+        return;
+    }
+    info!("Renaming. Reading key file at {:?}", opts.keys_path);
+    let keys = KeyFile::load_from_file(Path::new(&opts.keys_path));
+    let entries = renamed.into_iter().map(|v| v.to.to_string(true, true));
+    upload_entries(entries, &keys.cryptor());
+    info!("Renaming finished, download the journal to get the updated entries");
+}
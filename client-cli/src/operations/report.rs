@@ -1,16 +1,9 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    path::Path,
-    process::exit,
-};
+use std::path::Path;
 
 use clap::{Parser, ValueEnum};
-use qqself_core::{
-    date_time::datetime::DateDay,
-    db::{Query, Record, DB},
-};
-use tracing::error;
+use qqself_core::{date_time::datetime::DateDay, db::Query};
+
+use crate::journal::load_db;
 
 #[derive(Debug, Clone, ValueEnum)]
 enum TimePeriod {
@@ -34,27 +27,7 @@ pub struct ReportOpts {
 
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn report(opts: ReportOpts) {
-    let journal_path = Path::new(&opts.journal_path);
-    if !journal_path.exists() {
-        error!("Journal file does not exists at {:?}", journal_path);
-        exit(1);
-    }
-    let file = File::open(journal_path).expect("Journal path should point to the openable file");
-    let reader = BufReader::new(file);
-    let mut db = DB::new();
-    reader.lines().for_each(|line| {
-        let line = line.expect("Cannot read journal line");
-        if line.trim().starts_with('#') {
-            return; // Skip the comments
-        }
-        if line.trim().is_empty() {
-            return; // Skip empty lines
-        }
-        // Parse the record to see if it's a valid one
-        let record =
-            Record::parse(&line).unwrap_or_else(|_| panic!("entry should be valid, line='{line}'"));
-        db.add(record, false, None);
-    });
+    let mut db = load_db(Path::new(&opts.journal_path));
     println!("Skills:");
     db.skills().iter().for_each(|(_, skill)| {
         println!("{}", skill);
@@ -69,7 +42,7 @@ pub fn report(opts: ReportOpts) {
 
     let mut prev_day = None;
     for entry in db.query_results().iter() {
-        if !prev_day.is_some_and(|v| v == entry.date_range().start().date()) {
+        if prev_day != Some(entry.date_range().start().date()) {
             prev_day.replace(entry.date_range().start().date());
             println!("Day {}", entry.date_range().start().date());
         }
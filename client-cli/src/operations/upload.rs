@@ -10,6 +10,7 @@ use clap::Parser;
 use qqself_core::{
     api::{ApiRequests, Request},
     encryption::cryptor::Cryptor,
+    parsing::journal::JournalReader,
     record::Entry,
 };
 use rayon::prelude::{ParallelBridge, ParallelIterator};
@@ -53,31 +54,37 @@ pub fn upload(opts: UploadOpts) {
 fn upload_journal(journal_path: &Path, cryptor: Cryptor) {
     let file = File::open(journal_path).expect("Cannot open journal file");
     let reader = BufReader::new(file);
+
+    // Day headers apply to the following lines, so journal is read sequentially before parallel processing
+    let mut journal = JournalReader::default();
+    let entries = reader.lines().filter_map(move |line| {
+        let line = line.expect("Cannot read journal line");
+        journal
+            .read_line(&line)
+            .unwrap_or_else(|err| panic!("Error {} reading line: {}", err, &line))
+    });
+    upload_entries(entries, &cryptor);
+}
+
+/// Encrypts and uploads entries to the server, each entry is validated before the upload
+pub(crate) fn upload_entries(entries: impl Iterator<Item = String> + Send, cryptor: &Cryptor) {
     let (sending_runtime, send_channels) = start_sender();
     let api = ApiRequests::default();
 
-    // Process all the lines in parallel using Rayon and distribute encrypted values across sending channels
-    reader
-        .lines()
-        .enumerate()
-        .par_bridge()
-        .for_each(|(idx, line)| {
-            let line = line.expect("Cannot read journal line");
-            if line.trim().starts_with('#') {
-                return; // Skip the comments
-            }
-            if line.trim().is_empty() {
-                return; // Skip empty lines
-            }
-            // Parse the record to see if it's a valid one
-            if let Err(err) = Entry::parse(&line) {
-                panic!("Error {} parsing line: {}", err, &line);
-            }
-            let payload = cryptor.encrypt(&line).expect("Failure to encrypt");
-            let req = api.create_set_request(payload);
-            let tx = &send_channels[idx % send_channels.len()];
-            tx.blocking_send(req).unwrap();
-        });
+    // Process all the entries in parallel using Rayon and distribute encrypted values across sending channels
+    entries.enumerate().par_bridge().for_each(|(idx, entry)| {
+        // Parse the record to see if it's a valid one
+        if let Err(err) = Entry::parse(&entry) {
+            panic!(
+                "Error parsing entry:\n{}",
+                err.diagnostic(&entry, true).render(&entry)
+            );
+        }
+        let payload = cryptor.encrypt(&entry).expect("Failure to encrypt");
+        let req = api.create_set_request(payload);
+        let tx = &send_channels[idx % send_channels.len()];
+        tx.blocking_send(req).unwrap();
+    });
 
     // Done with sending, inform receivers that we are done and wait until Tokio has finished the processing
     drop(send_channels);
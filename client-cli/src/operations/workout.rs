@@ -0,0 +1,96 @@
+use std::{path::Path, process::exit};
+
+use clap::Parser;
+use qqself_core::db::Record;
+use tracing::{error, info};
+
+use crate::{
+    journal::load_db,
+    local_time::parse_utc_offset,
+    operations::import::save_entries,
+    workouts::{read_workout, sport_mapping},
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Import workouts from GPX and FIT files of sport watches, skipping already added ones"
+)]
+pub struct WorkoutOpts {
+    /// Paths to .gpx or .fit files
+    #[arg(required = true)]
+    files: Vec<String>,
+
+    /// Path to journal file with existing entries, workouts overlapping with those are skipped
+    #[arg(short, long, default_value = "journal.txt")]
+    journal_path: String,
+
+    /// Mapping of sport names to qqself tags, e.g. `cycling=bike`. Could be repeated. Running is mapped to `run`,
+    /// cycling to `ride`, swimming to `swim`, walking to `walk`, hiking to `hike` by default
+    #[arg(short, long = "map")]
+    mappings: Vec<String>,
+
+    /// Offset of local time zone from UTC in format +HH:MM, workout files use UTC time
+    #[arg(long, default_value = "+00:00", allow_hyphen_values = true)]
+    utc_offset: String,
+
+    /// Path to journal file to create with imported entries, entries are printed if not set
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Upload imported entries to the server
+    #[arg(short, long)]
+    upload: bool,
+
+    /// Path to key file, used for uploading
+    #[arg(short, long, default_value = "qqself_keys.txt")]
+    keys_path: String,
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn workout(opts: WorkoutOpts) {
+    let mapping = sport_mapping(&opts.mappings).unwrap_or_else(|err| {
+        error!("{err}");
+        exit(1);
+    });
+    let Some(utc_offset) = parse_utc_offset(&opts.utc_offset) else {
+        error!(
+            "UTC offset has to be in format +HH:MM, got {}",
+            opts.utc_offset
+        );
+        exit(1);
+    };
+    let mut db = load_db(Path::new(&opts.journal_path));
+    let mut entries = Vec::new();
+    let mut failed = 0;
+    for file in &opts.files {
+        let entry = read_workout(Path::new(file)).and_then(|v| v.to_entry(&mapping, utc_offset));
+        let entry = match entry {
+            Ok(v) => v,
+            Err(err) => {
+                println!("{file}: {err}");
+                failed += 1;
+                continue;
+            }
+        };
+        if let Some(existing) = db.overlapping(entry.date_range()).first() {
+            info!(
+                "Skipping {file}, overlaps with existing entry {}",
+                existing.to_string(true, false)
+            );
+            continue;
+        }
+        // Same workout could be in several files, e.g. both GPX and FIT, so imported ones are checked as well
+        db.add(Record::Entry(entry.clone()), false, None);
+        entries.push(entry);
+    }
+    if failed > 0 {
+        error!("Import failed, {failed} files have errors");
+        exit(1);
+    }
+    save_entries(
+        entries,
+        opts.output.as_deref(),
+        opts.upload,
+        &opts.keys_path,
+    );
+}
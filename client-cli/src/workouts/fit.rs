@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use qqself_core::date_time::timestamp::Timestamp;
+
+use super::Workout;
+
+/*
+FIT is a binary format of Garmin and most of other sport watches. File is a sequence of messages, every data
+message is preceded by the definition message which describes its fields. Only a few messages are needed:
+- Record (20) has timestamp (253) and distance (5) of every track point
+- Session (18) and Sport (12) have the sport type (5 and 0)
+See FIT protocol description at https://developer.garmin.com/fit/protocol
+*/
+
+/// FIT timestamps are seconds since 1989-12-31 00:00 UTC
+const FIT_EPOCH: u64 = 631_065_600;
+
+const MESSAGE_SPORT: u16 = 12;
+const MESSAGE_SESSION: u16 = 18;
+const MESSAGE_RECORD: u16 = 20;
+
+const FIELD_TIMESTAMP: u8 = 253;
+const FIELD_RECORD_DISTANCE: u8 = 5;
+const FIELD_SESSION_SPORT: u8 = 5;
+const FIELD_SPORT_SPORT: u8 = 0;
+
+/// Definition of the data message fields as (field number, size)
+struct Definition {
+    message: u16,
+    big_endian: bool,
+    fields: Vec<(u8, usize)>,
+    developer_size: usize,
+}
+
+/// Reads workout summary from FIT file. Times come from the first and the last record, distance from the last one
+pub fn read(data: &[u8]) -> Result<Workout, String> {
+    let header_size = *data.first().ok_or("Empty file")? as usize;
+    if data.len() < header_size || header_size < 12 || &data[8..12] != b".FIT" {
+        return Err("Not a FIT file".to_string());
+    }
+    let data_size = u32::from_le_bytes(data[4..8].try_into().expect("4 bytes")) as usize;
+    let data = data
+        .get(header_size..header_size + data_size)
+        .ok_or("FIT file is truncated")?;
+
+    let mut definitions: HashMap<u8, Definition> = HashMap::new();
+    let mut pos = 0;
+    let mut last_timestamp = 0;
+    let mut start: Option<u32> = None;
+    let mut end: Option<u32> = None;
+    let mut distance: Option<u32> = None;
+    let mut sport = None;
+    let truncated = || "FIT file is truncated".to_string();
+    while pos < data.len() {
+        let header = data[pos];
+        pos += 1;
+        if header & 0x80 == 0 && header & 0x40 != 0 {
+            // Definition message
+            let bytes = data.get(pos..pos + 5).ok_or_else(truncated)?;
+            let big_endian = bytes[1] == 1;
+            let message = if big_endian {
+                u16::from_be_bytes([bytes[2], bytes[3]])
+            } else {
+                u16::from_le_bytes([bytes[2], bytes[3]])
+            };
+            let count = bytes[4] as usize;
+            pos += 5;
+            let fields = data.get(pos..pos + count * 3).ok_or_else(truncated)?;
+            let fields = fields.chunks(3).map(|v| (v[0], v[1] as usize)).collect();
+            pos += count * 3;
+            let mut developer_size = 0;
+            if header & 0x20 != 0 {
+                let count = *data.get(pos).ok_or_else(truncated)? as usize;
+                let fields = data
+                    .get(pos + 1..pos + 1 + count * 3)
+                    .ok_or_else(truncated)?;
+                developer_size = fields.chunks(3).map(|v| v[1] as usize).sum();
+                pos += 1 + count * 3;
+            }
+            let definition = Definition {
+                message,
+                big_endian,
+                fields,
+                developer_size,
+            };
+            definitions.insert(header & 0x0F, definition);
+            continue;
+        }
+
+        // Data message, compressed header has local message type in bits 5-6 and time offset in bits 0-4
+        let (local, time_offset) = if header & 0x80 != 0 {
+            ((header >> 5) & 0x03, Some((header & 0x1F) as u32))
+        } else {
+            (header & 0x0F, None)
+        };
+        let definition = definitions
+            .get(&local)
+            .ok_or_else(|| format!("Data message without definition at byte {pos}"))?;
+        let mut timestamp = time_offset.map(|offset| {
+            let mut timestamp = (last_timestamp & !0x1F) + offset;
+            if offset < last_timestamp & 0x1F {
+                timestamp += 0x20; // Offset rolled over
+            }
+            timestamp
+        });
+        for (field, size) in &definition.fields {
+            let bytes = data.get(pos..pos + size).ok_or_else(truncated)?;
+            pos += size;
+            let Some(value) = read_value(bytes, definition.big_endian) else {
+                continue; // Invalid values mean field isn't set
+            };
+            match (definition.message, *field) {
+                (_, FIELD_TIMESTAMP) => timestamp = Some(value),
+                (MESSAGE_RECORD, FIELD_RECORD_DISTANCE) => distance = Some(value),
+                (MESSAGE_SESSION, FIELD_SESSION_SPORT) | (MESSAGE_SPORT, FIELD_SPORT_SPORT) => {
+                    sport = sport_name(value)
+                }
+                _ => {}
+            }
+        }
+        pos += definition.developer_size;
+        if let Some(timestamp) = timestamp {
+            last_timestamp = timestamp;
+            if definition.message == MESSAGE_RECORD {
+                start = Some(start.map_or(timestamp, |v| v.min(timestamp)));
+                end = Some(end.map_or(timestamp, |v| v.max(timestamp)));
+            }
+        }
+    }
+    let (Some(start), Some(end)) = (start, end) else {
+        return Err("No records with timestamp".to_string());
+    };
+    let timestamp = |v: u32| Timestamp::from_u64((v as u64 + FIT_EPOCH) * 1000);
+    Ok(Workout {
+        start: timestamp(start),
+        end: timestamp(end),
+        sport: sport.map(|v| v.to_string()),
+        distance_meters: distance.map(|v| v as f64 / 100.0), // Distance is in centimeters
+    })
+}
+
+/// Reads unsigned integer field, None if field has invalid value which means it's not set
+fn read_value(bytes: &[u8], big_endian: bool) -> Option<u32> {
+    let (value, invalid) = match bytes.len() {
+        1 => (bytes[0] as u32, u8::MAX as u32),
+        2 => {
+            let bytes = [bytes[0], bytes[1]];
+            let value = if big_endian {
+                u16::from_be_bytes(bytes)
+            } else {
+                u16::from_le_bytes(bytes)
+            };
+            (value as u32, u16::MAX as u32)
+        }
+        4 => {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            let value = if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            };
+            (value, u32::MAX)
+        }
+        _ => return None, // Strings and arrays aren't needed
+    };
+    (value != invalid).then_some(value)
+}
+
+fn sport_name(sport: u32) -> Option<&'static str> {
+    match sport {
+        1 => Some("running"),
+        2 => Some("cycling"),
+        5 => Some("swimming"),
+        11 => Some("walking"),
+        15 => Some("rowing"),
+        17 => Some("hiking"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds FIT file with given messages, CRC isn't checked so it's left empty
+    fn fit_file(messages: &[Vec<u8>]) -> Vec<u8> {
+        let data: Vec<u8> = messages.concat();
+        let mut file = vec![14, 0x20, 0, 0];
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(b".FIT");
+        file.extend([0, 0]);
+        file.extend(data);
+        file.extend([0, 0]);
+        file
+    }
+
+    #[test]
+    fn fit() {
+        // 2023-07-03 08:00:00 UTC
+        let start = (1688371200 - FIT_EPOCH as u32).to_le_bytes();
+        let end = (1688371200 + 3120 - FIT_EPOCH as u32).to_le_bytes();
+        let file = fit_file(&[
+            // Session definition with sport and developer field, local type 0
+            vec![0x60, 0, 0, 18, 0, 1, 5, 1, 0, 1, 7, 2, 0],
+            vec![0x00, 1, 0xAA, 0xBB],
+            // Record definition with timestamp and distance, local type 1
+            vec![0x41, 0, 0, 20, 0, 2, 253, 4, 0x86, 5, 4, 0x86],
+            [vec![0x01], start.to_vec(), 0u32.to_le_bytes().to_vec()].concat(),
+            [
+                vec![0x01],
+                end.to_vec(),
+                1_020_000u32.to_le_bytes().to_vec(),
+            ]
+            .concat(),
+            // Record definition in big endian without timestamp, local type 2
+            vec![0x42, 0, 1, 0, 20, 1, 5, 4, 0x86],
+            // Compressed timestamp with offset 5 which rolls over from 16, distance not set
+            [vec![0x80 | 0x40 | 5], u32::MAX.to_be_bytes().to_vec()].concat(),
+        ]);
+        let workout = read(&file).unwrap();
+        assert_eq!(
+            workout,
+            Workout {
+                start: Timestamp::from_u64(1688371200000),
+                end: Timestamp::from_u64((1688371200 + 3141) * 1000),
+                sport: Some("running".to_string()),
+                distance_meters: Some(10200.0),
+            }
+        );
+        assert!(read(b"not a fit file").is_err());
+    }
+}
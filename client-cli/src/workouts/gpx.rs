@@ -0,0 +1,164 @@
+use qqself_core::date_time::timestamp::Timestamp;
+
+use super::Workout;
+
+/*
+GPX is XML with track points, every point has coordinates and time:
+<trk><type>running</type><trkseg><trkpt lat="52.5" lon="13.4"><time>2023-07-03T08:00:00Z</time></trkpt></trkseg></trk>
+Only these elements are needed, so instead of full XML parser elements are just searched in the text
+*/
+
+/// Mean Earth radius in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Reads workout summary from GPX, distance is a sum of distances between track points
+pub fn read(data: &str) -> Result<Workout, String> {
+    let mut start: Option<Timestamp> = None;
+    let mut end: Option<Timestamp> = None;
+    let mut distance = 0.0;
+    let mut prev: Option<(f64, f64)> = None;
+    let mut rest = data;
+    while let Some(pos) = rest.find("<trkpt") {
+        rest = &rest[pos..];
+        let tag_end = rest.find('>').ok_or("Track point is not closed")?;
+        let close = if rest[..tag_end].ends_with('/') {
+            tag_end // Point without children, e.g. without time
+        } else {
+            rest.find("</trkpt>").ok_or("Track point is not closed")?
+        };
+        let point = &rest[..close];
+        rest = &rest[close..];
+
+        let coordinate = |name: &str| -> Result<f64, String> {
+            attribute(point, name)
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("Track point without {name}"))
+        };
+        let coordinates = (coordinate("lat")?, coordinate("lon")?);
+        if let Some(prev) = prev {
+            distance += haversine(prev, coordinates);
+        }
+        prev = Some(coordinates);
+
+        if let Some(time) = element(point, "time") {
+            let time =
+                parse_timestamp(time).ok_or_else(|| format!("Bad track point time {time}"))?;
+            start = Some(start.map_or(time, |v| v.min(time)));
+            end = Some(end.map_or(time, |v| v.max(time)));
+        }
+    }
+    let (Some(start), Some(end)) = (start, end) else {
+        return Err("No track points with time".to_string());
+    };
+    Ok(Workout {
+        start,
+        end,
+        sport: element(data, "type").map(|v| v.trim().to_lowercase()),
+        distance_meters: Some(distance),
+    })
+}
+
+/// Value of the attribute in the element text, both quote styles are supported
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    ['"', '\''].iter().find_map(|quote| {
+        let prefix = format!(" {name}={quote}");
+        let start = element.find(&prefix)? + prefix.len();
+        let len = element[start..].find(*quote)?;
+        Some(&element[start..start + len])
+    })
+}
+
+/// Text of the first element with the name
+fn element<'a>(data: &'a str, name: &str) -> Option<&'a str> {
+    let start = data.find(&format!("<{name}>"))? + name.len() + 2;
+    let len = data[start..].find(&format!("</{name}>"))?;
+    Some(&data[start..start + len])
+}
+
+/// Distance in meters between two coordinates in degrees
+fn haversine((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Parses ISO 8601 time like `2023-07-03T08:00:00Z`, `2023-07-03T08:00:00.250Z` or `2023-07-03T10:00:00+02:00`
+pub fn parse_timestamp(s: &str) -> Option<Timestamp> {
+    let s = s.trim();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> { s.get(range)?.parse().ok() };
+    if s.get(10..11)? != "T" {
+        return None;
+    }
+    let days = days_from_civil(number(0..4)?, number(5..7)?, number(8..10)?);
+    let mut seconds =
+        days * 86400 + number(11..13)? * 3600 + number(14..16)? * 60 + number(17..19)?;
+    // Fractional seconds are skipped, then offset follows
+    let zone = s[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    match zone {
+        "" | "Z" => {}
+        _ => {
+            let sign = match zone.get(0..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let hours: i64 = zone.get(1..3)?.parse().ok()?;
+            let minutes: i64 = zone.get(4..6)?.parse().ok()?;
+            seconds -= sign * (hours * 3600 + minutes * 60);
+        }
+    }
+    Some(Timestamp::from_u64(u64::try_from(seconds).ok()? * 1000))
+}
+
+/// Number of days since 1970-01-01 for the date in proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpx() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="Watch" version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+ <metadata><time>2023-07-03T07:59:00Z</time></metadata>
+ <trk>
+  <name>Morning Run</name>
+  <type>running</type>
+  <trkseg>
+   <trkpt lat="52.5000" lon="13.4000"><ele>34</ele><time>2023-07-03T08:00:00Z</time></trkpt>
+   <trkpt lat="52.5090" lon="13.4000"><ele>35</ele><time>2023-07-03T08:05:12.500Z</time></trkpt>
+   <trkpt lat='52.5180' lon='13.4000'><time>2023-07-03T10:10:24+02:00</time></trkpt>
+  </trkseg>
+ </trk>
+</gpx>"#;
+        let workout = read(data).unwrap();
+        assert_eq!(workout.sport.as_deref(), Some("running"));
+        assert_eq!(
+            workout.start,
+            parse_timestamp("2023-07-03T08:00:00Z").unwrap()
+        );
+        assert_eq!(
+            workout.end,
+            parse_timestamp("2023-07-03T08:10:24Z").unwrap()
+        );
+        let distance = workout.distance_meters.unwrap();
+        assert!((distance - 2001.5).abs() < 1.0, "distance {distance}");
+
+        assert_eq!(
+            parse_timestamp("2023-07-03T08:00:59Z"),
+            Some(Timestamp::from_u64(1688371259000))
+        );
+        assert_eq!(parse_timestamp("2023-07-03 08:00:59"), None);
+        assert!(read("<gpx></gpx>").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use qqself_core::{
+    date_time::{
+        datetime::{DateTime, DateTimeRange},
+        timestamp::Timestamp,
+    },
+    import::TagMapping,
+    record::Entry,
+};
+
+pub mod fit;
+pub mod gpx;
+
+/*
+Workouts are activity files recorded by sport watches and apps. Files have every track point, but entries need
+only the summary: when it happened, distance and pace, e.g. `2023-07-03 08:00 08:52 run distance=10.2km pace=5:12`
+*/
+
+/// Sport names of workout files mapped to tags, could be overridden by user mapping
+const SPORT_TAGS: [&str; 6] = [
+    "running=run",
+    "cycling=ride",
+    "swimming=swim",
+    "walking=walk",
+    "hiking=hike",
+    "rowing=row",
+];
+
+/// Tag for workouts without a sport
+const DEFAULT_SPORT: &str = "workout";
+
+/// Summary of the workout file
+#[derive(Debug, PartialEq)]
+pub struct Workout {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// Lowercase sport name, e.g. `running`
+    pub sport: Option<String>,
+    pub distance_meters: Option<f64>,
+}
+
+impl Workout {
+    /// Converts to the entry with sport tag, distance in kilometers and pace in minutes per kilometer. Workout
+    /// files use UTC, so times are shifted by the offset to get the local time
+    pub fn to_entry(&self, mapping: &TagMapping, utc_offset_minutes: i32) -> Result<Entry, String> {
+        let tag = mapping.map(self.sport.as_deref().unwrap_or(DEFAULT_SPORT))?;
+        let start = DateTime::from_timestamp(self.start, utc_offset_minutes);
+        let end = DateTime::from_timestamp(self.end, utc_offset_minutes);
+        let date_range = DateTimeRange::new(start, end)?;
+        let mut text = format!("{date_range} {tag}");
+        let km = self.distance_meters.unwrap_or_default() / 1000.0;
+        if km >= 0.05 {
+            text.push_str(&format!(" distance={km:.1}km"));
+            let seconds = (self.end.as_u64() - self.start.as_u64()) as f64 / 1000.0;
+            let pace = (seconds / km).round() as u64;
+            if pace > 0 {
+                text.push_str(&format!(" pace={}:{:02}", pace / 60, pace % 60));
+            }
+        }
+        Entry::parse(&text).map_err(|err| err.diagnostic(&text, true).render(&text))
+    }
+}
+
+/// Tag mapping with sport defaults, user rules take precedence
+pub fn sport_mapping(rules: &[String]) -> Result<TagMapping, String> {
+    let mut all: Vec<_> = SPORT_TAGS.iter().map(|v| v.to_string()).collect();
+    all.extend_from_slice(rules);
+    TagMapping::new(&all)
+}
+
+/// Reads workout file, format is chosen by the extension
+pub fn read_workout(path: &Path) -> Result<Workout, String> {
+    let extension = path
+        .extension()
+        .map(|v| v.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "gpx" => {
+            let data = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+            gpx::read(&data)
+        }
+        "fit" => {
+            let data = std::fs::read(path).map_err(|err| err.to_string())?;
+            fit::read(&data)
+        }
+        _ => Err("Unsupported file, expected .gpx or .fit".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry() {
+        let mapping = sport_mapping(&["cycling=bike".to_string()]).unwrap();
+        let workout = |sport: Option<&str>, minutes: u64, meters: Option<f64>| Workout {
+            start: Timestamp::from_u64(1688371200000), // 2023-07-03 08:00 UTC
+            end: Timestamp::from_u64(1688371200000 + minutes * 60 * 1000),
+            sport: sport.map(|v| v.to_string()),
+            distance_meters: meters,
+        };
+        let cases = vec![
+            (
+                workout(Some("running"), 53, Some(10_200.0)),
+                "2023-07-03 10:00 10:53 run distance=10.2km pace=05:12",
+            ),
+            (
+                workout(Some("cycling"), 60, Some(30_000.0)),
+                "2023-07-03 10:00 11:00 bike distance=30km pace=02:00",
+            ),
+            (
+                workout(None, 15 * 60, None),
+                "2023-07-03 10:00 - 2023-07-04 01:00 workout",
+            ),
+        ];
+        for (workout, want) in cases {
+            let got = workout.to_entry(&mapping, 120).unwrap();
+            assert_eq!(got.serialize(true, true), want);
+        }
+    }
+}
@@ -6,6 +6,7 @@ import { Store, ViewNotification, ViewUpdate } from "./store"
 
 export const loginSucceeded = async (store: Store, cryptor: Cryptor): Promise<void> => {
   await saveCredentials(cryptor)
+  const device = await getDevice()
 
   const onViewUpdate = (data: Map<string, string>) => {
     const update = Object.fromEntries(data) as unknown as ViewUpdate
@@ -37,7 +38,9 @@ export const loginSucceeded = async (store: Store, cryptor: Cryptor): Promise<vo
       (data: Map<string, string>) => setTimeout(() => onViewNotification(data), 0),
     ),
     dataEvents: new DataEvents(store, store.api),
+    device,
   }
+  store.userState.views.set_device(device)
 }
 
 export const login = (store: Store, serializedKeys: string): Promise<void> => {
@@ -93,6 +96,19 @@ export const saveCredentials = async (cryptor: Cryptor): Promise<void> => {
   return storage.setItem(STORAGE_KEYS_KEY, cryptor.serialize_keys())
 }
 
+// Id of this device, edits made here are versioned with it to detect concurrent edits from other devices
+const STORAGE_DEVICE_KEY = "device"
+export const getDevice = async (): Promise<string> => {
+  const storage = Storage.newDefaultStorage()
+  const cachedDevice = await storage.getItem(STORAGE_DEVICE_KEY)
+  if (cachedDevice) {
+    return cachedDevice
+  }
+  const device = `web-${crypto.randomUUID().slice(0, 8)}`
+  await storage.setItem(STORAGE_DEVICE_KEY, device)
+  return device
+}
+
 export const deleteCredentials = async (): Promise<void> => {
   const storage = Storage.newDefaultStorage()
   return storage.removeItem(STORAGE_KEYS_KEY)
@@ -35,7 +35,13 @@ export class DataEvents {
     let loadedLocal = 0
     for (const entry of await storage.values(KeyPrefixes.EntryRemote)) {
       const record = UiRecord.parse(entry.value)
-      this.store.userState.views.add_record(record, false, DateDay.fromDate(new Date()))
+      const payloadId = entry.key.slice(KeyPrefixes.EntryRemote.length)
+      this.store.userState.views.add_synced_record(
+        record,
+        payloadId,
+        false,
+        DateDay.fromDate(new Date()),
+      )
       loadedRemote++
     }
     for (const entry of await storage.values(KeyPrefixes.EntryLocal)) {
@@ -138,7 +144,12 @@ export class DataEvents {
     const decrypted = await this.store.userState.encryptionPool.decryptAll(remoteEntries)
     for (const entry of decrypted) {
       const record = UiRecord.parse(entry.text)
-      this.store.userState.views.add_record(record, false, DateDay.fromDate(new Date()))
+      this.store.userState.views.add_synced_record(
+        record,
+        entry.id,
+        false,
+        DateDay.fromDate(new Date()),
+      )
       await this.addEntryToCache(entry.text, entry.id)
     }
     if (decrypted.length > 1) {
@@ -17,10 +17,21 @@ export interface SkillsViewUpdate {
 export interface WeekUpdate {
   view: "Week"
 }
-export type ViewUpdate = QueryResultsUpdate | SkillsViewUpdate | WeekUpdate
+export interface MetricsUpdate {
+  view: "Metrics"
+  tag: string
+  prop: string
+}
+export type ViewUpdate = QueryResultsUpdate | SkillsViewUpdate | WeekUpdate | MetricsUpdate
 export interface SkillsViewNotification {
   view: "Skills"
-  message: string
+  message: string // Default English text of the notification
+  kind: "LevelUp" | "HourProgress"
+  skill?: string // Missing for HourProgress across all the skills
+  levelBefore?: number
+  level?: number
+  period?: "lifetime" | "year" | "month" | "week"
+  hours?: number
 }
 export type ViewNotification = SkillsViewNotification
 
@@ -73,6 +84,7 @@ export class Store {
     storage: Storage
     views: Views
     dataEvents: DataEvents
+    device: string
   }
 
   async dispatch<T extends keyof Events>(event: T, eventArgs: Events[T]): Promise<void> {
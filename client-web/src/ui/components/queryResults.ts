@@ -25,6 +25,9 @@ export class QueryResults extends LitElement {
   @property()
   query?: string
 
+  @property({ type: String })
+  device = ""
+
   @query(".query")
   queryElement!: HTMLInputElement
 
@@ -110,7 +113,7 @@ export class QueryResults extends LitElement {
 
   onQueryUpdated(sender: InputEvent) {
     this.query = (sender.target as HTMLInputElement).value
-    this.queryValidationError = validateQuery(this.query)
+    this.queryValidationError = validateQuery(this.query)?.message
     if (!this.queryValidationError) {
       const event: QueryUpdatedEvent = new CustomEvent("queryUpdated", {
         detail: {
@@ -176,7 +179,7 @@ export class QueryResults extends LitElement {
       <div class="results">
         ${Object.entries(this.data ?? {}).map(([, entry]) => this.renderDay(entry))}
       </div>
-      <q-record-input class="newEntry" .initialRecord=${this.currentRecord} .input=${
+      <q-record-input class="newEntry" .initialRecord=${this.currentRecord} .device=${this.device} .input=${
         this.currentRecordString
       } @update=${this.onEntryUpdated.bind(this)}  @save=${this.onSave.bind(this)}></q-record-input>
     </div>`
@@ -19,6 +19,9 @@ export class RecordInput extends LitElement {
   @property({ type: Object })
   initialRecord?: UiRecord
 
+  @property({ type: String })
+  device = ""
+
   @property({ type: String })
   input = ""
 
@@ -37,8 +40,12 @@ export class RecordInput extends LitElement {
   `
 
   onSave() {
-    const revision = this.initialRecord ? this.initialRecord.revision() + 1 : 1
-    const record: UiRecord = UiRecord.parse(this.input, revision) as never // Save button enabled only when record was created, so cast is safe
+    // Save button enabled only when record was created, so cast is safe. Edits supersede the initial record
+    const record: UiRecord = (
+      this.initialRecord
+        ? UiRecord.edit(this.input, this.initialRecord, this.device)
+        : UiRecord.parse(this.input, 1)
+    ) as never
     console.log(`Record`, record.to_string(true, true))
     const event: RecordSaveEvent = new CustomEvent("save", { detail: { record: record } })
     this.dispatchEvent(event)
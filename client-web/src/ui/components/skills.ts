@@ -41,7 +41,7 @@ export class Skills extends LitElement {
     }
   `
 
-  skillIcon(kind: string) {
+  skillIcon(kind: string, kindIcon?: string) {
     let icon: IconName | null = null
     if (kind == "physical") {
       icon = "activityPhysical"
@@ -53,6 +53,10 @@ export class Skills extends LitElement {
     if (icon) {
       return html`<q-icon class="icon" name=${icon}></q-icon>`
     }
+    if (kindIcon) {
+      // User defined kinds have no built-in icon, but may specify own symbol
+      return html`<span class="icon">${kindIcon}</span>`
+    }
   }
 
   render() {
@@ -61,7 +65,7 @@ export class Skills extends LitElement {
         ${this.skills.map(
           (skill) =>
             html` <div class="skill">
-              ${this.skillIcon(skill.kind)}
+              ${this.skillIcon(skill.kind, skill.kind_icon)}
               <span class="title">${skill.title}</span>
               <span class="level">${skill.level}</span>
             </div>`,
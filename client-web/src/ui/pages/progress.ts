@@ -161,6 +161,7 @@ export class ProgressPage extends LitElement {
         <q-query-results
           class="query-results"
           .data=${this.queryResultsData}
+          .device=${this.store.userState.device}
           .query=${`filter after=${DateDay.fromDate(new Date()).remove_days(30).toString()}. `}
           @queryUpdated=${this.onQueryUpdated.bind(this)}
           @save=${this.onEntryAdded.bind(this)}
@@ -1,8 +1,11 @@
 use std::panic;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use qqself_core::data_views::vocabulary::VocabularyView;
+use qqself_core::db::{ChangeEvent, Record};
 use qqself_core::encryption::cryptor::CryptorError;
 use qqself_core::encryption::hash::StableHash;
+use qqself_core::parsing::highlight::{utf16_pos, utf16_to_byte_pos, Highlighting};
 
 uniffi::include_scaffolding!("qqself");
 
@@ -17,6 +20,78 @@ pub fn string_hash(input: String) -> String {
     StableHash::hash_string(&input).to_string()
 }
 
+pub struct HighlightSpan {
+    pub kind: String,
+    pub start: u32,
+    pub end: u32,
+    pub message: Option<String>,
+}
+
+/// Returns token spans of the entry or query, error span is added at the end if input is invalid
+pub fn highlight(input: String, is_entry: bool) -> Vec<HighlightSpan> {
+    Highlighting::spans(&input, is_entry)
+        .into_iter()
+        .map(|v| HighlightSpan {
+            kind: v.kind.to_string(),
+            start: v.start as u32,
+            end: v.end as u32,
+            message: v.message,
+        })
+        .collect()
+}
+
+pub struct Completion {
+    pub kind: String,
+    pub text: String,
+    pub count: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+pub struct Completer {
+    vocabulary: Mutex<VocabularyView>,
+}
+
+impl Completer {
+    pub fn new() -> Self {
+        Self {
+            vocabulary: Mutex::new(VocabularyView::default()),
+        }
+    }
+
+    /// Adds entry to the vocabulary, invalid entries are skipped
+    pub fn add(&self, entry: String) {
+        if let Ok(record) = Record::parse(&entry) {
+            let mut vocabulary = self.vocabulary.lock().expect("Lock should be available");
+            vocabulary.update(&ChangeEvent::Added(record));
+        }
+    }
+
+    /// Returns completions for the input at the cursor, which is a UTF-16 position as Swift strings are using
+    pub fn complete(&self, input: String, cursor: u32, is_entry: bool) -> Vec<Completion> {
+        let vocabulary = self.vocabulary.lock().expect("Lock should be available");
+        let cursor = utf16_to_byte_pos(&input, cursor as usize);
+        let pos = |byte_pos: usize| utf16_pos(&input, byte_pos) as u32;
+        vocabulary
+            .complete(&input, cursor, is_entry)
+            .into_iter()
+            .map(|v| Completion {
+                kind: format!("{:?}", v.kind),
+                text: v.text,
+                count: v.count as u32,
+                start: pos(v.replace.start),
+                end: pos(v.replace.end),
+            })
+            .collect()
+    }
+}
+
+impl Default for Completer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // uniffi doesn't support static functions, wrap those manually
 
 pub fn cryptor_generate_new() -> Arc<Cryptor> {
@@ -7,7 +7,15 @@ mod views;
 
 use std::panic;
 
-use qqself_core::{build_info, db::Query, encryption::hash::StableHash};
+use qqself_core::{
+    build_info,
+    db::Query,
+    encryption::hash::StableHash,
+    parsing::{
+        diagnostic::Diagnostic,
+        highlight::{utf16_pos, Highlighting},
+    },
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 /// Initialize the library, for now only sets panic hooks and returns build info
@@ -17,9 +25,58 @@ pub fn initialize() -> String {
     build_info()
 }
 
+/// Details about invalid input. Positions are in UTF-16 code units as JavaScript strings are using
+#[wasm_bindgen(getter_with_clone)]
+pub struct ParseDiagnostic {
+    /// Full human readable message including the hint
+    pub message: String,
+    pub hint: Option<String>,
+    pub start: usize,
+    pub end: usize,
+    pub expected: Vec<String>,
+}
+
+impl ParseDiagnostic {
+    fn new(input: &str, diagnostic: Diagnostic) -> Self {
+        Self {
+            message: diagnostic.to_string(),
+            start: utf16_pos(input, diagnostic.span.start),
+            end: utf16_pos(input, diagnostic.span.end),
+            expected: diagnostic.expected.iter().map(|v| v.to_string()).collect(),
+            hint: diagnostic.hint,
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn validateQuery(query: String) -> Option<ParseDiagnostic> {
+    let err = Query::new(&query).err()?;
+    Some(ParseDiagnostic::new(&query, err.diagnostic(&query, false)))
+}
+
+/// Span of the input for syntax highlighting. Positions are in UTF-16 code units
+#[wasm_bindgen(getter_with_clone)]
+pub struct HighlightSpan {
+    /// Token name like `TagName` or `PropertyValue`, or `Error` for the part of the input that failed to parse
+    pub kind: String,
+    pub start: usize,
+    pub end: usize,
+    /// Error message with a hint for `Error` spans
+    pub message: Option<String>,
+}
+
+/// Returns token spans of the entry or query, error span is added at the end if input is invalid
 #[wasm_bindgen]
-pub fn validateQuery(query: String) -> Option<String> {
-    Query::new(&query).map_err(|v| v.to_string()).err()
+pub fn highlight(input: String, isEntry: bool) -> Vec<HighlightSpan> {
+    Highlighting::spans(&input, isEntry)
+        .into_iter()
+        .map(|v| HighlightSpan {
+            kind: v.kind.to_string(),
+            start: v.start,
+            end: v.end,
+            message: v.message,
+        })
+        .collect()
 }
 
 #[wasm_bindgen]
@@ -1,9 +1,16 @@
 use std::cell::RefCell;
 
 use qqself_core::{
-    data_views::skills::SkillsNotification,
-    date_time::datetime::DateDay,
+    data_views::{metrics::MetricKey, skills::SkillsNotification},
+    date_time::datetime::{DateDay, DateTime},
     db::{Notification, Query, Record, ViewUpdate, DB},
+    encryption::payload::PayloadId,
+    history::Change,
+    parsing::highlight::{utf16_pos, utf16_to_byte_pos},
+    progress::nudge::NudgeNotification,
+    rename::RenameRule,
+    resolution::ResolutionPolicy,
+    units::Dimension,
 };
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
@@ -15,6 +22,7 @@ pub struct Views {
 }
 
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct UiRecord {
     record: Record,
 }
@@ -30,6 +38,25 @@ impl UiRecord {
         UiRecord::parse(input, None).expect("deleted string should always be parsable")
     }
 
+    pub fn is_running(&self) -> bool {
+        self.record.date_range().is_running()
+    }
+
+    /// Returns stopped copy of running entry and deletion marker of the running one, both have to be added and
+    /// uploaded. `end` is in format YYYY-MM-DD HH:MM
+    pub fn stop(&self, end: String) -> Result<StoppedData, String> {
+        let end = end.parse::<DateTime>()?;
+        let stopped = self.record.stop(end)?;
+        Ok(StoppedData {
+            deleted: UiRecord {
+                record: stopped.deleted,
+            },
+            stopped: UiRecord {
+                record: stopped.stopped,
+            },
+        })
+    }
+
     pub fn day(&self) -> String {
         self.record.date_range().start().date().to_string()
     }
@@ -46,15 +73,47 @@ impl UiRecord {
         };
         Ok(UiRecord { record })
     }
+
+    /// Parses the edited version of the record made on the device, it supersedes the original record and
+    /// resolves it if it was a conflict
+    pub fn edit(input: String, original: &UiRecord, device: String) -> Result<UiRecord, String> {
+        let Record::Entry(entry) = Record::parse(&input)? else {
+            return Err("edited record has to be a single entry".to_string());
+        };
+        Ok(UiRecord {
+            record: original.record.edited(entry, &device),
+        })
+    }
+
+    /// Human readable changes between two revisions of the entry
+    pub fn diff(&self, to: &UiRecord) -> Result<Vec<String>, String> {
+        let (Record::Entry(from), Record::Entry(to)) = (&self.record, &to.record) else {
+            return Err("conflicts cannot be compared".to_string());
+        };
+        Ok(Change::diff(from, to)
+            .iter()
+            .map(|v| v.to_string())
+            .collect())
+    }
 }
 
 #[wasm_bindgen(getter_with_clone)]
 pub struct SkillData {
     pub title: String,
     pub kind: String,
+    pub kind_title: String,
+    pub kind_icon: Option<String>,
     pub level: usize,
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct SkillKindData {
+    pub name: String,
+    pub title: String,
+    pub order: usize,
+    pub icon: Option<String>,
+}
+
 #[wasm_bindgen(getter_with_clone)]
 pub struct SkillWeek {
     pub name: String,
@@ -62,6 +121,78 @@ pub struct SkillWeek {
     pub target: usize,
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct SkillLevelData {
+    pub level: usize,
+    pub day: String,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct SkillForecastData {
+    pub level: usize,
+    pub day: String,
+    pub minutes_per_week: usize,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct MetricData {
+    pub tag: String,
+    pub prop: String,
+    /// Dimension name like `length` for values with units, values of different dimensions are separate metrics
+    pub dimension: Option<String>,
+    /// Unit values are converted to, e.g. `m` for lengths
+    pub unit: Option<String>,
+    pub count: usize,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct MetricPoint {
+    pub datetime: String,
+    pub value: f32,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct ElementDuration {
+    pub element: String,
+    pub minutes: usize,
+}
+
+/// Completion for the input, `start` and `end` are UTF-16 positions of the input to replace with `text`
+#[wasm_bindgen(getter_with_clone)]
+pub struct CompletionData {
+    pub kind: String,
+    pub text: String,
+    pub count: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Records replacing the running record after it was stopped
+#[wasm_bindgen(getter_with_clone)]
+pub struct StoppedData {
+    pub deleted: UiRecord,
+    pub stopped: UiRecord,
+}
+
+/// Record before and after renaming, `to` has the next revision and has to be uploaded
+#[wasm_bindgen(getter_with_clone)]
+pub struct RenamedData {
+    pub from: UiRecord,
+    pub to: UiRecord,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct NudgeData {
+    pub kind: String,
+    pub skill: String,
+    pub message: String,
+    /// Days without practice for Inactive nudges
+    pub days: Option<usize>,
+    /// Minutes practiced and expected by now for BehindPace nudges
+    pub progress: Option<usize>,
+    pub expected: Option<usize>,
+}
+
 #[wasm_bindgen]
 impl Views {
     pub fn new(onUpdate: js_sys::Function, onNotification: js_sys::Function) -> Self {
@@ -79,6 +210,11 @@ impl Views {
                 ViewUpdate::Week => {
                     data.set(&"view".into(), &"Week".into());
                 }
+                ViewUpdate::Metrics(update) => {
+                    data.set(&"view".into(), &"Metrics".into());
+                    data.set(&"tag".into(), &update.metric.tag.into());
+                    data.set(&"prop".into(), &update.metric.prop.into());
+                }
             };
             if let Err(err) = onUpdate.call1(&JsValue::NULL, &data) {
                 error(&err);
@@ -87,13 +223,42 @@ impl Views {
         db.on_notification(Box::new(move |notification| {
             let data = js_sys::Map::new();
             match notification {
-                Notification::Skills(SkillsNotification::HourProgress(msg)) => {
+                Notification::Skills(notification) => {
                     data.set(&"view".into(), &"Skills".into());
-                    data.set(&"message".into(), &msg.into())
+                    // Default English message, clients may use structured data for own formatting
+                    data.set(&"message".into(), &notification.to_string().into());
+                    match notification {
+                        SkillsNotification::LevelUp {
+                            skill,
+                            level_before,
+                            level,
+                        } => {
+                            data.set(&"kind".into(), &"LevelUp".into());
+                            data.set(&"skill".into(), &skill.into());
+                            data.set(&"levelBefore".into(), &level_before.into());
+                            data.set(&"level".into(), &level.into());
+                        }
+                        SkillsNotification::HourProgress {
+                            skill,
+                            period,
+                            hours,
+                        } => {
+                            data.set(&"kind".into(), &"HourProgress".into());
+                            if let Some(skill) = skill {
+                                data.set(&"skill".into(), &skill.into());
+                            }
+                            data.set(&"period".into(), &period.name().into());
+                            data.set(&"hours".into(), &hours.into());
+                        }
+                    }
                 }
-                Notification::Skills(SkillsNotification::LevelUp(msg)) => {
-                    data.set(&"view".into(), &"Skills".into());
-                    data.set(&"message".into(), &msg.into())
+                Notification::Nudge(notification) => {
+                    data.set(&"view".into(), &"Nudge".into());
+                    data.set(&"message".into(), &notification.to_string().into());
+                }
+                Notification::Schema(warning) => {
+                    data.set(&"view".into(), &"Schema".into());
+                    data.set(&"message".into(), &warning.to_string().into());
                 }
             };
             if let Err(err) = onNotification.call1(&JsValue::NULL, &data) {
@@ -105,11 +270,97 @@ impl Views {
         }
     }
 
+    /// Sets id of the device, it's used for versioning of the edits made by views, e.g. renaming
+    pub fn set_device(&self, device: String) {
+        self.db.borrow_mut().set_device(&device);
+    }
+
     pub fn add_record(&self, record: &UiRecord, interactive: bool, now: Option<DateDay>) {
         let mut db = self.db.borrow_mut();
         db.add(record.record.clone(), interactive, now);
     }
 
+    /// Adds the record fetched from the API, payload id is used for last writer wins conflict resolution
+    pub fn add_synced_record(
+        &self,
+        record: &UiRecord,
+        payloadId: String,
+        interactive: bool,
+        now: Option<DateDay>,
+    ) {
+        let mut db = self.db.borrow_mut();
+        let payload_id = PayloadId::new_encoded(payloadId);
+        db.add_synced(record.record.clone(), &payload_id, interactive, now);
+    }
+
+    /// Starts keeping all the revisions of entries, should be called before records are added
+    pub fn enable_history(&self) {
+        self.db.borrow_mut().enable_history();
+    }
+
+    /// Returns all known revisions of the record ordered from the oldest one, empty if history isn't enabled
+    pub fn revisions(&self, record: &UiRecord) -> Vec<UiRecord> {
+        let db = self.db.borrow();
+        let Some(history) = db.history() else {
+            return vec![];
+        };
+        history
+            .revisions(record.record.date_range())
+            .into_iter()
+            .map(|v| UiRecord {
+                record: Record::Entry(v.entry().clone()),
+            })
+            .collect()
+    }
+
+    /// Adds the old revision as a new one, returned record has to be synced
+    pub fn restore_revision(
+        &self,
+        revision: &UiRecord,
+        now: Option<DateDay>,
+    ) -> Result<UiRecord, String> {
+        let Record::Entry(entry) = &revision.record else {
+            return Err("revision has to be a single entry".to_string());
+        };
+        let record = self.db.borrow_mut().restore(entry, now);
+        Ok(UiRecord { record })
+    }
+
+    /// Sets policy for automatic conflict resolution, one of `manual`, `last-writer-wins`, `prefer-deletion` or
+    /// `field-merge`. Existing conflicts are resolved again
+    pub fn set_resolution_policy(
+        &self,
+        policy: String,
+        now: Option<DateDay>,
+    ) -> Result<(), String> {
+        let policy = policy.parse::<ResolutionPolicy>()?;
+        self.db.borrow_mut().set_resolution_policy(policy, now);
+        Ok(())
+    }
+
+    /// Renames tags and properties in all the entries. Rules are separated by spaces, e.g. `jog=run run.dist=distance`.
+    /// In dry run mode nothing is changed and result could be used for preview
+    pub fn rename(
+        &self,
+        rules: String,
+        dryRun: bool,
+        now: Option<DateDay>,
+    ) -> Result<Vec<RenamedData>, String> {
+        let rules = rules
+            .split_whitespace()
+            .map(|v| v.parse::<RenameRule>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut db = self.db.borrow_mut();
+        Ok(db
+            .rename(&rules, dryRun, now)
+            .into_iter()
+            .map(|v| RenamedData {
+                from: UiRecord { record: v.from },
+                to: UiRecord { record: v.to },
+            })
+            .collect())
+    }
+
     pub fn update_query(&self, query: String) -> Result<(), String> {
         let query = Query::new(&query).map_err(|v| v.to_string())?;
         let mut db = self.db.borrow_mut();
@@ -134,14 +385,16 @@ impl Views {
 
     pub fn view_skills(&self) -> Vec<SkillData> {
         let db = self.db.borrow();
-        let mut skills = db.skills().iter().map(|(_, v)| v).collect::<Vec<_>>();
+        let mut skills = db.skills().values().collect::<Vec<_>>();
         skills.sort();
 
         let mut output = Vec::new();
         for skill in skills {
             let skill_data = SkillData {
                 title: skill.title().to_string(),
-                kind: skill.kind().to_string(),
+                kind: skill.kind().name().to_string(),
+                kind_title: skill.kind().title().to_string(),
+                kind_icon: skill.kind().icon().map(|v| v.to_string()),
                 level: skill.progress().level,
             };
             output.push(skill_data);
@@ -149,6 +402,80 @@ impl Views {
         output
     }
 
+    pub fn view_skill_kinds(&self) -> Vec<SkillKindData> {
+        let db = self.db.borrow();
+        let mut output = Vec::new();
+        for kind in db.skill_kinds().sorted() {
+            output.push(SkillKindData {
+                name: kind.name().to_string(),
+                title: kind.title().to_string(),
+                order: kind.order(),
+                icon: kind.icon().map(|v| v.to_string()),
+            });
+        }
+        output
+    }
+
+    pub fn view_skill_levels(&self, skill: String, now: DateDay) -> Vec<SkillLevelData> {
+        let db = self.db.borrow();
+        let Some(history) = db.skill_history(&skill, now) else {
+            return vec![];
+        };
+        history
+            .levels()
+            .iter()
+            .map(|v| SkillLevelData {
+                level: v.level,
+                day: v.day.to_string(),
+            })
+            .collect()
+    }
+
+    pub fn view_skill_forecast(&self, skill: String, now: DateDay) -> Option<SkillForecastData> {
+        let db = self.db.borrow();
+        let history = db.skill_history(&skill, now)?;
+        history.forecast().map(|v| SkillForecastData {
+            level: v.level,
+            day: v.day.to_string(),
+            minutes_per_week: v.minutes_per_week,
+        })
+    }
+
+    pub fn nudges(&self, now: DateDay, inactive_days: usize) -> Vec<NudgeData> {
+        let db = self.db.borrow();
+        let mut output = Vec::new();
+        for notification in db.nudges(now, inactive_days) {
+            let Notification::Nudge(nudge) = notification else {
+                continue;
+            };
+            let message = nudge.to_string();
+            output.push(match nudge {
+                NudgeNotification::Inactive { skill, days } => NudgeData {
+                    kind: "Inactive".to_string(),
+                    skill,
+                    message,
+                    days: Some(days),
+                    progress: None,
+                    expected: None,
+                },
+                NudgeNotification::BehindPace {
+                    skill,
+                    progress,
+                    expected,
+                    ..
+                } => NudgeData {
+                    kind: "BehindPace".to_string(),
+                    skill,
+                    message,
+                    days: None,
+                    progress: Some(progress as usize),
+                    expected: Some(expected as usize),
+                },
+            });
+        }
+        output
+    }
+
     pub fn view_week(&self) -> Vec<SkillWeek> {
         let db = self.db.borrow();
         let mut output = Vec::new();
@@ -162,4 +489,74 @@ impl Views {
         }
         output
     }
+
+    pub fn view_metrics(&self) -> Vec<MetricData> {
+        let db = self.db.borrow();
+        let mut output = Vec::new();
+        for (metric, series) in db.metrics() {
+            output.push(MetricData {
+                tag: metric.tag.clone(),
+                prop: metric.prop.clone(),
+                dimension: metric.dimension.map(|v| v.to_string()),
+                unit: metric.unit().map(|v| v.to_string()),
+                count: series.len(),
+            });
+        }
+        output
+    }
+
+    pub fn view_metric(
+        &self,
+        tag: String,
+        prop: String,
+        dimension: Option<String>,
+    ) -> Vec<MetricPoint> {
+        let db = self.db.borrow();
+        let Ok(dimension) = dimension.map(|v| v.parse::<Dimension>()).transpose() else {
+            return vec![];
+        };
+        let key = MetricKey {
+            tag,
+            prop,
+            dimension,
+        };
+        let Some(series) = db.metrics().get(&key) else {
+            return vec![];
+        };
+        series
+            .iter()
+            .map(|(date_range, value)| MetricPoint {
+                datetime: date_range.start().to_string(),
+                value: *value,
+            })
+            .collect()
+    }
+
+    /// Time spent per element of the prop, e.g. `with` each person
+    pub fn view_durations(&self, tag: String, prop: String) -> Vec<ElementDuration> {
+        let db = self.db.borrow();
+        db.durations(&tag, &prop)
+            .into_iter()
+            .map(|(element, minutes)| ElementDuration {
+                element: element.to_string(),
+                minutes: minutes as usize,
+            })
+            .collect()
+    }
+
+    /// Returns completions for the input at the cursor, which is a UTF-16 position as JavaScript strings are using
+    pub fn completions(&self, input: String, cursor: usize, isEntry: bool) -> Vec<CompletionData> {
+        let db = self.db.borrow();
+        let cursor = utf16_to_byte_pos(&input, cursor);
+        db.completions(&input, cursor, isEntry)
+            .into_iter()
+            .map(|v| CompletionData {
+                kind: format!("{:?}", v.kind),
+                text: v.text,
+                count: v.count,
+                start: utf16_pos(&input, v.replace.start),
+                end: utf16_pos(&input, v.replace.end),
+            })
+            .collect()
+    }
 }
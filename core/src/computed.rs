@@ -0,0 +1,479 @@
+use std::{collections::BTreeMap, iter::Peekable, str::Chars};
+
+use thiserror::Error;
+
+use crate::{
+    date_time::datetime::DateTimeRange,
+    db::{ChangeEvent, Record, Selector},
+    record::{Entry, Prop, PropOperator, PropVal, Tag},
+};
+
+/*
+Computed props - values derived from the entry itself, so users don't have to type those.
+
+Rules are defined with `compute` records, tags before `compute` are the selector and every `compute` prop is
+a formula for the prop with the same name, e.g. `run. compute pace="duration / distance". Running pace`.
+Computed props are added to the first selector tag of the matching entries. Those take part in queries,
+metrics and exports, but are never serialized, so stored entry text stays untouched.
+
+Formulas support numbers, `+ - * /` and parentheses. Names refer to props of the same tag: numbers are taken
+as is, quantities in the base unit of their dimension (meters, grams, etc.) and times in minutes. `duration` is
+the entry duration in minutes, unless the tag has a prop with such name. If any of the values is missing the
+prop isn't computed.
+*/
+
+/// Errors occurred during formula parsing
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FormulaError {
+    #[error("Unexpected character '{0}'")]
+    UnexpectedChar(char),
+    #[error("Unexpected end of formula")]
+    UnexpectedEnd,
+    #[error("Invalid number '{0}'")]
+    InvalidNumber(String),
+}
+
+/// Arithmetic expression used to compute a prop value
+#[derive(Debug, PartialEq, Clone)]
+pub enum Formula {
+    Number(f32),
+    Name(String),
+    Add(Box<Formula>, Box<Formula>),
+    Sub(Box<Formula>, Box<Formula>),
+    Mul(Box<Formula>, Box<Formula>),
+    Div(Box<Formula>, Box<Formula>),
+}
+
+impl Formula {
+    pub fn parse(input: &str) -> Result<Formula, FormulaError> {
+        let mut chars = input.chars().peekable();
+        let formula = Formula::parse_sum(&mut chars)?;
+        skip_spaces(&mut chars);
+        match chars.next() {
+            None => Ok(formula),
+            Some(c) => Err(FormulaError::UnexpectedChar(c)),
+        }
+    }
+
+    fn parse_sum(chars: &mut Peekable<Chars>) -> Result<Formula, FormulaError> {
+        let mut left = Formula::parse_product(chars)?;
+        loop {
+            skip_spaces(chars);
+            let op = match chars.peek() {
+                Some('+') => Formula::Add,
+                Some('-') => Formula::Sub,
+                _ => return Ok(left),
+            };
+            chars.next();
+            left = op(Box::new(left), Box::new(Formula::parse_product(chars)?));
+        }
+    }
+
+    fn parse_product(chars: &mut Peekable<Chars>) -> Result<Formula, FormulaError> {
+        let mut left = Formula::parse_operand(chars)?;
+        loop {
+            skip_spaces(chars);
+            let op = match chars.peek() {
+                Some('*') => Formula::Mul,
+                Some('/') => Formula::Div,
+                _ => return Ok(left),
+            };
+            chars.next();
+            left = op(Box::new(left), Box::new(Formula::parse_operand(chars)?));
+        }
+    }
+
+    fn parse_operand(chars: &mut Peekable<Chars>) -> Result<Formula, FormulaError> {
+        skip_spaces(chars);
+        match chars.peek().copied() {
+            None => Err(FormulaError::UnexpectedEnd),
+            Some('(') => {
+                chars.next();
+                let formula = Formula::parse_sum(chars)?;
+                skip_spaces(chars);
+                match chars.next() {
+                    Some(')') => Ok(formula),
+                    Some(c) => Err(FormulaError::UnexpectedChar(c)),
+                    None => Err(FormulaError::UnexpectedEnd),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let number = read_while(chars, |c| c.is_ascii_digit() || c == '.');
+                number
+                    .parse()
+                    .map(Formula::Number)
+                    .map_err(|_| FormulaError::InvalidNumber(number))
+            }
+            Some(c) if c.is_alphabetic() => Ok(Formula::Name(read_while(chars, |c| {
+                c.is_alphanumeric() || c == '_'
+            }))),
+            Some(c) => Err(FormulaError::UnexpectedChar(c)),
+        }
+    }
+
+    /// Evaluates formula with values from the tag and the entry duration
+    pub fn eval(&self, tag: &Tag, date_range: &DateTimeRange) -> Option<f32> {
+        let eval = |v: &Formula| v.eval(tag, date_range);
+        let value = match self {
+            Formula::Number(n) => *n,
+            Formula::Name(name) => return value(tag, name, date_range),
+            Formula::Add(a, b) => eval(a)? + eval(b)?,
+            Formula::Sub(a, b) => eval(a)? - eval(b)?,
+            Formula::Mul(a, b) => eval(a)? * eval(b)?,
+            Formula::Div(a, b) => eval(a)? / eval(b)?,
+        };
+        // Division by zero or overflow doesn't give any meaningful value
+        value.is_finite().then_some(value)
+    }
+}
+
+fn skip_spaces(chars: &mut Peekable<Chars>) {
+    read_while(chars, char::is_whitespace);
+}
+
+fn read_while(chars: &mut Peekable<Chars>, predicate: impl Fn(char) -> bool) -> String {
+    let mut out = String::new();
+    while let Some(c) = chars.next_if(|c| predicate(*c)) {
+        out.push(c);
+    }
+    out
+}
+
+/// Numeric value of the name used in the formula
+fn value(tag: &Tag, name: &str, date_range: &DateTimeRange) -> Option<f32> {
+    let Some(prop) = tag.props.iter().find(|v| v.name == name) else {
+        if name != "duration" || date_range.is_instant() || date_range.is_running() {
+            return None;
+        }
+        return Some(date_range.duration().minutes() as f32);
+    };
+    match &prop.val {
+        PropVal::Number(n) => Some(*n),
+        PropVal::Quantity(quantity) => Some(quantity.normalize().value),
+        PropVal::Time(time) => Some(time.minutes() as f32),
+        _ => None,
+    }
+}
+
+/// Rule deriving a single prop for entries matching the selector
+#[derive(Debug, PartialEq, Clone)]
+pub struct ComputedProp {
+    selector: Selector,
+    tag: String,
+    name: String,
+    formula: Formula,
+}
+
+impl ComputedProp {
+    /// Creates rules from given record if it's a `compute` record, props with invalid formulas are skipped
+    pub fn from_record(record: &Entry) -> Vec<ComputedProp> {
+        let Some(compute) = record.tags.iter().find(|v| v.name == "compute") else {
+            return vec![]; // Most of the records are not rules, early return in this case
+        };
+        let query: Vec<_> = record
+            .tags
+            .iter()
+            .filter(|v| v.name != "compute" && v.name != "entry")
+            .cloned()
+            .collect();
+        let Some(tag) = query.first().map(|v| v.name.clone()) else {
+            return vec![]; // Without a selector it's unclear where to add computed props
+        };
+        let selector = Selector {
+            inclusive_tags: query,
+            exclusive_tags: vec![Tag::new("compute".to_string(), vec![], 0)], // rules aren't computed themselves
+        };
+        compute
+            .props
+            .iter()
+            .filter_map(|prop| {
+                let formula = match &prop.val {
+                    PropVal::String(s) => Formula::parse(s).ok()?,
+                    PropVal::Number(n) => Formula::Number(*n),
+                    _ => return None,
+                };
+                Some(ComputedProp {
+                    selector: selector.clone(),
+                    tag: tag.clone(),
+                    name: prop.name.clone(),
+                    formula,
+                })
+            })
+            .collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    /// Computes the prop for the entry. Entry is left as is if it doesn't match or the prop is already set
+    fn apply(&self, entry: &mut Entry) -> bool {
+        if !self.selector.matches(entry) {
+            return false;
+        }
+        let date_range = entry.date_range;
+        let Some(tag) = entry.tags.iter_mut().find(|v| v.is_in_namespace(&self.tag)) else {
+            return false;
+        };
+        if tag.props.iter().any(|v| v.name == self.name) {
+            return false; // Props written by the user always win
+        }
+        let Some(value) = self.formula.eval(tag, &date_range) else {
+            return false;
+        };
+        tag.props.push(Prop {
+            name: self.name.clone(),
+            val: PropVal::Number((value * 100.0).round() / 100.0),
+            operator: PropOperator::Eq,
+            start_pos: 0,
+            computed: true,
+        });
+        true
+    }
+}
+
+/// All the computed prop rules, keyed by the date range of the record where those were defined
+#[derive(Default)]
+pub struct ComputedProps {
+    rules: BTreeMap<DateTimeRange, Vec<ComputedProp>>,
+}
+
+/// Rules of the record at the date range as those were before the change, see `ComputedProps::update`
+pub struct RulesChange {
+    date_range: DateTimeRange,
+    rules: Option<Vec<ComputedProp>>,
+}
+
+impl ComputedProps {
+    /// Updates rules from the change event. If rules got changed and entries need to be recomputed then returns the
+    /// replaced rules, so the state before the change can be restored without copying all the rules
+    pub fn update(&mut self, event: &ChangeEvent) -> Option<RulesChange> {
+        let (removed, added) = match event {
+            ChangeEvent::Added(record) => (None, record),
+            ChangeEvent::Replaced { from, to } => (self.rules.remove(from.date_range()), to),
+        };
+        let rules = match added {
+            Record::Entry(entry) if !added.is_deleted_record() => ComputedProp::from_record(entry),
+            _ => vec![], // Rules in conflicts are ignored until those are resolved
+        };
+        if removed.is_none() && rules.is_empty() {
+            return None;
+        }
+        let date_range = *added.date_range();
+        if !rules.is_empty() {
+            self.rules.insert(date_range, rules);
+        }
+        Some(RulesChange {
+            date_range,
+            rules: removed,
+        })
+    }
+
+    /// Puts back the rules of the change and returns the replaced ones, so restoring it again undoes the restore
+    pub fn restore(&mut self, change: RulesChange) -> RulesChange {
+        let replaced = match change.rules {
+            Some(rules) => self.rules.insert(change.date_range, rules),
+            None => self.rules.remove(&change.date_range),
+        };
+        RulesChange {
+            date_range: change.date_range,
+            rules: replaced,
+        }
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &ComputedProp> {
+        self.rules.values().flatten()
+    }
+
+    /// Returns the record with computed props added or None if no rule applies to it. Rules are applied in order
+    /// of definition, so later rules may refer to props computed by the earlier ones
+    pub fn apply(&self, record: &Record) -> Option<Record> {
+        if self.rules.is_empty() || record.is_deleted_record() {
+            return None;
+        }
+        let mut applied = false;
+        let mut apply = |entry: &Entry| {
+            let mut entry = entry.clone();
+            for rule in self.rules() {
+                applied |= rule.apply(&mut entry);
+            }
+            entry
+        };
+        let record = match record {
+            Record::Entry(entry) => Record::Entry(apply(entry)),
+            Record::Conflict(entries) => Record::Conflict(entries.iter().map(apply).collect()),
+        };
+        applied.then_some(record)
+    }
+
+    /// Same as `apply`, but returns the record as is if no rule applies to it
+    pub fn apply_or_clone(&self, record: &Record) -> Record {
+        self.apply(record).unwrap_or_else(|| record.clone())
+    }
+
+    /// Change event with computed props added to its records
+    pub fn apply_event(&self, event: &ChangeEvent) -> ChangeEvent {
+        match event {
+            ChangeEvent::Added(record) => ChangeEvent::Added(self.apply_or_clone(record)),
+            ChangeEvent::Replaced { from, to } => ChangeEvent::Replaced {
+                from: self.apply_or_clone(from),
+                to: self.apply_or_clone(to),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn entry(s: &str) -> Entry {
+        Entry::parse(s).unwrap()
+    }
+
+    fn computed(rules: &[&str], s: &str) -> String {
+        let mut computed = ComputedProps::default();
+        for rule in rules {
+            computed.update(&ChangeEvent::Added(Record::Entry(entry(rule))));
+        }
+        let Some(Record::Entry(entry)) = computed.apply(&Record::Entry(entry(s))) else {
+            return String::new();
+        };
+        let props: Vec<_> = entry
+            .tags
+            .iter()
+            .flat_map(|tag| tag.props.iter().filter(|v| v.computed))
+            .map(|v| v.to_string())
+            .collect();
+        props.join(" ")
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn formula_parse() {
+        let name = |s: &str| Box::new(Formula::Name(s.to_string()));
+        assert_eq!(
+            Formula::parse("duration / distance"),
+            Ok(Formula::Div(name("duration"), name("distance")))
+        );
+        assert_eq!(
+            Formula::parse("(a+b)*2 - c"),
+            Ok(Formula::Sub(
+                Box::new(Formula::Mul(
+                    Box::new(Formula::Add(name("a"), name("b"))),
+                    Box::new(Formula::Number(2.0))
+                )),
+                name("c")
+            ))
+        );
+        assert_eq!(Formula::parse("a +"), Err(FormulaError::UnexpectedEnd));
+        assert_eq!(Formula::parse("(a"), Err(FormulaError::UnexpectedEnd));
+        assert_eq!(
+            Formula::parse("a b"),
+            Err(FormulaError::UnexpectedChar('b'))
+        );
+        assert_eq!(
+            Formula::parse("1.2.3"),
+            Err(FormulaError::InvalidNumber("1.2.3".to_string()))
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn apply() {
+        let pace = "2023-07-01 00:00 run. compute pace=\"duration / distance\"";
+        // Entry duration and plain numbers
+        assert_eq!(
+            computed(&[pace], "2023-07-03 10:00 11:00 run distance=12"),
+            "pace=5"
+        );
+        // Quantities are taken in base units
+        let speed = "2023-07-01 00:00 run. compute speed=\"distance / 1000 / (duration / 60)\"";
+        assert_eq!(
+            computed(&[speed], "2023-07-03 10:00 10:30 run distance=5km"),
+            "speed=10"
+        );
+        // Values are rounded
+        assert_eq!(
+            computed(&[pace], "2023-07-03 10:00 11:00 run distance=7"),
+            "pace=8.57"
+        );
+        // Missing values, instant entries and division by zero
+        assert_eq!(computed(&[pace], "2023-07-03 10:00 11:00 run"), "");
+        assert_eq!(computed(&[pace], "2023-07-03 10:00 run distance=5"), "");
+        assert_eq!(
+            computed(&[pace], "2023-07-03 10:00 11:00 run distance=0"),
+            ""
+        );
+        // Props written by the user win
+        assert_eq!(
+            computed(&[pace], "2023-07-03 10:00 11:00 run distance=12 pace=4"),
+            ""
+        );
+        // Selector props have to match
+        let trail = "2023-07-01 00:01 run kind=trail. compute pace=\"duration / distance\"";
+        assert_eq!(
+            computed(&[trail], "2023-07-03 10:00 11:00 run distance=12"),
+            ""
+        );
+        // Later rules may use props computed by earlier ones
+        let hours = "2023-07-01 00:01 read. compute hours=\"duration / 60\"";
+        let speed = "2023-07-01 00:02 read. compute speed=\"pages / hours\"";
+        assert_eq!(
+            computed(&[hours, speed], "2023-07-03 10:00 11:30 read pages=30"),
+            "hours=1.5 speed=20"
+        );
+        // Rule records themselves are left as is
+        assert_eq!(computed(&[pace], pace), "");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn update() {
+        let mut computed = ComputedProps::default();
+        let rule = Record::Entry(entry(
+            "2023-07-01 00:00 run. compute pace=\"duration / distance\"",
+        ));
+        let other = Record::Entry(entry("2023-07-01 10:00 11:00 run distance=10"));
+        assert!(computed
+            .update(&ChangeEvent::Added(other.clone()))
+            .is_none());
+        assert!(computed.update(&ChangeEvent::Added(rule.clone())).is_some());
+        assert_eq!(computed.rules().count(), 1);
+
+        // Computed props aren't serialized
+        let applied = computed.apply(&other).unwrap();
+        assert_eq!(applied, other);
+        assert_eq!(
+            applied.to_string(true, false),
+            "2023-07-01 10:00 11:00 run distance=10"
+        );
+
+        // Deleting the rule record removes its rules
+        let deleted = Record::parse(&rule.to_deleted_string()).unwrap();
+        let change = computed
+            .update(&ChangeEvent::Replaced {
+                from: rule,
+                to: deleted,
+            })
+            .unwrap();
+        assert_eq!(computed.rules().count(), 0);
+        assert_eq!(computed.apply(&other), None);
+
+        // Restoring the change brings the state before it back and vice versa
+        let change = computed.restore(change);
+        assert_eq!(computed.rules().count(), 1);
+        computed.restore(change);
+        assert_eq!(computed.rules().count(), 0);
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    date_time::datetime::DateTimeRange,
+    db::{ChangeEvent, Record, ViewUpdate},
+    record::{Entry, PropVal, RESERVED_TAGS},
+    units::{Dimension, Unit},
+};
+
+/// Identifies single metric as a numeric property of a tag, e.g. `weight kg=72.4` gives `weight kg`. Values with
+/// units of different dimensions and plain numbers can't be summed up, so each of those is a separate metric
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct MetricKey {
+    pub tag: String,
+    pub prop: String,
+    /// Dimension of the values with units, None for plain numbers
+    pub dimension: Option<Dimension>,
+}
+
+impl MetricKey {
+    /// Unit of the metric values, all the values of the dimension are converted to its base unit
+    pub fn unit(&self) -> Option<Unit> {
+        self.dimension.map(Unit::base)
+    }
+}
+
+/// Identifies single text element of a property, e.g. `run with=anna,bob` gives `run with anna` and `run with bob`
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct ElementKey {
+    pub tag: String,
+    pub prop: String,
+    pub element: String,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct MetricsUpdate {
+    pub metric: MetricKey,
+}
+
+/// Time series of numeric property values. Mostly useful for point in time entries like
+/// `2023-07-03 08:00 weight kg=72.4`, but values of regular entries are collected as well.
+/// Values with units are converted to the base unit of their dimension, e.g. `10km` and `500m` are stored as
+/// meters, numbers without units are taken as is. Text values are grouped by list element instead, so time spent
+/// e.g. `with` each person can be aggregated
+#[derive(Default)]
+pub struct MetricsView {
+    data: BTreeMap<MetricKey, BTreeMap<DateTimeRange, f32>>,
+    elements: BTreeMap<ElementKey, BTreeSet<DateTimeRange>>,
+}
+
+impl MetricsView {
+    pub fn update(
+        &mut self,
+        event: &ChangeEvent,
+        on_view_update: &Option<Box<dyn Fn(ViewUpdate)>>,
+    ) {
+        let entry = match event {
+            ChangeEvent::Added(Record::Entry(entry)) => entry,
+            ChangeEvent::Replaced { from, to } => {
+                if let Record::Entry(from) = from {
+                    self.delete_entry(from, on_view_update);
+                }
+                let Record::Entry(to) = to else {
+                    return; // TODO Handle conflicts
+                };
+                to
+            }
+            _ => return, // TODO Handle conflicts
+        };
+        for (metric, value) in Self::values(entry) {
+            self.data
+                .entry(metric.clone())
+                .or_default()
+                .insert(entry.date_range, value);
+            if let Some(on_view_update) = on_view_update {
+                on_view_update(ViewUpdate::Metrics(MetricsUpdate { metric }))
+            }
+        }
+        for element in Self::elements(entry) {
+            self.elements
+                .entry(element)
+                .or_default()
+                .insert(entry.date_range);
+        }
+    }
+
+    fn delete_entry(&mut self, entry: &Entry, on_view_update: &Option<Box<dyn Fn(ViewUpdate)>>) {
+        for (metric, _) in Self::values(entry) {
+            let Some(series) = self.data.get_mut(&metric) else {
+                continue;
+            };
+            series.remove(&entry.date_range);
+            if series.is_empty() {
+                self.data.remove(&metric);
+            }
+            if let Some(on_view_update) = on_view_update {
+                on_view_update(ViewUpdate::Metrics(MetricsUpdate { metric }))
+            }
+        }
+        for element in Self::elements(entry) {
+            let Some(ranges) = self.elements.get_mut(&element) else {
+                continue;
+            };
+            ranges.remove(&entry.date_range);
+            if ranges.is_empty() {
+                self.elements.remove(&element);
+            }
+        }
+    }
+
+    /// Numeric values of the entry props. List items are summed up per dimension, so `laps=400m,1km` gives 1400m
+    fn values(entry: &Entry) -> BTreeMap<MetricKey, f32> {
+        let mut values = BTreeMap::new();
+        // Settings aren't measurements
+        for tag in entry
+            .tags
+            .iter()
+            .filter(|tag| !RESERVED_TAGS.contains(&tag.name.as_str()))
+        {
+            for prop in &tag.props {
+                for item in prop.val.items() {
+                    let (value, dimension) = match item {
+                        PropVal::Number(value) => (*value, None),
+                        PropVal::Quantity(quantity) => {
+                            (quantity.normalize().value, Some(quantity.unit.dimension()))
+                        }
+                        _ => continue,
+                    };
+                    let metric = MetricKey {
+                        tag: tag.name.clone(),
+                        prop: prop.name.clone(),
+                        dimension,
+                    };
+                    *values.entry(metric).or_default() += value;
+                }
+            }
+        }
+        values
+    }
+
+    /// Text values of the entry props, each list item separately
+    fn elements(entry: &Entry) -> BTreeSet<ElementKey> {
+        let mut elements = BTreeSet::new();
+        for tag in entry
+            .tags
+            .iter()
+            .filter(|tag| !RESERVED_TAGS.contains(&tag.name.as_str()))
+        {
+            for prop in &tag.props {
+                for item in prop.val.items() {
+                    if let PropVal::String(element) = item {
+                        elements.insert(ElementKey {
+                            tag: tag.name.clone(),
+                            prop: prop.name.clone(),
+                            element: element.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        elements
+    }
+
+    pub fn data(&self) -> &BTreeMap<MetricKey, BTreeMap<DateTimeRange, f32>> {
+        &self.data
+    }
+
+    /// Returns values of the metric sorted by time, dimension is None for values without units
+    pub fn series(
+        &self,
+        tag: &str,
+        prop: &str,
+        dimension: Option<Dimension>,
+    ) -> Option<&BTreeMap<DateTimeRange, f32>> {
+        self.data.get(&MetricKey {
+            tag: tag.to_string(),
+            prop: prop.to_string(),
+            dimension,
+        })
+    }
+
+    /// Sum of all the metric values, e.g. total running distance
+    pub fn total(&self, tag: &str, prop: &str, dimension: Option<Dimension>) -> Option<f32> {
+        self.series(tag, prop, dimension).map(|v| v.values().sum())
+    }
+
+    /// Total duration in minutes of the entries per element of the prop, e.g. time spent `with` each person.
+    /// Entries listing several elements are counted for each of those
+    pub fn durations(&self, tag: &str, prop: &str) -> BTreeMap<&str, u64> {
+        self.elements
+            .iter()
+            .filter(|(key, _)| key.tag == tag && key.prop == prop)
+            .map(|(key, ranges)| {
+                let minutes = ranges.iter().map(|v| v.duration().minutes()).sum();
+                (key.element.as_str(), minutes)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(s: &str) -> Record {
+        Record::Entry(Entry::parse(s).unwrap())
+    }
+
+    fn series(view: &MetricsView, tag: &str, prop: &str) -> Vec<String> {
+        view.series(tag, prop, None)
+            .map(|v| v.iter().map(|(k, v)| format!("{k} {v}")).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn update() {
+        let mut view = MetricsView::default();
+        let add = |view: &mut MetricsView, s: &str| {
+            view.update(&ChangeEvent::Added(record(s)), &None);
+        };
+        add(&mut view, "2023-07-03 08:00 weight kg=72.4");
+        add(&mut view, "2023-07-02 08:00 weight kg=73. Before breakfast");
+        add(
+            &mut view,
+            "2023-07-03 10:00 11:00 run distance=10 place=park",
+        );
+        add(
+            &mut view,
+            "2023-07-03 12:00 12:00 run. skill kind=physical perfect=1. Runner",
+        );
+        add(&mut view, "2023-07-03 13:00 sleep");
+        assert_eq!(
+            series(&view, "weight", "kg"),
+            vec!["2023-07-02 08:00 73", "2023-07-03 08:00 72.4"]
+        );
+        assert_eq!(
+            series(&view, "run", "distance"),
+            vec!["2023-07-03 10:00 11:00 10"]
+        );
+        assert_eq!(view.data().len(), 2);
+
+        // Replacing the entry updates the value
+        view.update(
+            &ChangeEvent::Replaced {
+                from: record("2023-07-03 08:00 weight kg=72.4"),
+                to: record("2023-07-03 08:00 weight kg=72. entry revision=2"),
+            },
+            &None,
+        );
+        assert_eq!(
+            series(&view, "weight", "kg"),
+            vec!["2023-07-02 08:00 73", "2023-07-03 08:00 72"]
+        );
+
+        // Deleting the last value removes the metric
+        view.update(
+            &ChangeEvent::Replaced {
+                from: record("2023-07-03 10:00 11:00 run distance=10 place=park"),
+                to: record("2023-07-03 10:00 11:00 entry revision=2 deleted"),
+            },
+            &None,
+        );
+        assert_eq!(series(&view, "run", "distance"), Vec::<String>::new());
+        assert_eq!(view.data().len(), 1);
+    }
+
+    #[test]
+    fn units() {
+        let mut view = MetricsView::default();
+        for entry in [
+            "2023-07-03 10:00 11:00 run distance=10km",
+            "2023-07-04 10:00 10:05 run distance=500m",
+            "2023-07-05 10:00 11:00 run distance=6.2mi",
+        ] {
+            view.update(&ChangeEvent::Added(record(entry)), &None);
+        }
+        let length = Some(Dimension::Length);
+        let total = view.total("run", "distance", length).unwrap();
+        assert!((total - 20478.0).abs() < 1.0, "total {total}");
+        assert_eq!(view.total("run", "distance", None), None);
+        assert_eq!(view.total("run", "pace", length), None);
+    }
+
+    #[test]
+    fn mixed_units() {
+        let mut view = MetricsView::default();
+        for entry in [
+            "2023-07-03 10:00 11:00 run distance=10",
+            "2023-07-04 10:00 10:05 run distance=500m",
+            "2023-07-05 10:00 11:00 run distance=1km",
+            "2023-07-06 10:00 11:00 run distance=2kg",
+        ] {
+            view.update(&ChangeEvent::Added(record(entry)), &None);
+        }
+        let metrics: Vec<_> = view
+            .data()
+            .keys()
+            .map(|v| (v.dimension, v.unit()))
+            .collect();
+        assert_eq!(
+            metrics,
+            vec![
+                (None, None),
+                (Some(Dimension::Length), Some(Unit::Meter)),
+                (Some(Dimension::Mass), Some(Unit::Kilogram)),
+            ]
+        );
+        assert_eq!(view.total("run", "distance", None), Some(10.0));
+        assert_eq!(
+            view.total("run", "distance", Some(Dimension::Length)),
+            Some(1500.0)
+        );
+        assert_eq!(
+            view.total("run", "distance", Some(Dimension::Mass)),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let mut view = MetricsView::default();
+        for entry in [
+            "2023-07-03 10:00 11:00 run laps=400m,1km",
+            "2023-07-04 10:00 11:00 run laps=200m,3,2 with=anna,bob",
+        ] {
+            view.update(&ChangeEvent::Added(record(entry)), &None);
+        }
+        let length = Some(Dimension::Length);
+        let laps = view.series("run", "laps", length).unwrap();
+        assert_eq!(
+            laps.values().copied().collect::<Vec<_>>(),
+            vec![1400.0, 200.0]
+        );
+        assert_eq!(view.total("run", "laps", length), Some(1600.0));
+        assert_eq!(view.total("run", "laps", None), Some(5.0));
+        assert_eq!(view.series("run", "with", None), None);
+
+        // Deleting the entry removes all its items
+        view.update(
+            &ChangeEvent::Replaced {
+                from: record("2023-07-04 10:00 11:00 run laps=200m,3,2 with=anna,bob"),
+                to: record("2023-07-04 10:00 11:00 entry revision=2 deleted"),
+            },
+            &None,
+        );
+        assert_eq!(view.total("run", "laps", length), Some(1400.0));
+        assert_eq!(view.total("run", "laps", None), None);
+    }
+
+    #[test]
+    fn durations() {
+        let mut view = MetricsView::default();
+        for entry in [
+            "2023-07-03 10:00 11:00 run with=anna,bob",
+            "2023-07-04 10:00 10:30 run with=anna",
+            "2023-07-05 10:00 10:20 run with=bob distance=5",
+            "2023-07-05 12:00 13:00 read with=anna",
+        ] {
+            view.update(&ChangeEvent::Added(record(entry)), &None);
+        }
+        let durations = |view: &MetricsView| {
+            view.durations("run", "with")
+                .into_iter()
+                .map(|(k, v)| format!("{k} {v}"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(durations(&view), vec!["anna 90", "bob 80"]);
+        assert!(view.durations("run", "distance").is_empty());
+
+        // Edited entry is counted only with its current elements
+        view.update(
+            &ChangeEvent::Replaced {
+                from: record("2023-07-03 10:00 11:00 run with=anna,bob"),
+                to: record("2023-07-03 10:00 11:00 run with=bob. entry revision=2"),
+            },
+            &None,
+        );
+        assert_eq!(durations(&view), vec!["anna 30", "bob 80"]);
+    }
+}
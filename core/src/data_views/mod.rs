@@ -1,3 +1,6 @@
+pub mod metrics;
 pub mod query_results;
+pub mod skill_history;
 pub mod skills;
+pub mod vocabulary;
 pub mod week;
@@ -0,0 +1,166 @@
+use std::collections::btree_map::Iter;
+
+use crate::{
+    date_time::datetime::{DateDay, DateTimeRange},
+    db::Record,
+    progress::skill::{Skill, SkillProgress},
+};
+
+/// Number of weeks used to calculate recent pace for the forecast
+const PACE_WEEKS: usize = 4;
+
+/// History of the skill levels with a forecast of the next level. Unlike other views it's calculated
+/// on request as it's needed only for a single skill at a time
+#[derive(Debug, PartialEq)]
+pub struct SkillHistory {
+    levels: Vec<LevelReached>,
+    forecast: Option<LevelForecast>,
+}
+
+/// Day when skill reached certain level
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LevelReached {
+    pub level: usize,
+    pub day: DateDay,
+}
+
+/// Expected day when next level will be reached if practicing continues with the recent weekly pace
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LevelForecast {
+    pub level: usize,
+    pub day: DateDay,
+    pub minutes_per_week: usize,
+}
+
+impl SkillHistory {
+    pub fn new(skill: &Skill, all: Iter<DateTimeRange, Record>, now: DateDay) -> Self {
+        let pace_start = now.remove_days(PACE_WEEKS * 7);
+        let mut levels = Vec::new();
+        let mut minutes = 0;
+        let mut level = SkillProgress::new(0).level;
+        let mut recent_minutes = 0;
+        // Entries are sorted by date range, so levels are reached in the same order
+        for (_, record) in all {
+            let Record::Entry(entry) = record else {
+                continue;
+            };
+            if !entry.date_range().is_closed() || !skill.selector().matches(entry) {
+                continue;
+            }
+            let duration = entry.date_range().duration().minutes() as usize;
+            minutes += duration;
+            let day = entry.date_range().end().date();
+            if day > pace_start && day <= now {
+                recent_minutes += duration;
+            }
+            let level_now = SkillProgress::new(minutes).level;
+            // Long entries may give multiple levels at once
+            for reached in level + 1..=level_now {
+                levels.push(LevelReached {
+                    level: reached,
+                    day,
+                });
+            }
+            level = level_now;
+        }
+
+        let minutes_per_week = recent_minutes / PACE_WEEKS;
+        let forecast = if minutes_per_week == 0 {
+            None // No recent practice, next level won't be reached at this pace
+        } else {
+            let minutes_till_next = SkillProgress::new(minutes).minutes_till_next;
+            let days = (minutes_till_next * 7).div_ceil(minutes_per_week);
+            Some(LevelForecast {
+                level: level + 1,
+                day: now.add_days(days),
+                minutes_per_week,
+            })
+        };
+        SkillHistory { levels, forecast }
+    }
+
+    /// Levels reached so far starting from level 2, as level 1 is where every skill starts
+    pub fn levels(&self) -> &[LevelReached] {
+        &self.levels
+    }
+
+    pub fn forecast(&self) -> Option<&LevelForecast> {
+        self.forecast.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::record::Entry;
+
+    use super::*;
+
+    fn history(entries: &[&str], now: DateDay) -> SkillHistory {
+        let skill = Skill::from_record(
+            &Entry::parse("2023-07-01 00:00 00:00 run. skill kind=physical. Running").unwrap(),
+        )
+        .unwrap();
+        let all: BTreeMap<_, _> = entries
+            .iter()
+            .map(|v| {
+                let record = Record::parse(v).unwrap();
+                (*record.date_range(), record)
+            })
+            .collect();
+        SkillHistory::new(&skill, all.iter(), now)
+    }
+
+    #[test]
+    fn levels() {
+        let got = history(
+            &[
+                "2023-07-01 10:00 10:30 run",
+                "2023-07-02 10:00 11:00 run", // 90 minutes - level 2
+                "2023-07-02 12:00 12:00 read",
+                "2023-07-03 10:00 13:00 run", // 270 minutes - level 4
+            ],
+            DateDay::new(2023, 7, 3),
+        );
+        assert_eq!(
+            got.levels(),
+            &[
+                LevelReached {
+                    level: 2,
+                    day: DateDay::new(2023, 7, 2)
+                },
+                LevelReached {
+                    level: 3,
+                    day: DateDay::new(2023, 7, 3)
+                },
+                LevelReached {
+                    level: 4,
+                    day: DateDay::new(2023, 7, 3)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn forecast() {
+        // 4 hours within last 4 weeks is a pace of an hour per week
+        let got = history(
+            &["2023-06-10 10:00 12:00 run", "2023-07-01 10:00 12:00 run"],
+            DateDay::new(2023, 7, 3),
+        );
+        assert_eq!(got.levels().len(), 3); // 240 minutes is level 4, 43 minutes till the next one
+        assert_eq!(
+            got.forecast(),
+            Some(&LevelForecast {
+                level: 5,
+                day: DateDay::new(2023, 7, 9),
+                minutes_per_week: 60,
+            })
+        );
+
+        // No recent practice - no forecast
+        let got = history(&["2023-01-01 10:00 12:00 run"], DateDay::new(2023, 7, 3));
+        assert_eq!(got.forecast(), None);
+    }
+}
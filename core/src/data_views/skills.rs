@@ -6,7 +6,7 @@ use std::{
 use crate::{
     date_time::datetime::{DateDay, DateTimeRange},
     db::{ChangeEvent, Notification, Record, ViewUpdate},
-    progress::skill::{Skill, SkillProgress},
+    progress::skill::{Skill, SkillKind, SkillKinds, SkillProgress},
     record::Entry,
 };
 
@@ -14,6 +14,7 @@ use crate::{
 #[derive(Default)]
 pub struct SkillsView {
     data: BTreeMap<String, Skill>,
+    kinds: SkillKinds,
 }
 
 #[derive(PartialEq, Debug)]
@@ -21,10 +22,48 @@ pub struct SkillsUpdate {
     pub skill: String,
 }
 
-#[derive(PartialEq, Debug)]
+/// Structured notification about skill progress, so clients can localize and render it on their own.
+/// `Display` implementation provides default English text
+#[derive(PartialEq, Debug, Clone)]
 pub enum SkillsNotification {
-    LevelUp(String),
-    HourProgress(String),
+    /// Skill reached a new level
+    LevelUp {
+        skill: String,
+        level_before: usize,
+        level: usize,
+    },
+    /// Practiced time reached a checkpoint within the period. Without a skill it's a total of all skills
+    HourProgress {
+        skill: Option<String>,
+        period: Period,
+        hours: usize,
+    },
+}
+
+impl Display for SkillsNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkillsNotification::LevelUp { skill, level, .. } => {
+                f.write_fmt(format_args!("{} level increased to {}", skill, level))
+            }
+            SkillsNotification::HourProgress {
+                skill: Some(skill),
+                period,
+                hours,
+            } => f.write_fmt(format_args!(
+                "Great job - you've practiced {} hours of {} {}",
+                hours, skill, period
+            )),
+            SkillsNotification::HourProgress {
+                skill: None,
+                period,
+                hours,
+            } => f.write_fmt(format_args!(
+                "Great job - across all skills you've practiced {} hours {}",
+                hours, period
+            )),
+        }
+    }
 }
 
 // TODO Skills view become quite complex, we should refactor it and split into multiple structs
@@ -45,20 +84,29 @@ impl SkillsView {
                 to: Record::Entry(to),
             } => {
                 // It's a replacement, remove previous entry from calculation and continue processing new one as usual
-                self.delete_entry(from, on_view_update);
+                self.delete_entry(from, all.clone(), on_view_update);
                 to
             }
             _ => return, // TODO Handle conflicts
         };
 
+        if let Some(kind) = SkillKind::from_record(entry) {
+            // Kind definition got changed, all the skills of this kind need to pick it up
+            let name = kind.name().to_string();
+            self.kinds.add(kind);
+            self.update_kinds(&name, on_view_update);
+            return;
+        }
+
         if let Some(mut skill) = Skill::from_record(entry) {
+            skill.set_kind(self.kinds.get(skill.kind().name()));
             // If it's a Skill - go back and re-read all previous record to accumulate duration
             for (_, record) in all.clone() {
                 let Record::Entry(entry) = record else {
                     continue;
                 };
-                if skill.selector().matches(entry) {
-                    skill.add_duration(entry.date_range.duration());
+                if entry.date_range.is_closed() && skill.selector().matches(entry) {
+                    skill.add_practice(&entry.date_range);
                 }
             }
             self.data.insert(skill.title().to_string(), skill.clone());
@@ -67,10 +115,13 @@ impl SkillsView {
                 self.process_notification(&skill, on_notification, now, all.clone(), None, true)
             }
         } else {
+            if !entry.date_range.is_closed() {
+                return; // Point in time and running entries have no duration and aren't counted as a practice
+            }
             // If it's a record - add it to the corresponding Skill if exists
             for (_, skill) in self.data.iter_mut() {
                 if skill.selector().matches(entry) {
-                    skill.add_duration(entry.date_range.duration());
+                    skill.add_practice(&entry.date_range);
                 }
             }
             // Second iteration to notify about skills progress
@@ -97,16 +148,44 @@ impl SkillsView {
         }
     }
 
-    fn delete_entry(&mut self, entry: &Entry, on_view_update: &Option<Box<dyn Fn(ViewUpdate)>>) {
+    fn delete_entry(
+        &mut self,
+        entry: &Entry,
+        all: Iter<DateTimeRange, Record>,
+        on_view_update: &Option<Box<dyn Fn(ViewUpdate)>>,
+    ) {
+        if let Some(kind) = SkillKind::from_record(entry) {
+            self.kinds.remove(kind.name());
+            self.update_kinds(kind.name(), on_view_update);
+            return;
+        }
         if let Some(skill) = Skill::from_record(entry) {
             self.data.remove(skill.title());
             self.process_update(&skill, on_view_update);
             return;
         }
+        if !entry.date_range.is_closed() {
+            return;
+        }
         // If it's a record - remove it from the corresponding skills if any
         for (_, skill) in self.data.iter_mut() {
             if skill.selector().matches(entry) {
                 skill.remove_duration(entry.date_range.duration());
+                if skill.last_practiced() == Some(entry.date_range.end().date()) {
+                    // Removed entry could be the last practice, find the previous one. Rare case, so rescan is fine
+                    let last_practiced = all
+                        .clone()
+                        .filter_map(|(_, record)| match record {
+                            Record::Entry(v)
+                                if v.date_range.is_closed() && skill.selector().matches(v) =>
+                            {
+                                Some(v.date_range.end().date())
+                            }
+                            _ => None,
+                        })
+                        .max();
+                    skill.set_last_practiced(last_practiced);
+                }
                 // self.process_update cannot be used as self is borrowed as mut inside a loop
                 if let Some(on_view_update) = on_view_update {
                     on_view_update(ViewUpdate::Skills(SkillsUpdate {
@@ -117,6 +196,24 @@ impl SkillsView {
         }
     }
 
+    fn update_kinds(&mut self, kind: &str, on_view_update: &Option<Box<dyn Fn(ViewUpdate)>>) {
+        for (_, skill) in self.data.iter_mut() {
+            if skill.kind().name() != kind {
+                continue;
+            }
+            skill.set_kind(self.kinds.get(kind));
+            if let Some(on_view_update) = on_view_update {
+                on_view_update(ViewUpdate::Skills(SkillsUpdate {
+                    skill: skill.title().to_string(),
+                }))
+            }
+        }
+    }
+
+    pub fn kinds(&self) -> &SkillKinds {
+        &self.kinds
+    }
+
     pub fn data(&self) -> &BTreeMap<String, Skill> {
         // TODO Now Skills are sorted by it's Title, it should be sorted by our custom logic, see `Skill::Ord`
         &self.data
@@ -146,11 +243,11 @@ impl SkillsView {
             SkillProgress::new(skill.progress().duration_minutes - v.duration().minutes() as usize)
         });
         if progress_before.level < progress_now.level {
-            on_notification(Notification::Skills(SkillsNotification::LevelUp(format!(
-                "{} level increased to {}",
-                skill.title(),
-                progress_now.level
-            ))))
+            on_notification(Notification::Skills(SkillsNotification::LevelUp {
+                skill: skill.title().to_string(),
+                level_before: progress_before.level,
+                level: progress_now.level,
+            }))
         }
 
         // Accumulate time
@@ -176,7 +273,7 @@ impl SkillsView {
         } else {
             vec![] // When total notification is disabled we skip any calculation of it
         };
-        let mut checkpoints_skills = vec![
+        let mut checkpoints_skills = [
             Checkpoint::by_skill(now, Period::Lifetime, &[], 100, skill.title().to_string()),
             Checkpoint::by_skill(now, Period::Year, &[], 50, skill.title().to_string()),
             Checkpoint::by_skill(now, Period::Month, &[], 10, skill.title().to_string()),
@@ -204,14 +301,26 @@ impl SkillsView {
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum Period {
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Period {
     Lifetime,
     Year,
     Month,
     Week,
 }
 
+impl Period {
+    /// Stable identifier of the period, could be used by clients as a localization key
+    pub fn name(&self) -> &'static str {
+        match self {
+            Period::Lifetime => "lifetime",
+            Period::Year => "year",
+            Period::Month => "month",
+            Period::Week => "week",
+        }
+    }
+}
+
 impl Display for Period {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
@@ -294,17 +403,11 @@ impl Checkpoint {
         on_notification: &dyn Fn(Notification),
     ) {
         let notify = |hours| {
-            let msg = match &self.skill {
-                Some(skill) => format!(
-                    "Great job - you've practiced {} hours of {} {}",
-                    hours, skill, self.period
-                ),
-                None => format!(
-                    "Great job - across all skills you've practiced {} hours {}",
-                    hours, self.period
-                ),
-            };
-            on_notification(Notification::Skills(SkillsNotification::HourProgress(msg)));
+            on_notification(Notification::Skills(SkillsNotification::HourProgress {
+                skill: self.skill.clone(),
+                period: self.period,
+                hours,
+            }));
         };
 
         // We've processed all events and already added an entry_duration, starting point is without it
@@ -364,11 +467,7 @@ mod tests {
             record
         }
 
-        fn check_notification(
-            &mut self,
-            event: ChangeEvent,
-            now: Option<DateDay>,
-        ) -> Vec<SkillsNotification> {
+        fn check_notification(&mut self, event: ChangeEvent, now: Option<DateDay>) -> Vec<String> {
             match &event {
                 ChangeEvent::Added(record) => {
                     self.records.insert(*record.date_range(), record.clone())
@@ -389,8 +488,9 @@ mod tests {
                     match got {
                         Notification::Skills(update) => {
                             let mut foo = called_clone.borrow_mut();
-                            foo.push(update);
+                            foo.push(update.to_string());
                         }
+                        Notification::Nudge(_) | Notification::Schema(_) => unreachable!(),
                     };
                 })),
             );
@@ -419,7 +519,7 @@ mod tests {
                 ChangeEvent::Added(Record::parse("2022-06-06 10:00 12:00 run").unwrap()),
                 now
             ),
-            vec![]
+            Vec::<String>::new()
         );
 
         // Adding skill afterwards recalculates all previously added entities
@@ -431,9 +531,7 @@ mod tests {
                 ),
                 now
             ),
-            vec![SkillsNotification::LevelUp(
-                "Running level increased to 2".to_string()
-            )]
+            vec!["Running level increased to 2"]
         );
 
         // Adding not enough for level up
@@ -442,7 +540,7 @@ mod tests {
                 ChangeEvent::Added(Record::parse("2022-06-06 14:00 14:05 run").unwrap()),
                 now
             ),
-            vec![]
+            Vec::<String>::new()
         );
 
         // Adding more to cause another level up
@@ -451,9 +549,7 @@ mod tests {
                 ChangeEvent::Added(Record::parse("2022-06-06 15:00 17:00 run").unwrap()),
                 now
             ),
-            vec![SkillsNotification::LevelUp(
-                "Running level increased to 4".to_string()
-            )]
+            vec!["Running level increased to 4"]
         );
     }
 
@@ -468,7 +564,7 @@ mod tests {
                 ChangeEvent::Added(Record::parse("2022-06-08 00:00 23:00 foo").unwrap()),
                 now
             ),
-            vec![]
+            Vec::<String>::new()
         );
 
         // Total time in a week
@@ -490,13 +586,9 @@ mod tests {
                 now
             ),
             vec![
-                SkillsNotification::LevelUp("Bar1 level increased to 13".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 10 hours of Bar1 this month".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 15 hours of Bar1 this week".to_string()
-                )
+                "Bar1 level increased to 13",
+                "Great job - you've practiced 10 hours of Bar1 this month",
+                "Great job - you've practiced 15 hours of Bar1 this week"
             ]
         );
 
@@ -507,13 +599,46 @@ mod tests {
                 now
             ),
             vec![
-                SkillsNotification::LevelUp("Bar2 level increased to 5".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - across all skills you've practiced 20 hours this week".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 5 hours of Bar2 this week".to_string()
-                ),
+                "Bar2 level increased to 5",
+                "Great job - across all skills you've practiced 20 hours this week",
+                "Great job - you've practiced 5 hours of Bar2 this week",
+            ]
+        );
+    }
+
+    #[test]
+    fn structured_notifications() {
+        let mut view = TestSkillView::default();
+        view.add("2023-07-13 00:00 00:00 run. skill kind=physical. Running");
+        let called = Rc::new(RefCell::new(Vec::new()));
+        let called_clone = called.clone();
+        let record = Record::parse("2023-07-13 00:00 05:00 run").unwrap();
+        view.records.insert(*record.date_range(), record.clone());
+        view.skill_view.update(
+            view.records.iter(),
+            &ChangeEvent::Added(record),
+            true,
+            Some(DateDay::new(2023, 7, 13)),
+            &None,
+            &Some(Box::new(move |got| {
+                if let Notification::Skills(got) = got {
+                    called_clone.borrow_mut().push(got);
+                }
+            })),
+        );
+        assert_eq!(
+            called.take(),
+            vec![
+                SkillsNotification::LevelUp {
+                    skill: "Running".to_string(),
+                    level_before: 1,
+                    level: 5
+                },
+                SkillsNotification::HourProgress {
+                    skill: Some("Running".to_string()),
+                    period: Period::Week,
+                    hours: 5
+                }
             ]
         );
     }
@@ -532,16 +657,10 @@ mod tests {
                 Some(DateDay::new(2023, 7, 18))
             ),
             vec![
-                SkillsNotification::LevelUp("Running level increased to 17".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - across all skills you've practiced 20 hours this week".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 30 hours of Running this month".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Running this week".to_string()
-                )
+                "Running level increased to 17",
+                "Great job - across all skills you've practiced 20 hours this week",
+                "Great job - you've practiced 30 hours of Running this month",
+                "Great job - you've practiced 20 hours of Running this week"
             ]
         );
     }
@@ -562,23 +681,13 @@ mod tests {
                 Some(DateDay::new(2023, 7, 13))
             ),
             vec![
-                SkillsNotification::LevelUp("Running level increased to 13".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - across all skills you've practiced 20 hours this week".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Running this month".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Running this week".to_string()
-                ),
-                SkillsNotification::LevelUp("Swimming level increased to 13".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Swimming this month".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Swimming this week".to_string()
-                )
+                "Running level increased to 13",
+                "Great job - across all skills you've practiced 20 hours this week",
+                "Great job - you've practiced 20 hours of Running this month",
+                "Great job - you've practiced 20 hours of Running this week",
+                "Swimming level increased to 13",
+                "Great job - you've practiced 20 hours of Swimming this month",
+                "Great job - you've practiced 20 hours of Swimming this week"
             ]
         );
     }
@@ -601,11 +710,25 @@ mod tests {
                 },
                 Some(DateDay::new(2023, 7, 13))
             ),
-            vec![]
+            Vec::<String>::new()
         );
         view.check_skills(vec![("Running", 0)]);
     }
 
+    #[test]
+    fn entries_without_duration() {
+        let mut view = TestSkillView::default();
+        view.add("2023-07-13 00:00 00:00 run. skill kind=physical. Running");
+        view.add("2023-07-13 00:00 01:00 run");
+        // Measurements aren't a practice and don't count towards the skill
+        view.add("2023-07-14 08:00 run heartrate=52");
+        // Running entries count only once stopped
+        view.add("2023-07-15 08:00 - run");
+        view.check_skills(vec![("Running", 60)]);
+        let skill = view.skill_view.data.get("Running").unwrap();
+        assert_eq!(skill.last_practiced(), Some(DateDay::new(2023, 7, 13)));
+    }
+
     #[test]
     fn delete_skill() {
         let mut view = TestSkillView::default();
@@ -625,7 +748,7 @@ mod tests {
                 },
                 Some(DateDay::new(2023, 7, 13))
             ),
-            vec![]
+            Vec::<String>::new()
         );
         view.check_skills(vec![("Running2", 0)]);
 
@@ -636,13 +759,9 @@ mod tests {
                 Some(DateDay::new(2023, 7, 13))
             ),
             vec![
-                SkillsNotification::LevelUp("Running2 level increased to 8".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 10 hours of Running2 this month".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 10 hours of Running2 this week".to_string()
-                ),
+                "Running2 level increased to 8",
+                "Great job - you've practiced 10 hours of Running2 this month",
+                "Great job - you've practiced 10 hours of Running2 this week",
             ]
         );
         view.check_skills(vec![("Running2", 600)]);
@@ -667,16 +786,10 @@ mod tests {
                 Some(DateDay::new(2023, 7, 13))
             ),
             vec![
-                SkillsNotification::LevelUp("Running level increased to 13".to_string()),
-                SkillsNotification::HourProgress(
-                    "Great job - across all skills you've practiced 20 hours this week".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Running this month".to_string()
-                ),
-                SkillsNotification::HourProgress(
-                    "Great job - you've practiced 20 hours of Running this week".to_string()
-                ),
+                "Running level increased to 13",
+                "Great job - across all skills you've practiced 20 hours this week",
+                "Great job - you've practiced 20 hours of Running this month",
+                "Great job - you've practiced 20 hours of Running this week",
             ]
         );
         view.check_skills(vec![("Running", 1200)]);
@@ -690,13 +803,49 @@ mod tests {
                 },
                 Some(DateDay::new(2023, 7, 13))
             ),
-            vec![SkillsNotification::LevelUp(
-                "Running level increased to 2".to_string()
-            )]
+            vec!["Running level increased to 2"]
         );
         view.check_skills(vec![("Running", 90)]);
     }
 
+    #[test]
+    fn custom_kinds() {
+        let mut view = TestSkillView::default();
+        let kind = |view: &TestSkillView, skill: &str| {
+            let kind = view.skill_view.data.get(skill).unwrap().kind();
+            (kind.name().to_string(), kind.order())
+        };
+
+        // Skill with not yet defined kind is still tracked
+        view.add("2023-07-13 00:00 00:00 talk. skill kind=social. Talking");
+        assert_eq!(
+            kind(&view, "Talking"),
+            ("social".to_string(), SkillKind::DEFAULT_ORDER)
+        );
+
+        // Defining the kind updates existing skills
+        let definition = view.add("2023-07-13 00:01 00:01 skillkind name=social order=3. Social");
+        assert_eq!(kind(&view, "Talking"), ("social".to_string(), 3));
+        assert_eq!(view.skill_view.kinds().get("social").title(), "Social");
+
+        // New skills pick up the defined kind right away
+        view.add("2023-07-13 00:02 00:02 meet. skill kind=social. Meeting");
+        assert_eq!(kind(&view, "Meeting"), ("social".to_string(), 3));
+
+        // Deleting the definition falls back to the default order
+        view.check_notification(
+            ChangeEvent::Replaced {
+                from: definition.clone(),
+                to: Record::parse(&definition.to_deleted_string()).unwrap(),
+            },
+            None,
+        );
+        assert_eq!(
+            kind(&view, "Talking"),
+            ("social".to_string(), SkillKind::DEFAULT_ORDER)
+        );
+    }
+
     #[test]
     fn add_to_previous_month() {
         let mut view = TestSkillView::default();
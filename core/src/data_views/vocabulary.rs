@@ -0,0 +1,296 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{
+    db::{ChangeEvent, Record},
+    parsing::tokenizer::{Token, Tokenizer},
+    record::{Entry, PropVal},
+};
+
+/*
+Vocabulary is an index of all tag names, property names and property values used in the journal with the
+usage counts. It's used for autocompletion, so users pick existing names instead of creating a new tag
+by a typo. Suggestions are context aware and depend on the token under the cursor:
+- Tag name position: `2023-07-03 10:00 11:00 ru|`, `run. |` - tags sorted by usage
+- Property name position: `run |`, `run dist|` - properties of the current tag
+- Property value position: `run place=|` - values of the current tag property
+*/
+
+#[derive(Default, Debug)]
+struct PropWords {
+    count: usize,
+    values: BTreeMap<String, usize>,
+}
+
+#[derive(Default, Debug)]
+struct TagWords {
+    count: usize,
+    props: BTreeMap<String, PropWords>,
+}
+
+#[derive(Default)]
+pub struct VocabularyView {
+    tags: BTreeMap<String, TagWords>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompletionKind {
+    Tag,
+    Prop,
+    Value,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    pub text: String,
+    /// How many times it was used in the journal, completions are sorted by it
+    pub count: usize,
+    /// Byte range of the input that should be replaced with the completion text
+    pub replace: Range<usize>,
+}
+
+impl VocabularyView {
+    pub fn update(&mut self, event: &ChangeEvent) {
+        match event {
+            ChangeEvent::Added(Record::Entry(entry)) => self.add_entry(entry),
+            ChangeEvent::Replaced { from, to } => {
+                if let Record::Entry(from) = from {
+                    self.remove_entry(from);
+                }
+                if let Record::Entry(to) = to {
+                    self.add_entry(to);
+                }
+            }
+            _ => {} // TODO Handle conflicts
+        }
+    }
+
+    fn add_entry(&mut self, entry: &Entry) {
+        for tag in entry.tags.iter().filter(|v| v.name != "entry") {
+            let tag_words = self.tags.entry(tag.name.clone()).or_default();
+            tag_words.count += 1;
+            for prop in &tag.props {
+                let prop_words = tag_words.props.entry(prop.name.clone()).or_default();
+                prop_words.count += 1;
+                // List items are counted separately, so `with=anna,bob` suggests both names
+                for val in prop.val.items().iter().filter(|v| **v != PropVal::None) {
+                    *prop_words.values.entry(val.to_string()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    fn remove_entry(&mut self, entry: &Entry) {
+        for tag in entry.tags.iter().filter(|v| v.name != "entry") {
+            let Some(tag_words) = self.tags.get_mut(&tag.name) else {
+                continue;
+            };
+            for prop in &tag.props {
+                let Some(prop_words) = tag_words.props.get_mut(&prop.name) else {
+                    continue;
+                };
+                for val in prop.val.items().iter().filter(|v| **v != PropVal::None) {
+                    let value = val.to_string();
+                    if let Some(count) = prop_words.values.get_mut(&value) {
+                        *count -= 1;
+                        if *count == 0 {
+                            prop_words.values.remove(&value);
+                        }
+                    }
+                }
+                prop_words.count -= 1;
+                if prop_words.count == 0 {
+                    tag_words.props.remove(&prop.name);
+                }
+            }
+            tag_words.count -= 1;
+            if tag_words.count == 0 {
+                self.tags.remove(&tag.name);
+            }
+        }
+    }
+
+    /// Returns completions for the input at the cursor byte position. Entries start with a date, queries don't
+    pub fn complete(&self, input: &str, cursor: usize, is_entry: bool) -> Vec<Completion> {
+        let Some(before) = input.get(..cursor) else {
+            return vec![];
+        };
+        let tokenizer = Tokenizer::new(before, is_entry);
+        let chars: Vec<_> = before.char_indices().collect();
+        if tokenizer.tokens.len() < chars.len() {
+            return vec![]; // Input before the cursor is invalid, nothing to suggest
+        }
+        let tokens: Vec<_> = chars.iter().zip(tokenizer.tokens).collect();
+
+        // Word right before the cursor that user is typing now, if any
+        let word_token = tokens.last().map(|(_, token)| *token);
+        let word_start = tokens
+            .iter()
+            .rev()
+            .take_while(|(_, token)| Some(*token) == word_token)
+            .last()
+            .map(|((idx, _), _)| *idx)
+            .unwrap_or(cursor);
+        let word = |token: Token| {
+            tokens
+                .iter()
+                .rev()
+                .skip_while(|(_, v)| *v != token)
+                .take_while(|(_, v)| *v == token)
+                .map(|((_, c), _)| c)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect::<String>()
+        };
+        let tag = word(Token::TagName);
+        let prop = word(Token::PropertyName);
+
+        let mut listed: Vec<String> = Vec::new();
+        let (kind, prefix, replace) = match word_token {
+            Some(Token::TagName) => (
+                CompletionKind::Tag,
+                &before[word_start..],
+                word_start..cursor,
+            ),
+            Some(Token::PropertyName) => (
+                CompletionKind::Prop,
+                &before[word_start..],
+                word_start..cursor,
+            ),
+            Some(Token::PropertyValue) => {
+                // Only the last item of the list is completed, items already in the list aren't suggested
+                let item_start = before[word_start..]
+                    .rfind(',')
+                    .map(|v| word_start + v + 1)
+                    .unwrap_or(word_start);
+                listed.extend(
+                    before[word_start..item_start]
+                        .split(',')
+                        .map(|v| v.to_string()),
+                );
+                (
+                    CompletionKind::Value,
+                    &before[item_start..],
+                    item_start..cursor,
+                )
+            }
+            Some(Token::PropertyOperator) => (CompletionKind::Value, "", cursor..cursor),
+            Some(Token::TagSeparator) | None => (CompletionKind::Tag, "", cursor..cursor),
+            Some(Token::Space) => {
+                // After the space it depends on what was before it
+                match tokens.iter().rev().find(|(_, v)| *v != Token::Space) {
+                    Some((_, Token::TagName | Token::PropertyName | Token::PropertyValue)) => {
+                        (CompletionKind::Prop, "", cursor..cursor)
+                    }
+                    Some((_, Token::PropertyOperator)) => {
+                        (CompletionKind::Value, "", cursor..cursor)
+                    }
+                    Some((_, Token::Comment)) => return vec![],
+                    _ => (CompletionKind::Tag, "", cursor..cursor),
+                }
+            }
+            _ => return vec![], // Dates, times and comments aren't completed
+        };
+
+        let candidates: Vec<(&String, usize)> = match kind {
+            CompletionKind::Tag => self.tags.iter().map(|(k, v)| (k, v.count)).collect(),
+            CompletionKind::Prop => match self.tags.get(&tag) {
+                Some(words) => words.props.iter().map(|(k, v)| (k, v.count)).collect(),
+                None => vec![],
+            },
+            CompletionKind::Value => match self.tags.get(&tag).and_then(|v| v.props.get(&prop)) {
+                Some(words) => words.values.iter().map(|(k, v)| (k, *v)).collect(),
+                None => vec![],
+            },
+        };
+        let mut completions: Vec<_> = candidates
+            .into_iter()
+            .filter(|(text, _)| text.starts_with(prefix) && text.as_str() != prefix)
+            .filter(|(text, _)| !listed.contains(text))
+            .map(|(text, count)| Completion {
+                kind,
+                text: text.clone(),
+                count,
+                replace: replace.clone(),
+            })
+            .collect();
+        completions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+        completions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn vocabulary(entries: &[&str]) -> VocabularyView {
+        let mut view = VocabularyView::default();
+        for entry in entries {
+            view.update(&ChangeEvent::Added(Record::parse(entry).unwrap()));
+        }
+        view
+    }
+
+    fn complete(view: &VocabularyView, input: &str) -> Vec<String> {
+        view.complete(input, input.len(), true)
+            .into_iter()
+            .map(|v| format!("{} {}", v.text, v.count))
+            .collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn completions() {
+        let view = vocabulary(&[
+            "2023-07-03 10:00 11:00 run distance=5 place=park",
+            "2023-07-03 12:00 13:00 run distance=10 place=park. entry revision=2",
+            "2023-07-03 14:00 15:00 read book=\"War and peace\"",
+            "2023-07-03 16:00 17:00 rest",
+            "2023-07-03 18:00 19:00 run place=forest,park",
+        ]);
+        let prefix = "2023-07-04 10:00 11:00 ";
+        let cases = vec![
+            ("r", vec!["run 3", "read 1", "rest 1"]),
+            ("re", vec!["read 1", "rest 1"]),
+            ("run", vec![]),
+            ("run place=forest,", vec!["park 3"]),
+            ("run place=park,f", vec!["forest 1"]),
+            ("read. ", vec!["run 3", "read 1", "rest 1"]),
+            ("run ", vec!["place 3", "distance 2"]),
+            ("run p", vec!["place 3"]),
+            ("run place=", vec!["park 3", "forest 1"]),
+            ("run distance=1", vec!["10 1"]),
+            ("read book=", vec!["\"War and peace\" 1"]),
+            ("run distance=5 ", vec!["place 3", "distance 2"]),
+            ("run. Comm", vec![]),
+            ("unknown ", vec![]),
+        ];
+        for (input, want) in cases {
+            let input = format!("{prefix}{input}");
+            assert_eq!(complete(&view, &input), want, "input |{input}|");
+        }
+
+        // Replace range covers the word under the cursor
+        let input = format!("{prefix}run pl");
+        let got = view.complete(&input, input.len(), true);
+        assert_eq!(got[0].replace, input.len() - 2..input.len());
+
+        // Queries have no date
+        let got = view.complete("run. rea", 8, false);
+        assert_eq!(got[0].text, "read");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn replaced_entries() {
+        let mut view = vocabulary(&["2023-07-03 10:00 11:00 rnu"]);
+        view.update(&ChangeEvent::Replaced {
+            from: Record::parse("2023-07-03 10:00 11:00 rnu").unwrap(),
+            to: Record::parse("2023-07-03 10:00 11:00 run. entry revision=2").unwrap(),
+        });
+        assert_eq!(complete(&view, "2023-07-04 10:00 11:00 r"), vec!["run 1"]);
+    }
+}
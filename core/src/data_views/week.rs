@@ -11,6 +11,7 @@ use crate::{
 #[derive(Default)]
 pub struct WeekView {
     data: BTreeMap<String, WeekProgress>,
+    week_start: Option<DateDay>,
 }
 
 #[derive(Debug)]
@@ -45,6 +46,7 @@ impl WeekView {
         on_view_update: &Option<Box<dyn Fn(ViewUpdate)>>,
     ) {
         let week_start = now.as_start_of_week();
+        self.week_start = Some(week_start);
         let entry = match event {
             ChangeEvent::Added(Record::Entry(entry)) => entry,
             ChangeEvent::Replaced {
@@ -69,7 +71,7 @@ impl WeekView {
                 let Record::Entry(entry) = record else {
                     continue;
                 };
-                if skill.selector().matches(entry) {
+                if entry.date_range.is_closed() && skill.selector().matches(entry) {
                     progress += entry.date_range.duration().minutes();
                 }
             }
@@ -84,8 +86,8 @@ impl WeekView {
                 on_view_update(ViewUpdate::Week)
             }
         } else {
-            if entry.date_range.start().date() < week_start {
-                return; // Entry is too old and not relevant for the week - skip
+            if entry.date_range.start().date() < week_start || !entry.date_range.is_closed() {
+                return; // Entry is too old or has no duration, not relevant for the week - skip
             }
             for (_, week_progress) in self.data.iter_mut() {
                 if week_progress.skill.selector().matches(entry) {
@@ -106,6 +108,9 @@ impl WeekView {
             }
             return;
         }
+        if !entry.date_range.is_closed() {
+            return;
+        }
         // If it's a record - remove it from the corresponding skills if any
         for (_, skill) in self.data.iter_mut() {
             if skill.skill.selector().matches(entry) {
@@ -120,6 +125,11 @@ impl WeekView {
     pub fn data(&self) -> &BTreeMap<String, WeekProgress> {
         &self.data
     }
+
+    /// First day of the week for which progress was calculated, if any
+    pub fn week_start(&self) -> Option<DateDay> {
+        self.week_start
+    }
 }
 
 #[cfg(test)]
@@ -1,7 +1,7 @@
 use std::{
     cmp::Ordering,
     fmt::{Debug, Display, Formatter},
-    ops::Sub,
+    ops::{Add, Sub},
     str::FromStr,
 };
 
@@ -9,15 +9,28 @@ use crate::date_time::timestamp::Timestamp;
 
 /// Date time range with start and end, format YYYY-MM-DD HH:MM - YYYY-MM-DD HH:MM
 /// If day is the same then short notation format is supported: YYYY-MM-DD HH:MM HH:MM
+/// Point in time entries (e.g. measurements) have no end at all: YYYY-MM-DD HH:MM
+/// Running entries (e.g. started timer) have an end which is not known yet: YYYY-MM-DD HH:MM -
+/// Start with a duration is supported as well, but resolved to the end right away: YYYY-MM-DD HH:MM +1h30m
 #[derive(PartialEq, Clone, Copy, Eq)]
 pub struct DateTimeRange {
     start: DateTime,
     end: DateTime,
+    kind: RangeKind,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+enum RangeKind {
+    Closed,
+    Instant,
+    Running,
 }
 
 impl DateTimeRange {
     pub const SIZE_LONG: usize = 35;
     pub const SIZE_SHORT: usize = 22;
+    pub const SIZE_INSTANT: usize = DateTime::SIZE;
+    pub const SIZE_RUNNING: usize = DateTime::SIZE + 2;
     pub const SIZE_SEPARATOR: usize = 3;
 
     /// Creates new DateTimeRange, returns error when end is less than start
@@ -25,7 +38,48 @@ impl DateTimeRange {
         if end < start {
             return Err("end time cannot be before the start");
         }
-        Ok(Self { start, end })
+        Ok(Self {
+            start,
+            end,
+            kind: RangeKind::Closed,
+        })
+    }
+
+    /// Creates point in time DateTimeRange which has zero duration and no end
+    pub fn instant(at: DateTime) -> Self {
+        Self {
+            start: at,
+            end: at,
+            kind: RangeKind::Instant,
+        }
+    }
+
+    /// Creates DateTimeRange which is started, but not finished yet. Duration is zero until it's stopped
+    pub fn running(start: DateTime) -> Self {
+        Self {
+            start,
+            end: start,
+            kind: RangeKind::Running,
+        }
+    }
+
+    /// Resolves running DateTimeRange to the finished one
+    pub fn stop(&self, end: DateTime) -> Result<Self, &'static str> {
+        if !self.is_running() {
+            return Err("only running date range can be stopped");
+        }
+        Self::new(self.start, end)
+    }
+
+    pub fn is_instant(&self) -> bool {
+        self.kind == RangeKind::Instant
+    }
+    pub fn is_running(&self) -> bool {
+        self.kind == RangeKind::Running
+    }
+    /// If range has both start and end, so it has a duration
+    pub fn is_closed(&self) -> bool {
+        self.kind == RangeKind::Closed
     }
     pub fn start(&self) -> DateTime {
         self.start
@@ -36,11 +90,20 @@ impl DateTimeRange {
     pub fn duration(&self) -> Duration {
         self.end - self.start
     }
+    /// Checks if closed ranges share some time. Ranges touching each other don't overlap, point in time and
+    /// running ranges never overlap
+    pub fn overlaps(&self, other: &DateTimeRange) -> bool {
+        self.is_closed() && other.is_closed() && self.start < other.end && other.start < self.end
+    }
 }
 
 impl Display for DateTimeRange {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if self.start.date() == self.end.date() {
+        if self.is_instant() {
+            f.write_fmt(format_args!("{}", self.start))
+        } else if self.is_running() {
+            f.write_fmt(format_args!("{} -", self.start))
+        } else if self.start.date() == self.end.date() {
             f.write_fmt(format_args!("{} {}", self.start, self.end.time()))
         } else {
             f.write_fmt(format_args!("{} - {}", self.start, self.end))
@@ -59,6 +122,7 @@ impl Ord for DateTimeRange {
         self.start
             .cmp(&other.start)
             .then_with(|| self.end.cmp(&other.end))
+            .then_with(|| self.kind.cmp(&other.kind))
     }
 }
 
@@ -72,16 +136,28 @@ impl FromStr for DateTimeRange {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let start = s[0..DateTime::SIZE].parse::<DateTime>()?;
-        if s.len() == Self::SIZE_LONG {
-            let end = s[DateTime::SIZE + Self::SIZE_SEPARATOR..].parse::<DateTime>()?;
+        let unsupported = || "Not supported date time range length of the string".to_string();
+        // Checked slicing as input may contain multibyte chars
+        let start = s.get(0..DateTime::SIZE).ok_or_else(unsupported)?;
+        let start = start.parse::<DateTime>()?;
+        let rest = s.get(DateTime::SIZE..).ok_or_else(unsupported)?;
+        if let Some(duration) = rest.strip_prefix(" +") {
+            let duration = duration.parse::<Duration>()?;
+            Self::new(start, start + duration).map_err(|v| v.to_string())
+        } else if s.len() == Self::SIZE_INSTANT {
+            Ok(Self::instant(start))
+        } else if s.len() == Self::SIZE_RUNNING && s.ends_with(" -") {
+            Ok(Self::running(start))
+        } else if s.len() == Self::SIZE_LONG {
+            let end = rest.get(Self::SIZE_SEPARATOR..).ok_or_else(unsupported)?;
+            let end = end.parse::<DateTime>()?;
             Self::new(start, end).map_err(|v| v.to_string())
         } else if s.len() == Self::SIZE_SHORT {
-            let time = s[DateTime::SIZE + 1..].parse::<Time>()?;
+            let time = rest.get(1..).ok_or_else(unsupported)?.parse::<Time>()?;
             let end = DateTime::new(start.date(), time);
             Self::new(start, end).map_err(|v| v.to_string())
         } else {
-            Err("Not supported date time range length of the string".to_string())
+            Err(unsupported())
         }
     }
 }
@@ -102,12 +178,24 @@ impl DateTime {
         let datetime = time::PrimitiveDateTime::new(date.0, time.0);
         Self(datetime)
     }
+    /// Converts timestamp to the date time with given offset from UTC, seconds are truncated
+    pub fn from_timestamp(timestamp: Timestamp, utc_offset_minutes: i32) -> Self {
+        let utc = time::OffsetDateTime::from_unix_timestamp((timestamp.as_u64() / 1000) as i64)
+            .expect("Timestamp should be within supported range");
+        let local = utc + time::Duration::minutes(utc_offset_minutes.into());
+        let time = time::Time::from_hms(local.hour(), local.minute(), 0).expect("valid time");
+        DateTime::new(DateDay(local.date()), Time(time))
+    }
     pub fn date(&self) -> DateDay {
         DateDay(self.0.date())
     }
     pub fn time(&self) -> Time {
         Time(self.0.time())
     }
+    /// Adds the duration, None if the result is out of supported range
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_add(duration.0).map(Self)
+    }
 }
 
 impl Display for DateTime {
@@ -122,12 +210,21 @@ impl FromStr for DateTime {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let date = s[0..10].parse::<DateDay>()?;
-        let time = s[11..].parse::<Time>()?;
+        let err = || format!("Date time has to be in format YYYY-MM-DD HH:MM, got {s}");
+        let date = s.get(0..10).ok_or_else(err)?.parse::<DateDay>()?;
+        let time = s.get(11..).ok_or_else(err)?.parse::<Time>()?;
         Ok(DateTime::new(date, time))
     }
 }
 
+impl Add<Duration> for DateTime {
+    type Output = DateTime;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        DateTime(self.0 + rhs.0)
+    }
+}
+
 impl Sub<DateTime> for DateTime {
     type Output = Duration;
 
@@ -183,6 +280,11 @@ impl DateDay {
         Self(res)
     }
 
+    /// Number of days passed since the given day, zero if it's in the future
+    pub fn days_since(&self, earlier: DateDay) -> usize {
+        (self.0 - earlier.0).whole_days().max(0) as usize
+    }
+
     pub fn year(&self) -> usize {
         self.0.year() as usize
     }
@@ -282,7 +384,7 @@ impl FromStr for Time {
     }
 }
 
-/// Duration measured in hours and minutes, format HH:MM
+/// Duration measured in hours and minutes, format HH:MM. For parsing compact format is supported as well: 1h30m, 2h, 45m
 #[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord)]
 pub struct Duration(time::Duration);
 
@@ -298,6 +400,21 @@ impl Duration {
             .try_into()
             .expect("duration minutes are invalid")
     }
+
+    /// Parses duration in format like 1h30m, 2h or 45m
+    fn parse_compact(s: &str) -> Result<Self, String> {
+        let err = || "Time duration has to be in format HH:MM or 1h30m".to_string();
+        let (hours, minutes) = match s.split_once('h') {
+            Some((hours, minutes)) => (parse_number(hours, 0, 999)?, minutes),
+            None => (0, s),
+        };
+        let minutes = match minutes.strip_suffix('m') {
+            Some(minutes) => parse_number(minutes, 0, 999)?,
+            None if minutes.is_empty() && s.ends_with('h') => 0,
+            None => return Err(err()),
+        };
+        Ok(Self::new(hours, minutes))
+    }
 }
 
 impl Display for Duration {
@@ -314,7 +431,7 @@ impl FromStr for Duration {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let sep = match s.find(':') {
             Some(sep) => sep,
-            _ => return Err("Time duration has to be digits separated by :".to_string()),
+            _ => return Self::parse_compact(s),
         };
         let hours = parse_number(&s[0..sep], 0, 999)?;
         let minutes = parse_number(&s[sep + 1..s.len()], 0, 59)?;
@@ -397,6 +514,32 @@ mod tests {
         assert!(datetime < datetime_date);
     }
 
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn datetime_from_timestamp() {
+        let timestamp = Timestamp::from_u64(1688371259000); // 2023-07-03 08:00:59 UTC
+        assert_eq!(
+            DateTime::from_timestamp(timestamp, 0).to_string(),
+            "2023-07-03 08:00"
+        );
+        assert_eq!(
+            DateTime::from_timestamp(timestamp, -9 * 60).to_string(),
+            "2023-07-02 23:00"
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn range_overlaps() {
+        let range = |s: &str| s.parse::<DateTimeRange>().unwrap();
+        let run = range("2023-07-03 10:00 11:00");
+        assert!(run.overlaps(&range("2023-07-03 10:30 12:00")));
+        assert!(run.overlaps(&range("2023-07-03 09:00 - 2023-07-04 09:00")));
+        assert!(!run.overlaps(&range("2023-07-03 11:00 12:00")));
+        assert!(!run.overlaps(&range("2023-07-03 10:30")));
+        assert!(!run.overlaps(&range("2023-07-03 10:30 -")));
+    }
+
     #[cfg(feature = "wasm")]
     #[wasm_bindgen_test::wasm_bindgen_test]
     fn datetime_now() {
@@ -419,6 +562,15 @@ mod tests {
         assert_eq!(day.add_days(3).days_from_monday(), 6); // Finding end of the week - Sunday
     }
 
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn dateday_days_since() {
+        let day = DateDay::new(2023, 7, 6);
+        assert_eq!(day.days_since(DateDay::new(2023, 7, 6)), 0);
+        assert_eq!(day.days_since(DateDay::new(2023, 6, 30)), 6);
+        assert_eq!(day.days_since(DateDay::new(2023, 7, 10)), 0);
+    }
+
     #[test]
     #[wasm_bindgen_test::wasm_bindgen_test]
     fn dateday_as_start() {
@@ -538,6 +690,73 @@ mod tests {
         assert_eq!(range.to_string(), "2022-11-23 12:49 12:49");
     }
 
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn datetimerange_instant() {
+        let got = "2022-11-23 12:49".parse::<DateTimeRange>().unwrap();
+        assert!(got.is_instant());
+        assert_eq!(got.duration(), Duration::new(0, 0));
+        assert_eq!(got.to_string(), "2022-11-23 12:49");
+
+        // Instant differs from zero duration range
+        let range = "2022-11-23 12:49 12:49".parse::<DateTimeRange>().unwrap();
+        assert!(!range.is_instant());
+        assert_ne!(got, range);
+        assert!(got > range);
+    }
+
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn datetimerange_bad_input() {
+        for input in [
+            "",
+            "2022-11-23",
+            "2022-11-23 12:4é",
+            "2022-11-23 12:49 -é",
+            "2022-11-23 12:49 1é:00",
+        ] {
+            assert!(input.parse::<DateTimeRange>().is_err(), "input {input}");
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn datetimerange_running() {
+        let got = "2022-11-23 12:49 -".parse::<DateTimeRange>().unwrap();
+        assert!(got.is_running());
+        assert_eq!(got.to_string(), "2022-11-23 12:49 -");
+
+        let end = DateTime::new(DateDay::new(2022, 11, 23), Time::new(14, 0));
+        let stopped = got.stop(end).unwrap();
+        assert!(stopped.is_closed());
+        assert_eq!(stopped.to_string(), "2022-11-23 12:49 14:00");
+        assert!(stopped.stop(end).is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn datetimerange_start_duration() {
+        for (input, want) in [
+            ("2022-11-23 12:00 +1h30m", "2022-11-23 12:00 13:30"),
+            ("2022-11-23 12:00 +45m", "2022-11-23 12:00 12:45"),
+            (
+                "2022-11-23 23:00 +2h",
+                "2022-11-23 23:00 - 2022-11-24 01:00",
+            ),
+            ("2022-11-23 12:00 +01:10", "2022-11-23 12:00 13:10"),
+        ] {
+            let got = input.parse::<DateTimeRange>().unwrap();
+            assert_eq!(got.to_string(), want);
+        }
+        for input in [
+            "2022-11-23 12:00 +",
+            "2022-11-23 12:00 +1x",
+            "2022-11-23 12:00 +h",
+        ] {
+            assert!(input.parse::<DateTimeRange>().is_err(), "input {input}");
+        }
+    }
+
     #[test]
     #[wasm_bindgen_test::wasm_bindgen_test]
     fn datetimerange_duration() {
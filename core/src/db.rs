@@ -1,12 +1,25 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use crate::computed::{ComputedProps, RulesChange};
+use crate::data_views::metrics::{MetricKey, MetricsUpdate, MetricsView};
 use crate::data_views::query_results::QueryResultsView;
+use crate::data_views::skill_history::SkillHistory;
 use crate::data_views::skills::{SkillsNotification, SkillsUpdate, SkillsView};
+use crate::data_views::vocabulary::{Completion, VocabularyView};
 use crate::data_views::week::{WeekProgress, WeekView};
-use crate::date_time::datetime::{DateDay, DateTimeRange};
+use crate::date_time::datetime::{DateDay, DateTime, DateTimeRange};
+use crate::date_time::timestamp::Timestamp;
+use crate::encryption::hash::StableHash;
+use crate::encryption::payload::PayloadId;
+use crate::history::History;
 use crate::parsing::parser::{ParseError, Parser};
-use crate::progress::skill::Skill;
+use crate::progress::nudge::{self, NudgeNotification};
+use crate::progress::skill::{Skill, SkillKinds};
 use crate::record::{Entry, PropVal, Tag};
+use crate::rename::{rename_entry, RenameRule, Renamed};
+use crate::resolution::ResolutionPolicy;
+use crate::schema::{SchemaWarning, Schemas};
+use crate::version::{Causality, Version};
 
 #[derive(PartialEq, Eq, Clone, Debug, PartialOrd, Ord)]
 pub enum Record {
@@ -16,9 +29,33 @@ pub enum Record {
 
 impl Record {
     pub fn revision(&self) -> usize {
+        self.version().revision()
+    }
+
+    /// Version of the record, for conflicts it's a version that knows about all the conflicting entries, so an edit
+    /// based on it resolves the conflict
+    pub fn version(&self) -> Version {
         match self {
-            Record::Entry(v) => v.revision(),
-            Record::Conflict(v) => v.first().expect("conflict contains entries").revision(),
+            Record::Entry(v) => v.version(),
+            Record::Conflict(v) => v
+                .iter()
+                .map(|v| v.version())
+                .reduce(|a, b| a.join(&b))
+                .expect("conflict contains entries"),
+        }
+    }
+
+    /// Returns the record edited on the device with the version superseding this one
+    pub fn edited(&self, entry: Entry, device: &str) -> Record {
+        let mut entry = entry;
+        entry.set_version(&self.version().next(device));
+        Record::Entry(entry)
+    }
+
+    pub(crate) fn entries(&self) -> Vec<&Entry> {
+        match self {
+            Record::Entry(v) => vec![v],
+            Record::Conflict(v) => v.iter().collect(),
         }
     }
 
@@ -40,13 +77,27 @@ impl Record {
     }
 
     pub fn to_deleted_string(&self) -> String {
+        // Clock is kept as is, so deletion supersedes the record, but not the concurrent edits of it
+        let version = self.version();
+        let version = Version::new(version.revision() + 1, version.clock().clone());
         format!(
-            "{} entry revision={} deleted. Marker that entry for this data range was deleted",
+            "{} entry {version} deleted. Marker that entry for this data range was deleted",
             self.date_range(),
-            self.revision() + 1
         )
     }
 
+    /// Finishes running record. Stopped entry has another date range, so the running one has to be replaced with the
+    /// deletion marker, both records have to be added and uploaded
+    pub fn stop(&self, end: DateTime) -> Result<Stopped, String> {
+        let Record::Entry(entry) = self else {
+            return Err("conflicts cannot be stopped".to_string());
+        };
+        Ok(Stopped {
+            deleted: Record::parse(&self.to_deleted_string())?,
+            stopped: Record::Entry(entry.stop(end)?),
+        })
+    }
+
     pub fn is_deleted_record(&self) -> bool {
         let Record::Entry(entry) = self else {
             return false;
@@ -87,17 +138,54 @@ impl Record {
         if input.lines().count() > 1 {
             let mut entries = BTreeSet::new();
             for input in input.lines() {
-                let entry = Entry::parse(input).map_err(|err| err.to_string())?;
+                let entry =
+                    Entry::parse(input).map_err(|err| err.diagnostic(input, true).to_string())?;
                 entries.insert(entry);
             }
             Ok(Record::Conflict(entries))
         } else {
-            let entry = Entry::parse(input).map_err(|err| err.to_string())?;
+            let entry =
+                Entry::parse(input).map_err(|err| err.diagnostic(input, true).to_string())?;
             Ok(Record::Entry(entry))
         }
     }
 }
 
+/// Records replacing the stopped running record
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Stopped {
+    /// Deletion marker of the running record
+    pub deleted: Record,
+    /// Finished copy of the running record with the resolved date range
+    pub stopped: Record,
+}
+
+/// Payload timestamps of synced entries. Entries are identified by date range and a hash of their content instead of
+/// a full copy, concurrent entries may have the same version, so it's not enough to tell those apart
+#[derive(Default, Debug, Clone)]
+pub struct Timestamps(BTreeMap<(DateTimeRange, [u8; StableHash::SIZE]), Timestamp>);
+
+impl Timestamps {
+    pub fn get(&self, entry: &Entry) -> Option<Timestamp> {
+        self.0.get(&Timestamps::key(entry)).copied()
+    }
+
+    /// Same entry could be synced more than once, the earliest payload is kept
+    pub fn insert(&mut self, entry: &Entry, timestamp: Timestamp) {
+        let existing = self.0.entry(Timestamps::key(entry)).or_insert(timestamp);
+        *existing = (*existing).min(timestamp);
+    }
+
+    fn remove(&mut self, entry: &Entry) {
+        self.0.remove(&Timestamps::key(entry));
+    }
+
+    fn key(entry: &Entry) -> (DateTimeRange, [u8; StableHash::SIZE]) {
+        let hash = StableHash::hash_string(&entry.serialize(true, true));
+        (entry.date_range, hash.as_bytes())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ChangeEvent {
     Added(Record),
@@ -110,32 +198,54 @@ pub enum ViewUpdate {
     QueryResults,
     Skills(SkillsUpdate),
     Week,
+    Metrics(MetricsUpdate),
 }
 
 /// Emitter when user interactively added a new record and progress notification has
-/// to be shown to the user
+/// to be shown to the user. Nudges are not emitted, but returned on request via `DB::nudges`
+#[derive(PartialEq, Debug)]
 pub enum Notification {
     Skills(SkillsNotification),
+    Nudge(NudgeNotification),
+    Schema(SchemaWarning),
 }
 
 // Parsed collection of all active entries and goals
 #[derive(Default)]
 pub struct DB {
     entries: BTreeMap<DateTimeRange, Record>,
+    computed: ComputedProps,
+    schemas: Schemas,
+    device: Option<String>,
+    policy: ResolutionPolicy,
+    unresolved: BTreeMap<DateTimeRange, BTreeSet<Entry>>, // Conflicting entries of records resolved by the policy
+    timestamps: Timestamps,
+    history: Option<History>,
     on_notification: Option<Box<dyn Fn(Notification)>>,
     on_view_update: Option<Box<dyn Fn(ViewUpdate)>>,
     view_query_results: QueryResultsView,
     view_skills: SkillsView,
     view_week: WeekView,
+    view_metrics: MetricsView,
+    view_vocabulary: VocabularyView,
 }
 
 impl DB {
     pub fn new() -> Self {
         DB {
             entries: BTreeMap::new(),
+            computed: ComputedProps::default(),
+            schemas: Schemas::default(),
+            device: None,
+            policy: ResolutionPolicy::default(),
+            unresolved: BTreeMap::new(),
+            timestamps: Timestamps::default(),
+            history: None,
             view_skills: SkillsView::default(),
             view_query_results: QueryResultsView::default(),
             view_week: WeekView::default(),
+            view_metrics: MetricsView::default(),
+            view_vocabulary: VocabularyView::default(),
             on_view_update: None,
             on_notification: None,
         }
@@ -145,6 +255,16 @@ impl DB {
         self.view_skills.data()
     }
 
+    /// Returns levels history and next level forecast for the skill with given title
+    pub fn skill_history(&self, skill: &str, now: DateDay) -> Option<SkillHistory> {
+        let skill = self.view_skills.data().get(skill)?;
+        Some(SkillHistory::new(skill, self.entries.iter(), now))
+    }
+
+    pub fn skill_kinds(&self) -> &SkillKinds {
+        self.view_skills.kinds()
+    }
+
     pub fn query_results(&self) -> &BTreeSet<Record> {
         self.view_query_results.data()
     }
@@ -153,6 +273,21 @@ impl DB {
         self.view_week.data()
     }
 
+    /// Rules deriving computed props, those are added to the records of query results and metrics
+    pub fn computed(&self) -> &ComputedProps {
+        &self.computed
+    }
+
+    /// Time series of numeric property values, keyed by tag, property name and dimension of the unit
+    pub fn metrics(&self) -> &BTreeMap<MetricKey, BTreeMap<DateTimeRange, f32>> {
+        self.view_metrics.data()
+    }
+
+    /// Total duration in minutes per element of the prop, e.g. time spent `with` each person
+    pub fn durations(&self, tag: &str, prop: &str) -> BTreeMap<&str, u64> {
+        self.view_metrics.durations(tag, prop)
+    }
+
     /// Adds new record to the DB. Interactively means user is adding a record right now. If records are restored from
     /// cache, fetched from API then it's considered not interactive. In interactive mode user may benefit from
     /// `Notifications`, so those are emitted in case of noticeable progress
@@ -162,9 +297,21 @@ impl DB {
         interactive: bool,
         now: Option<DateDay>,
     ) -> Option<ChangeEvent> {
+        if let Some(history) = &mut self.history {
+            history.add(&record, &self.timestamps);
+        }
         let event = self.merge(record);
         if let Some(event) = &event {
-            self.view_query_results.update(event, &self.on_view_update);
+            if let Some(change) = self.computed.update(event) {
+                self.recompute(change);
+            }
+            // Queries and metrics work with computed props as well, other views see records as those are stored
+            let computed_event = self.computed.apply_event(event);
+            self.view_query_results
+                .update(&computed_event, &self.on_view_update);
+            self.view_metrics
+                .update(&computed_event, &self.on_view_update);
+            self.view_vocabulary.update(event);
             self.view_skills.update(
                 self.entries.iter(),
                 event,
@@ -178,99 +325,283 @@ impl DB {
                 self.view_week
                     .update(self.entries.iter(), event, now, &self.on_view_update);
             }
+            self.schemas.update(event);
+            let added = match event {
+                ChangeEvent::Added(record) | ChangeEvent::Replaced { to: record, .. } => record,
+            };
+            if let (true, Some(on_notification), Record::Entry(entry)) =
+                (interactive, &self.on_notification, added)
+            {
+                for warning in self.validate(entry) {
+                    on_notification(Notification::Schema(warning));
+                }
+            }
         }
         event
     }
 
-    pub fn count(&self) -> usize {
-        self.entries.len()
+    /// Adds the record fetched from the API. Timestamp of the payload is used by `ResolutionPolicy::LastWriterWins`
+    pub fn add_synced(
+        &mut self,
+        record: Record,
+        payload_id: &PayloadId,
+        interactive: bool,
+        now: Option<DateDay>,
+    ) -> Option<ChangeEvent> {
+        if let Some((timestamp, _)) = payload_id.decode() {
+            for entry in record.entries() {
+                self.timestamps.insert(entry, timestamp);
+            }
+        }
+        self.add(record, interactive, now)
     }
 
-    // Merge new record into existing database and return ChangeEvent telling how exactly DB got changed.
-    // Implementation may looks a bit complex as we need to handle all possible edge cases when
-    // syncing multiple sources. Goal is that after merging all the records the DB will converge to one
-    // single possible state, no matter in which order local or remote events were processed
-    fn merge(&mut self, record_new: Record) -> Option<ChangeEvent> {
-        // Merging rules:
-        // - If no record with the same daterange exists - append.
-        // - If record with same daterange exists:
-        //   - If new record revision is higher - replace existing.
-        //   - If revision is the same:
-        //     - If both records are conflicts - append records from new conflict to existing
-        //     - If both records are values - replace existing record with new created conflict and append both records there
-        //     - If existing record is conflict, but new one is value - append new value to conflict entries
-        //     - If new one is conflict, but existing record is value - replace existing record with new conflict with added existing record
-        let mut record_new = record_new;
-        let entry_new_key = record_new.date_range();
-        let mut record_old = match self.entries.get_mut(entry_new_key) {
-            None => {
-                let event = ChangeEvent::Added(record_new.clone());
-                self.entries.insert(*entry_new_key, record_new);
-                return Some(event); // New record - append
+    /// Sets the policy for automatic resolution of conflicts, existing conflicts are resolved again with it
+    pub fn set_resolution_policy(&mut self, policy: ResolutionPolicy, now: Option<DateDay>) {
+        self.policy = policy;
+        let conflicts: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|(key, record)| match (self.unresolved.get(key), record) {
+                (Some(conflict), _) | (None, Record::Conflict(conflict)) => {
+                    Some(Record::Conflict(conflict.clone()))
+                }
+                (None, Record::Entry(_)) => None,
+            })
+            .collect();
+        for conflict in conflicts {
+            self.add(conflict, false, now);
+        }
+    }
+
+    /// Starts keeping all the revisions of entries, including outdated ones. Only latest revisions of records
+    /// already added are known, so it's better to enable it before adding records
+    pub fn enable_history(&mut self) {
+        if self.history.is_some() {
+            return;
+        }
+        let mut history = History::default();
+        for (key, record) in &self.entries {
+            match self.unresolved.get(key) {
+                Some(conflict) => {
+                    history.add(&Record::Conflict(conflict.clone()), &self.timestamps)
+                }
+                None => history.add(record, &self.timestamps),
             }
-            Some(v) => v,
+        }
+        self.history = Some(history);
+    }
+
+    /// Revisions history, exists only if it was enabled by `enable_history`
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    /// Adds the old revision of the entry as a new one superseding the current record. Returned record has to be
+    /// uploaded to sync other devices
+    pub fn restore(&mut self, revision: &Entry, now: Option<DateDay>) -> Record {
+        let mut entry = revision.clone();
+        let record = match (self.entries.get(&revision.date_range), &self.device) {
+            (Some(current), Some(device)) => current.edited(entry, device),
+            (Some(current), None) => {
+                let version = current.version();
+                entry.set_version(&Version::new(
+                    version.revision() + 1,
+                    version.clock().clone(),
+                ));
+                Record::Entry(entry)
+            }
+            (None, _) => Record::Entry(entry),
         };
-        if &record_new == record_old {
-            return None; // Same records - ignore
+        self.add(record.clone(), false, now);
+        record
+    }
+
+    /// Returns DB with the state as it was at the time, based on synced revisions from the history. Local changes
+    /// which weren't synced are not included. None if history wasn't enabled
+    pub fn at(&self, time: Timestamp, now: Option<DateDay>) -> Option<DB> {
+        let history = self.history.as_ref()?;
+        let mut db = DB::new();
+        db.policy = self.policy;
+        for (entry, timestamp) in history.synced_at(time) {
+            db.timestamps.insert(entry, timestamp);
+            db.add(Record::Entry(entry.clone()), false, now);
         }
-        if record_new.revision() > record_old.revision() {
+        Some(db)
+    }
+
+    /// Checks the entry against schemas of its tags, useful to warn the user before the entry is saved
+    pub fn validate(&self, entry: &Entry) -> Vec<SchemaWarning> {
+        if Record::Entry(entry.clone()).is_deleted_record() {
+            return vec![];
+        }
+        self.schemas.validate(entry, self.view_skills.kinds())
+    }
+
+    /// Returns reminders about skills not practiced for `inactive_days` or behind their weekly pace
+    pub fn nudges(&self, now: DateDay, inactive_days: usize) -> Vec<Notification> {
+        nudge::nudges(
+            self.view_skills.data(),
+            self.view_week.data(),
+            self.view_week.week_start(),
+            now,
+            inactive_days,
+        )
+        .into_iter()
+        .map(Notification::Nudge)
+        .collect()
+    }
+
+    /// Returns tag, property or value completions for the input at the cursor byte position, most used first
+    pub fn completions(&self, input: &str, cursor: usize, is_entry: bool) -> Vec<Completion> {
+        self.view_vocabulary.complete(input, cursor, is_entry)
+    }
+
+    /// Sets id of the device, edits made by DB itself, e.g. renaming, are versioned with it to detect concurrent
+    /// edits precisely. Without it only revisions are increased
+    pub fn set_device(&mut self, device: &str) {
+        self.device = Some(device.to_string());
+    }
+
+    /// Renames tags and properties in all the entries. Returns new revisions of changed records, those have to be
+    /// uploaded to sync other devices. In dry run mode DB stays untouched and result is just a preview
+    pub fn rename(
+        &mut self,
+        rules: &[RenameRule],
+        dry_run: bool,
+        now: Option<DateDay>,
+    ) -> Vec<Renamed> {
+        let renamed: Vec<_> = self
+            .entries
+            .values()
+            .filter(|v| !v.is_deleted_record())
+            .filter_map(|record| match record {
+                Record::Entry(entry) => {
+                    let renamed = rename_entry(entry, rules)?;
+                    let to = match &self.device {
+                        Some(device) => record.edited(renamed, device),
+                        None => Record::Entry(renamed),
+                    };
+                    Some(Renamed {
+                        from: record.clone(),
+                        to,
+                    })
+                }
+                Record::Conflict(_) => None, // Conflicts have to be resolved by user first
+            })
+            .collect();
+        if !dry_run {
+            for v in &renamed {
+                self.add(v.to.clone(), false, now);
+            }
+        }
+        renamed
+    }
+
+    /// Returns existing records which share some time with the range, useful to skip entries that were already
+    /// added when importing from other sources
+    pub fn overlapping(&self, range: &DateTimeRange) -> Vec<&Record> {
+        self.entries
+            .values()
+            .filter(|v| !v.is_deleted_record() && v.date_range().overlaps(range))
+            .collect()
+    }
+
+    /// Computed prop rules got changed, so views depending on computed props are updated for affected records
+    fn recompute(&mut self, change: RulesChange) {
+        // Records as those were before the change, rules are restored temporarily to avoid copying all of them
+        let change = self.computed.restore(change);
+        let before: Vec<_> = self
+            .entries
+            .values()
+            .map(|v| self.computed.apply(v))
+            .collect();
+        self.computed.restore(change);
+        for (record, from) in self.entries.values().zip(before) {
+            let to = self.computed.apply(record);
+            if from.is_none() && to.is_none() {
+                continue; // Not affected by the rules
+            }
             let event = ChangeEvent::Replaced {
-                from: record_old.clone(),
-                to: record_new.clone(),
+                from: from.unwrap_or_else(|| record.clone()),
+                to: to.unwrap_or_else(|| record.clone()),
             };
-            *record_old = record_new;
-            return Some(event); // Newer record - replace
-        }
-        if record_new.revision() < record_old.revision() {
-            return None; // Older record - ignore
+            self.view_query_results.update(&event, &self.on_view_update);
+            self.view_metrics.update(&event, &self.on_view_update);
         }
+    }
 
-        match (&mut record_old, &mut record_new) {
-            (Record::Conflict(conflict_old), Record::Conflict(conflict_new)) => {
-                let conflict_before = conflict_old.clone();
-                // Existing and new are conflicts - merge it's entries
-                conflict_old.append(conflict_new);
-                Some(ChangeEvent::Replaced {
-                    from: Record::Conflict(conflict_before),
-                    to: Record::Conflict(conflict_old.clone()),
-                })
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Merge new record into existing database and return ChangeEvent telling how exactly DB got changed.
+    // Goal is that after merging all the records the DB will converge to one single possible state, no matter
+    // in which order local or remote events were processed. Record for the date range keeps only entries which
+    // aren't superseded by any other known entry, see `Version::causality`:
+    // - If no record with the same daterange exists - append.
+    // - Entries superseded by the new ones are replaced, e.g. edit replaces the original entry or a conflict.
+    // - New entries superseded by existing ones are ignored, e.g. outdated edit arrived from the server later.
+    // - Concurrent edits and different entries with the same version are kept together as a conflict.
+    // Conflict is then resolved by the policy, which is a pure function of conflicting entries, so resolved
+    // record converges as well. Conflicting entries are kept aside, so entries arriving later are merged with them.
+    fn merge(&mut self, record_new: Record) -> Option<ChangeEvent> {
+        let key = *record_new.date_range();
+        let record_old = self.entries.get(&key).cloned();
+        let known = match (self.unresolved.get(&key), &record_old) {
+            (Some(conflict), _) => conflict.iter().collect(),
+            (None, Some(record)) => record.entries(),
+            (None, None) => vec![],
+        };
+        let all: BTreeSet<&Entry> = known.into_iter().chain(record_new.entries()).collect();
+        let mut latest = BTreeSet::new();
+        for entry in &all {
+            let version = entry.version();
+            if all
+                .iter()
+                .any(|v| v.version().causality(&version) == Causality::After)
+            {
+                self.timestamps.remove(entry); // Superseded entries are never needed again
+            } else {
+                latest.insert((*entry).clone());
             }
-            (Record::Entry(value_old), Record::Entry(value_new)) => {
-                // Two conflicting values - replace existing record with Conflict value and append new entry there
-                let value_before = value_old.clone();
-                *record_old =
-                    Record::Conflict(BTreeSet::from([value_old.clone(), value_new.to_owned()]));
-                Some(ChangeEvent::Replaced {
-                    from: Record::Entry(value_before),
-                    to: record_old.clone(),
-                })
+        }
+        let resolved = match latest.len() {
+            1 => None,
+            _ => self.policy.resolve(&latest, &self.timestamps),
+        };
+        let record = match resolved {
+            Some(resolved) => {
+                self.unresolved.insert(key, latest);
+                Record::Entry(resolved)
             }
-            (Record::Conflict(conflict_old), Record::Entry(value_new)) => {
-                let conflict_before = conflict_old.clone();
-                // Existing conflict - append new entry
-                conflict_old.insert(value_new.to_owned());
-                Some(ChangeEvent::Replaced {
-                    from: Record::Conflict(conflict_before),
-                    to: Record::Conflict(conflict_old.clone()),
-                })
+            None if latest.len() == 1 => {
+                self.unresolved.remove(&key);
+                Record::Entry(latest.pop_first().expect("one entry exists"))
             }
-            (Record::Entry(value_old), Record::Conflict(conflict_new)) => {
-                // Existing value, but new conflict, merge to conflict
-                let value_before = value_old.clone();
-                let mut entries = conflict_new.to_owned();
-                entries.insert(value_old.clone());
-                *record_old = Record::Conflict(entries);
-                Some(ChangeEvent::Replaced {
-                    from: Record::Entry(value_before),
-                    to: record_old.clone(),
-                })
+            None => {
+                self.unresolved.remove(&key);
+                Record::Conflict(latest)
             }
+        };
+        if Some(&record) == record_old.as_ref() {
+            return None; // Same records or new entries are outdated - ignore
         }
+        self.entries.insert(key, record.clone());
+        Some(match record_old {
+            None => ChangeEvent::Added(record),
+            Some(from) => ChangeEvent::Replaced { from, to: record },
+        })
     }
 
     pub fn update_query(&mut self, query: Query) {
+        let entries: BTreeMap<_, _> = self
+            .entries
+            .iter()
+            .map(|(k, v)| (*k, self.computed.apply_or_clone(v)))
+            .collect();
         self.view_query_results
-            .update_query(query, self.entries.iter(), &self.on_view_update);
+            .update_query(query, entries.iter(), &self.on_view_update);
     }
 
     pub fn on_view_update(&mut self, cb: Box<dyn Fn(ViewUpdate)>) {
@@ -351,21 +682,17 @@ impl Query {
         for tag in &tags {
             if tag.name == "filter" {
                 for prop in &tag.props {
-                    if let (PropVal::String(val), "after") = (&prop.val, prop.name.as_str()) {
-                        date_start = Some(val.parse::<DateDay>().map_err(|_| {
-                            ParseError::Unexpected(
-                                format!("'date' in YYYY-MM-DD format is expected, got |{val}|"),
-                                prop.start_pos,
-                            )
-                        })?);
-                    } else if let (PropVal::String(val), "before") = (&prop.val, prop.name.as_str())
-                    {
-                        date_end = Some(val.parse::<DateDay>().map_err(|_| {
-                            ParseError::Unexpected(
-                                format!("'date' in YYYY-MM-DD format is expected, got |{val}|"),
-                                prop.start_pos,
-                            )
-                        })?);
+                    let date = match &prop.val {
+                        PropVal::Date(date) => Ok(*date),
+                        val => Err(ParseError::Unexpected(
+                            format!("'date' in YYYY-MM-DD format is expected, got |{val}|"),
+                            prop.start_pos,
+                        )),
+                    };
+                    if prop.name == "after" {
+                        date_start = Some(date?);
+                    } else if prop.name == "before" {
+                        date_end = Some(date?);
                     } else {
                         return Err(ParseError::Unexpected(
                             "'after' or 'before' property is expected".to_string(),
@@ -669,6 +996,430 @@ mod tests {
         )
     }
 
+    #[test]
+    fn rename() {
+        let mut db = TestDB::default();
+        db.add_entry("00:01 jog dist=5");
+        db.add_entry("00:02 read");
+        db.add_entry("00:03 jog. entry revision=2");
+        db.add_entry("00:04 jog. skill kind=physical. Jogging");
+        let rules = vec!["jog=run".parse().unwrap()];
+
+        // Dry run only previews the changes
+        let renamed = db.db.rename(&rules, true, None);
+        let preview: Vec<_> = renamed
+            .iter()
+            .map(|v| v.to.to_string(true, true)[ENTRY_PREFIX.len() + 1..].to_string())
+            .collect();
+        assert_eq!(
+            preview,
+            vec![
+                "00:01 run dist=5. entry revision=2",
+                "00:03 run. entry revision=3",
+                "00:04 run. skill kind=physical. entry revision=2. Jogging",
+            ]
+        );
+        db.assert_query_results("run", vec![]);
+
+        // Skills pick up the renamed history as new revisions replace old ones
+        db.db.rename(&rules, false, None);
+        db.assert_query_results(
+            "run",
+            vec![
+                "00:01 run dist=5. entry revision=2",
+                "00:03 run. entry revision=3",
+                "00:04 run. skill kind=physical. entry revision=2. Jogging",
+            ],
+        );
+        assert_eq!(db.db.skills()["Jogging"].progress().duration_minutes, 4);
+        assert!(db.db.rename(&rules, false, None).is_empty());
+    }
+
+    #[test]
+    fn overlapping() {
+        let mut db = DB::new();
+        for entry in [
+            "2023-07-03 10:00 11:00 run",
+            "2023-07-03 10:30 weight kg=72",
+            "2023-07-03 12:00 13:00 read",
+            "2023-07-03 12:00 13:00 entry revision=2 deleted",
+        ] {
+            db.add(Record::parse(entry).unwrap(), false, None);
+        }
+        let overlapping = |range: &str| -> Vec<String> {
+            db.overlapping(&range.parse().unwrap())
+                .iter()
+                .map(|v| v.to_string(true, true))
+                .collect()
+        };
+        assert_eq!(
+            overlapping("2023-07-03 10:30 11:30"),
+            vec!["2023-07-03 10:00 11:00 run"]
+        );
+        assert!(overlapping("2023-07-03 11:00 11:30").is_empty());
+        assert!(overlapping("2023-07-03 12:30 14:00").is_empty()); // Deleted entries don't count
+    }
+
+    #[test]
+    fn merge_logic_versions() {
+        let original = parse_entry("00:01 run");
+        let edit = |record: &Record, s: &str, device: &str| {
+            let Record::Entry(entry) = parse_entry(s) else {
+                unreachable!()
+            };
+            record.edited(entry, device)
+        };
+        let laptop = edit(&original, "00:01 run distance=5", "laptop");
+        let laptop2 = edit(&laptop, "00:01 run distance=7", "laptop");
+        let phone = edit(&original, "00:01 run distance=6", "phone");
+        assert_eq!(
+            laptop2.to_string(false, true),
+            "00:00 00:01 run distance=7. entry revision=3 clock=laptop@2"
+        );
+
+        // Concurrent edits become a conflict even if revisions differ, no matter in which order those arrived
+        let conflict = Record::Conflict(new_conflict(vec![&laptop2, &phone]));
+        for order in [
+            vec![&original, &laptop, &laptop2, &phone],
+            vec![&phone, &laptop2, &original, &laptop],
+            vec![&laptop, &phone, &laptop2, &original],
+        ] {
+            let mut db = TestDB::default();
+            for record in order {
+                db.add(record.clone());
+            }
+            db.assert_record(vec![&conflict]);
+        }
+
+        // Edit of the conflict resolves it, outdated edits are ignored afterwards
+        let resolved = edit(&conflict, "00:01 run distance=7", "phone");
+        assert_eq!(
+            resolved.to_string(false, true),
+            "00:00 00:01 run distance=7. entry revision=4 clock=laptop@2,phone@2"
+        );
+        let mut db = TestDB::default();
+        db.add(conflict.clone());
+        db.add(resolved.clone());
+        db.add(laptop.clone());
+        db.add(phone.clone());
+        db.assert_record(vec![&resolved]);
+
+        // Deletion supersedes the record, but not a concurrent edit of it
+        let deleted = Record::parse(&laptop.to_deleted_string()).unwrap();
+        let mut db = TestDB::default();
+        db.add(laptop.clone());
+        db.add(deleted.clone());
+        db.assert_record(vec![&deleted]);
+        let laptop_phone = edit(&laptop, "00:01 run distance=8", "phone");
+        db.add(laptop_phone.clone());
+        db.assert_record(vec![&Record::Conflict(new_conflict(vec![
+            &deleted,
+            &laptop_phone,
+        ]))]);
+    }
+
+    #[test]
+    fn merge_logic_arrival_order() {
+        fn permutations(records: &[Record]) -> Vec<Vec<Record>> {
+            if records.len() <= 1 {
+                return vec![records.to_vec()];
+            }
+            let mut output = Vec::new();
+            for i in 0..records.len() {
+                let mut rest = records.to_vec();
+                let first = rest.remove(i);
+                for mut permutation in permutations(&rest) {
+                    permutation.insert(0, first.clone());
+                    output.push(permutation);
+                }
+            }
+            output
+        }
+
+        // Entries with and without clocks, those without a clock never supersede the ones with it
+        let original = parse_entry("00:01 run");
+        let phone = parse_entry("00:01 run distance=5. entry revision=2 clock=phone@1");
+        let old_client = parse_entry("00:01 run distance=6. entry revision=3");
+        let laptop = parse_entry("00:01 run distance=7. entry revision=4 clock=laptop@1");
+        let conflict = Record::Conflict(new_conflict(vec![&laptop, &phone]));
+        for order in permutations(&[original, phone, old_client, laptop]) {
+            let mut db = TestDB::default();
+            for record in order {
+                db.add(record);
+            }
+            db.assert_record(vec![&conflict]);
+        }
+    }
+
+    #[test]
+    fn merge_logic_resolution() {
+        use crate::encryption::hash::StableHash;
+
+        let original = parse_entry("00:01 run");
+        let edit = |s: &str, device: &str| {
+            let Record::Entry(entry) = parse_entry(s) else {
+                unreachable!()
+            };
+            original.edited(entry, device)
+        };
+        let laptop = edit("00:01 run distance=5", "laptop");
+        let phone = edit("00:01 run. Morning", "phone");
+        let tablet = edit("00:01 run distance=6", "tablet");
+        let deleted = Record::parse(&laptop.to_deleted_string()).unwrap();
+        let resolved = |policy: ResolutionPolicy, records: &[&Record]| {
+            let mut db = DB::new();
+            db.set_resolution_policy(policy, None);
+            for (i, record) in records.iter().enumerate() {
+                let id =
+                    PayloadId::encode(Timestamp::from_u64(i as u64), StableHash::hash_string(""));
+                db.add_synced((*record).clone(), &id, false, None);
+            }
+            let records: Vec<_> = db
+                .entries
+                .values()
+                .map(|v| v.to_string(false, true))
+                .collect();
+            records.join("\n")
+        };
+
+        // Resolved record converges, no matter in which order entries arrived
+        let merged = "00:00 00:01 run distance=5. entry revision=2 clock=laptop@1,phone@1. Morning";
+        assert_eq!(
+            resolved(ResolutionPolicy::FieldMerge, &[&original, &laptop, &phone]),
+            merged
+        );
+        assert_eq!(
+            resolved(ResolutionPolicy::FieldMerge, &[&phone, &original, &laptop]),
+            merged
+        );
+        // Conflicting fields and more concurrent edits arrived later are kept for the user
+        assert_eq!(
+            resolved(ResolutionPolicy::FieldMerge, &[&laptop, &phone, &tablet]),
+            Record::Conflict(new_conflict(vec![&laptop, &phone, &tablet])).to_string(false, true)
+        );
+        assert_eq!(
+            resolved(ResolutionPolicy::Manual, &[&laptop, &phone]),
+            Record::Conflict(new_conflict(vec![&laptop, &phone])).to_string(false, true)
+        );
+        assert_eq!(
+            resolved(ResolutionPolicy::PreferDeletion, &[&phone, &deleted]),
+            "00:00 00:01 entry revision=3 deleted clock=laptop@1,phone@1. Marker that entry for this data range was deleted"
+        );
+        assert_eq!(
+            resolved(ResolutionPolicy::LastWriterWins, &[&phone, &laptop]),
+            "00:00 00:01 run distance=5. entry revision=2 clock=laptop@1,phone@1"
+        );
+        assert_eq!(
+            resolved(ResolutionPolicy::LastWriterWins, &[&laptop, &phone]),
+            "00:00 00:01 run. entry revision=2 clock=laptop@1,phone@1. Morning"
+        );
+        // Entries from clients without a clock have the same version, each keeps its own payload time
+        let morning = parse_entry("00:01 run. Morning");
+        let evening = parse_entry("00:01 run. Evening");
+        assert_eq!(
+            resolved(ResolutionPolicy::LastWriterWins, &[&morning, &evening]),
+            "00:00 00:01 run. Evening"
+        );
+        assert_eq!(
+            resolved(ResolutionPolicy::LastWriterWins, &[&evening, &morning]),
+            "00:00 00:01 run. Morning"
+        );
+
+        // Changing the policy resolves existing conflicts, edit of resolved record supersedes all the entries
+        let mut db = TestDB::default();
+        db.add(laptop.clone());
+        db.add(phone.clone());
+        db.db
+            .set_resolution_policy(ResolutionPolicy::FieldMerge, None);
+        let Record::Entry(entry) = parse_entry("00:01 run distance=7") else {
+            unreachable!()
+        };
+        let record = db.db.entries.values().next().unwrap().clone();
+        assert_eq!(record.to_string(false, true), merged);
+        let edited = record.edited(entry, "phone");
+        db.add(edited.clone());
+        db.add(tablet.clone());
+        db.assert_record(vec![&Record::Conflict(new_conflict(vec![
+            &edited, &tablet,
+        ]))]);
+    }
+
+    #[test]
+    fn computed() {
+        let mut db = DB::new();
+        let add = |db: &mut DB, s: &str| db.add(Record::parse(s).unwrap(), false, None);
+        add(&mut db, "2023-07-03 10:00 11:00 run distance=12");
+        add(&mut db, "2023-07-04 10:00 10:30 run distance=5");
+        let pace = |db: &DB| -> Vec<f32> {
+            db.metrics()
+                .iter()
+                .filter(|(k, _)| k.tag == "run" && k.prop == "pace")
+                .flat_map(|(_, v)| v.values().copied())
+                .collect()
+        };
+        assert!(pace(&db).is_empty());
+
+        // Adding a rule computes props of existing entries
+        let rule = "2023-07-01 00:00 run. compute pace=\"duration / distance\"";
+        add(&mut db, rule);
+        assert_eq!(pace(&db), vec![5.0, 6.0]);
+
+        // New entries get computed props as well, those take part in queries
+        add(&mut db, "2023-07-05 10:00 10:40 run distance=10");
+        assert_eq!(pace(&db), vec![5.0, 6.0, 4.0]);
+        db.update_query(Query::new("run pace<5.5").unwrap());
+        let results: Vec<_> = db
+            .query_results()
+            .iter()
+            .map(|v| v.to_string(true, true)) // Computed props aren't serialized
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                "2023-07-03 10:00 11:00 run distance=12",
+                "2023-07-05 10:00 10:40 run distance=10"
+            ]
+        );
+
+        // Deleting the rule removes computed props
+        let deleted = Record::parse(rule).unwrap().to_deleted_string();
+        add(&mut db, &deleted);
+        assert!(pace(&db).is_empty());
+        assert!(db.query_results().is_empty());
+    }
+
+    #[test]
+    fn stop() {
+        let mut db = DB::new();
+        let running = Record::parse("2023-07-03 10:00 - run. Morning").unwrap();
+        db.add(running.clone(), false, None);
+        let stopped = running.stop("2023-07-03 11:00".parse().unwrap()).unwrap();
+        assert_eq!(
+            stopped.stopped.to_string(true, true),
+            "2023-07-03 10:00 11:00 run. Morning"
+        );
+        db.add(stopped.deleted.clone(), false, None);
+        db.add(stopped.stopped.clone(), false, None);
+        let records: Vec<_> = db
+            .entries
+            .values()
+            .filter(|v| !v.is_deleted_record())
+            .collect();
+        assert_eq!(records, vec![&stopped.stopped]);
+        assert!(stopped
+            .stopped
+            .stop("2023-07-03 12:00".parse().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn history() {
+        use crate::encryption::hash::StableHash;
+
+        let edit = |record: &Record, s: &str| {
+            let Record::Entry(entry) = parse_entry(s) else {
+                unreachable!()
+            };
+            record.edited(entry, "laptop")
+        };
+        let original = parse_entry("00:01 run");
+        let edit1 = edit(&original, "00:01 run distance=5");
+        let edit2 = edit(&edit1, "00:01 run distance=6");
+        let mut db = DB::new();
+        db.enable_history();
+        db.set_device("phone");
+        for (i, record) in [&original, &edit2, &edit1].into_iter().enumerate() {
+            let id = PayloadId::encode(
+                Timestamp::from_u64(i as u64 + 1),
+                StableHash::hash_string(""),
+            );
+            db.add_synced(record.clone(), &id, false, None);
+        }
+        let history = db.history().unwrap();
+        let range = *original.date_range();
+        assert_eq!(history.edits(&range), 2);
+        let revisions: Vec<_> = history
+            .revisions(&range)
+            .iter()
+            .map(|v| {
+                (
+                    v.entry().serialize(false, true),
+                    v.timestamp().unwrap().as_u64(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            revisions,
+            vec![
+                ("00:00 00:01 run".to_string(), 1),
+                (
+                    "00:00 00:01 run distance=5. entry revision=2 clock=laptop@1".to_string(),
+                    3
+                ),
+                (
+                    "00:00 00:01 run distance=6. entry revision=3 clock=laptop@2".to_string(),
+                    2
+                ),
+            ]
+        );
+
+        // State in the past is restored from the revisions known by then
+        let records = |db: &DB| -> Vec<String> {
+            db.entries
+                .values()
+                .map(|v| v.to_string(false, true))
+                .collect()
+        };
+        assert!(records(&db.at(Timestamp::from_u64(0), None).unwrap()).is_empty());
+        assert_eq!(
+            records(&db.at(Timestamp::from_u64(1), None).unwrap()),
+            vec!["00:00 00:01 run"]
+        );
+        let at = db.at(Timestamp::from_u64(2), None).unwrap();
+        assert_eq!(records(&at), records(&db));
+
+        // Restored revision supersedes the current one
+        let Record::Entry(first) = original else {
+            unreachable!()
+        };
+        let restored = db.restore(&first, None);
+        assert_eq!(
+            restored.to_string(false, true),
+            "00:00 00:01 run. entry revision=4 clock=laptop@2,phone@1"
+        );
+        assert_eq!(records(&db), vec![restored.to_string(false, true)]);
+        assert_eq!(db.history().unwrap().edits(&range), 3);
+        assert!(DB::new().at(Timestamp::from_u64(1), None).is_none());
+    }
+
+    #[test]
+    fn schemas() {
+        let warnings = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut db = DB::new();
+        let warnings_clone = warnings.clone();
+        db.on_notification(Box::new(move |v| {
+            if let Notification::Schema(warning) = v {
+                warnings_clone.borrow_mut().push(warning.to_string());
+            }
+        }));
+        let mut add = |s: &str, interactive: bool| {
+            db.add(Record::parse(s).unwrap(), interactive, None);
+        };
+        add("2023-07-01 00:00 run. schema distance=length", false);
+        add("2023-07-03 10:00 11:00 run", false); // Not interactive, no warnings
+        add("2023-07-04 10:00 11:00 run distance=10km", true);
+        assert!(warnings.borrow().is_empty());
+        add("2023-07-05 10:00 11:00 run", true);
+        add("2023-07-06 10:00 11:00 run distance=10", true);
+        assert_eq!(
+            *warnings.borrow(),
+            vec![
+                "Missing required prop 'distance' of 'run'",
+                "Prop 'distance' of 'run' should be a length like 10m, got '10'"
+            ]
+        );
+    }
+
     // #[test]
     // TODO DB::add should return a record which will include incremented revision number of existing record
     // TODO DB::remove should be used for deleting as otherwise it's not possible to update the views
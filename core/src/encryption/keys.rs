@@ -32,7 +32,7 @@ impl<const S: usize> Key<S> {
     }
 
     pub fn decoded(&self) -> Option<String> {
-        let data = self.0.decoded().take()?;
+        let data = self.0.decoded()?;
         String::from_utf8(data).ok()
     }
 }
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{
+    db::Record,
+    record::{Entry, PropVal},
+};
+
+/*
+Export of the entries for analysis in spreadsheets and notebooks. Every entry is a row, conflicting entries are
+exported as separate rows and deleted ones are skipped. Internal `entry` tag with the revision is left out.
+
+JSON keeps the structure as is: tags are objects with property values, tag without properties is an empty object.
+CSV is flat, so every tag has its own column with `1` when entry has it and every property is a column in format
+`tag.prop`:
+
+start,end,duration_minutes,run,run.distance,read,comment
+2023-07-03 10:00,2023-07-03 11:00,60,1,5,,Morning
+*/
+
+/// Property value for export, numbers stay numbers in JSON, properties without value become `true`
+#[derive(Serialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum ExportValue {
+    Flag(bool),
+    Number(f32),
+    Text(String),
+    List(Vec<ExportValue>),
+}
+
+impl ExportValue {
+    fn new(val: &PropVal) -> Self {
+        match val {
+            PropVal::None => ExportValue::Flag(true),
+            PropVal::Number(v) => ExportValue::Number(*v),
+            PropVal::Bool(v) => ExportValue::Flag(*v),
+            PropVal::String(v) => ExportValue::Text(v.clone()),
+            // Units and dates are kept in text, so values don't lose the meaning
+            PropVal::Quantity(_) | PropVal::Time(_) | PropVal::Date(_) => {
+                ExportValue::Text(val.to_string())
+            }
+            PropVal::List(items) => ExportValue::List(items.iter().map(ExportValue::new).collect()),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        match self {
+            ExportValue::Flag(v) => (*v as u8).to_string(),
+            ExportValue::Number(v) => v.to_string(),
+            ExportValue::Text(v) => v.clone(),
+            // Spreadsheets have no lists, so items are joined in one cell
+            ExportValue::List(items) => items
+                .iter()
+                .map(|v| v.to_csv())
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct ExportRow {
+    /// Start in format YYYY-MM-DD HH:MM
+    pub start: String,
+    /// End is the same as start for point in time entries and missing for running ones
+    pub end: Option<String>,
+    pub duration_minutes: Option<u64>,
+    pub tags: BTreeMap<String, BTreeMap<String, ExportValue>>,
+    pub comment: Option<String>,
+}
+
+impl ExportRow {
+    fn new(entry: &Entry) -> Self {
+        let date_range = entry.date_range();
+        let (end, duration_minutes) = if date_range.is_running() {
+            (None, None)
+        } else if date_range.is_instant() {
+            (Some(date_range.start().to_string()), Some(0))
+        } else {
+            (
+                Some(date_range.end().to_string()),
+                Some(date_range.duration().minutes()),
+            )
+        };
+        let tags = entry
+            .tags
+            .iter()
+            .filter(|tag| tag.name != "entry")
+            .map(|tag| {
+                let props = tag
+                    .props
+                    .iter()
+                    .map(|prop| (prop.name.clone(), ExportValue::new(&prop.val)))
+                    .collect();
+                (tag.name.clone(), props)
+            })
+            .collect();
+        Self {
+            start: date_range.start().to_string(),
+            end,
+            duration_minutes,
+            tags,
+            comment: entry.comment().clone(),
+        }
+    }
+}
+
+/// Converts records to rows for export
+pub fn export_rows<'a>(records: impl IntoIterator<Item = &'a Record>) -> Vec<ExportRow> {
+    let mut rows = Vec::new();
+    for record in records {
+        if record.is_deleted_record() {
+            continue;
+        }
+        match record {
+            Record::Entry(entry) => rows.push(ExportRow::new(entry)),
+            Record::Conflict(entries) => rows.extend(entries.iter().map(ExportRow::new)),
+        }
+    }
+    rows
+}
+
+/// Writes rows as CSV with a header, tag and property columns are sorted by name
+pub fn to_csv(rows: &[ExportRow]) -> String {
+    let mut columns: BTreeSet<(String, Option<String>)> = BTreeSet::new();
+    for row in rows {
+        for (tag, props) in &row.tags {
+            columns.insert((tag.clone(), None));
+            for prop in props.keys() {
+                columns.insert((tag.clone(), Some(prop.clone())));
+            }
+        }
+    }
+    let mut header = vec![
+        "start".to_string(),
+        "end".to_string(),
+        "duration_minutes".to_string(),
+    ];
+    header.extend(columns.iter().map(|(tag, prop)| match prop {
+        Some(prop) => format!("{tag}.{prop}"),
+        None => tag.clone(),
+    }));
+    header.push("comment".to_string());
+
+    let mut output = csv_line(&header);
+    for row in rows {
+        let mut line = vec![
+            row.start.clone(),
+            row.end.clone().unwrap_or_default(),
+            row.duration_minutes
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+        ];
+        line.extend(columns.iter().map(|(tag, prop)| {
+            let Some(props) = row.tags.get(tag) else {
+                return String::new();
+            };
+            match prop {
+                Some(prop) => props.get(prop).map(|v| v.to_csv()).unwrap_or_default(),
+                None => "1".to_string(),
+            }
+        }));
+        line.push(row.comment.clone().unwrap_or_default());
+        output.push_str(&csv_line(&line));
+    }
+    output
+}
+
+fn csv_line(values: &[String]) -> String {
+    let mut line = values
+        .iter()
+        .map(|v| {
+            if v.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", v.replace('"', "\"\""))
+            } else {
+                v.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn records(entries: &[&str]) -> Vec<Record> {
+        entries.iter().map(|v| Record::parse(v).unwrap()).collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn rows() {
+        let records = records(&[
+            "2023-07-03 08:00 weight kg=72.4",
+            "2023-07-03 10:00 11:30 run distance=5 place=park fast. entry revision=2. Morning",
+            "2023-07-03 12:00 - read",
+            "2023-07-03 13:00 14:00 entry revision=2 deleted",
+        ]);
+        let rows = export_rows(&records);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].end.as_deref(), Some("2023-07-03 08:00"));
+        assert_eq!(rows[0].duration_minutes, Some(0));
+        assert_eq!(rows[1].duration_minutes, Some(90));
+        assert_eq!(
+            rows[1].tags["run"],
+            BTreeMap::from([
+                ("distance".to_string(), ExportValue::Number(5.0)),
+                ("fast".to_string(), ExportValue::Flag(true)),
+                ("place".to_string(), ExportValue::Text("park".to_string())),
+            ])
+        );
+        assert!(!rows[1].tags.contains_key("entry"));
+        assert_eq!(rows[2].end, None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn csv() {
+        let records = records(&[
+            "2023-07-03 10:00 11:00 run distance=5. Morning, sunny",
+            "2023-07-03 12:00 13:00 read book=\"War and peace\"",
+            "2023-07-03 14:00 15:00 run distance=5 with=anna,bob",
+        ]);
+        assert_eq!(
+            to_csv(&export_rows(&records)),
+            "start,end,duration_minutes,read,read.book,run,run.distance,run.with,comment
+2023-07-03 10:00,2023-07-03 11:00,60,,,1,5,,\"Morning, sunny\"
+2023-07-03 12:00,2023-07-03 13:00,60,1,War and peace,,,,
+2023-07-03 14:00,2023-07-03 15:00,60,,,1,5,\"anna,bob\",
+"
+        );
+    }
+}
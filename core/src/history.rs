@@ -0,0 +1,221 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    date_time::{datetime::DateTimeRange, timestamp::Timestamp},
+    db::{Record, Timestamps},
+    record::{Entry, Prop, Tag},
+};
+
+/// Entry as it was received at some point, timestamp of the payload is known only for synced revisions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    entry: Entry,
+    timestamp: Option<Timestamp>,
+}
+
+impl Revision {
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+}
+
+/// All the revisions of entries added to the DB, including outdated and superseded ones
+#[derive(Default)]
+pub struct History {
+    revisions: BTreeMap<DateTimeRange, BTreeMap<Entry, Option<Timestamp>>>,
+}
+
+impl History {
+    pub(crate) fn add(&mut self, record: &Record, timestamps: &Timestamps) {
+        for entry in record.entries() {
+            let timestamp = self
+                .revisions
+                .entry(entry.date_range)
+                .or_default()
+                .entry(entry.clone())
+                .or_default();
+            // Same entry could be added locally first and then synced, so the earliest known payload is kept
+            *timestamp = match (*timestamp, timestamps.get(entry)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+    }
+
+    /// Returns revisions of entries for the date range ordered by version, so the latest are at the end
+    pub fn revisions(&self, date_range: &DateTimeRange) -> Vec<Revision> {
+        self.revisions
+            .get(date_range)
+            .into_iter()
+            .flat_map(|v| v.iter())
+            .map(|(entry, timestamp)| Revision {
+                entry: entry.clone(),
+                timestamp: *timestamp,
+            })
+            .collect()
+    }
+
+    /// Number of edits of the entry for the date range, first revision isn't counted
+    pub fn edits(&self, date_range: &DateTimeRange) -> usize {
+        self.revisions
+            .get(date_range)
+            .map_or(0, |v| v.len().saturating_sub(1))
+    }
+
+    /// Synced revisions which were known by the time, local only revisions are not included
+    pub(crate) fn synced_at(&self, time: Timestamp) -> impl Iterator<Item = (&Entry, Timestamp)> {
+        self.revisions
+            .values()
+            .flat_map(|v| v.iter())
+            .filter_map(move |(entry, timestamp)| match timestamp {
+                Some(timestamp) if *timestamp <= time => Some((entry, *timestamp)),
+                _ => None,
+            })
+    }
+}
+
+/// Difference between two revisions of the entry, versioning in `entry` tag is ignored
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Change {
+    TagAdded(Tag),
+    TagRemoved(Tag),
+    PropAdded {
+        tag: String,
+        prop: Prop,
+    },
+    PropRemoved {
+        tag: String,
+        prop: Prop,
+    },
+    PropChanged {
+        tag: String,
+        from: Prop,
+        to: Prop,
+    },
+    CommentChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+impl Change {
+    pub fn diff(from: &Entry, to: &Entry) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for tag in from.tags.iter().filter(|v| v.name != "entry") {
+            match to.tags.iter().find(|v| v.name == tag.name) {
+                Some(v) => Change::diff_props(tag, v, &mut changes),
+                None => changes.push(Change::TagRemoved(tag.clone())),
+            }
+        }
+        for tag in to.tags.iter().filter(|v| v.name != "entry") {
+            if !from.tags.iter().any(|v| v.name == tag.name) {
+                changes.push(Change::TagAdded(tag.clone()));
+            }
+        }
+        if from.comment != to.comment {
+            changes.push(Change::CommentChanged {
+                from: from.comment.clone(),
+                to: to.comment.clone(),
+            });
+        }
+        changes
+    }
+
+    fn diff_props(from: &Tag, to: &Tag, changes: &mut Vec<Change>) {
+        let tag = || from.name.clone();
+        for prop in from.stored_props() {
+            match to.stored_props().find(|v| v.name == prop.name) {
+                None => changes.push(Change::PropRemoved {
+                    tag: tag(),
+                    prop: prop.clone(),
+                }),
+                Some(v) if v != prop => changes.push(Change::PropChanged {
+                    tag: tag(),
+                    from: prop.clone(),
+                    to: v.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for prop in to.stored_props() {
+            if !from.stored_props().any(|v| v.name == prop.name) {
+                changes.push(Change::PropAdded {
+                    tag: tag(),
+                    prop: prop.clone(),
+                });
+            }
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::TagAdded(tag) => f.write_fmt(format_args!("Added tag: {tag}")),
+            Change::TagRemoved(tag) => f.write_fmt(format_args!("Removed tag: {tag}")),
+            Change::PropAdded { tag, prop } => {
+                f.write_fmt(format_args!("Added prop: {tag} {prop}"))
+            }
+            Change::PropRemoved { tag, prop } => {
+                f.write_fmt(format_args!("Removed prop: {tag} {prop}"))
+            }
+            Change::PropChanged { tag, from, to } => {
+                f.write_fmt(format_args!("Changed prop: {tag} {from} -> {to}"))
+            }
+            Change::CommentChanged { from, to } => match (from, to) {
+                (None, Some(to)) => f.write_fmt(format_args!("Added comment: {to}")),
+                (Some(from), None) => f.write_fmt(format_args!("Removed comment: {from}")),
+                (from, to) => f.write_fmt(format_args!(
+                    "Changed comment: {} -> {}",
+                    from.as_deref().unwrap_or_default(),
+                    to.as_deref().unwrap_or_default()
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn diff() {
+        let cases = vec![
+            ("run", "run", vec![]),
+            ("run. entry revision=2", "run. entry revision=3", vec![]),
+            (
+                "run dist=5 place=park. Morning",
+                "run dist=6 pace=5. walk. Evening",
+                vec![
+                    "Changed prop: run dist=5 -> dist=6",
+                    "Removed prop: run place=park",
+                    "Added prop: run pace=5",
+                    "Added tag: walk",
+                    "Changed comment: Morning -> Evening",
+                ],
+            ),
+            (
+                "run. walk dist=1",
+                "walk dist=2",
+                vec!["Removed tag: run", "Changed prop: walk dist=1 -> dist=2"],
+            ),
+            ("run", "run. Morning", vec!["Added comment: Morning"]),
+        ];
+        for (from, to, want) in cases {
+            let parse = |v: &str| Entry::parse(&format!("2023-07-03 10:00 11:00 {v}")).unwrap();
+            let got: Vec<_> = Change::diff(&parse(from), &parse(to))
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+            assert_eq!(got, want, "{from} -> {to}");
+        }
+    }
+}
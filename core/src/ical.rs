@@ -0,0 +1,695 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use thiserror::Error;
+
+use crate::{
+    date_time::datetime::{DateDay, DateTime, Duration},
+    db::Record,
+    encryption::hash::StableHash,
+    import::{ImportedEntry, TagMapping},
+    record::Entry,
+};
+
+/*
+Conversion between entries and iCalendar (RFC 5545) events.
+
+Export creates VEVENT per entry with tags as CATEGORIES and comment as SUMMARY. Full entry is kept in a custom
+X-QQSELF-ENTRY property, so exported calendar can be imported back without losing properties.
+
+Import supports a practical subset of iCalendar used by calendar apps:
+- Times are taken as is, TZID and UTC are ignored, so entries have the same times calendar app shows
+- All day events are skipped as those aren't activities
+- Tags are taken from CATEGORIES, or from SUMMARY if there are no categories, and mapped with `TagMapping`
+- Recurring events are expanded over the given window. RRULE with FREQ, INTERVAL, COUNT, UNTIL and BYDAY for weekly
+  events is supported, EXDATE and modified occurrences with RECURRENCE-ID are respected
+*/
+
+/// Lines longer than that are folded as RFC requires
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Safety limit for recurring events without COUNT or UNTIL
+const MAX_RECURRENCE_PERIODS: usize = 100_000;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum IcsError {
+    #[error("Bad date time {0} of event at line {1}")]
+    BadDateTime(String, usize),
+    #[error("Bad duration {0} of event at line {1}")]
+    BadDuration(String, usize),
+    #[error("Event without start at line {0}")]
+    NoStart(usize),
+    #[error("Unsupported recurrence rule {0} of event at line {1}")]
+    BadRule(String, usize),
+    #[error("Bad event at line {1}: {0}")]
+    BadEntry(String, usize),
+}
+
+/// Exports entries as iCalendar events. Running and deleted entries are skipped
+pub fn export_ics<'a>(records: impl IntoIterator<Item = &'a Record>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//qqself//qqself//EN".to_string(),
+    ];
+    for record in records {
+        if record.is_deleted_record() {
+            continue;
+        }
+        let entries: Vec<&Entry> = match record {
+            Record::Entry(entry) => vec![entry],
+            Record::Conflict(entries) => entries.iter().collect(),
+        };
+        for entry in entries {
+            let date_range = entry.date_range();
+            if date_range.is_running() {
+                continue;
+            }
+            let end = if date_range.is_instant() {
+                date_range.start()
+            } else {
+                date_range.end()
+            };
+            let text = entry.serialize(true, true);
+            let tags: Vec<_> = entry
+                .tags
+                .iter()
+                .filter(|v| v.name != "entry")
+                .map(|v| v.name.as_str())
+                .collect();
+            let summary = match entry.comment() {
+                Some(comment) => comment.clone(),
+                None => tags.join(" "),
+            };
+            let start = format_datetime(date_range.start());
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}@qqself", StableHash::hash_string(&text)),
+                format!("DTSTAMP:{start}"),
+                format!("DTSTART:{start}"),
+                format!("DTEND:{}", format_datetime(end)),
+                format!("SUMMARY:{}", escape(&summary)),
+                format!("CATEGORIES:{}", tags.join(",")),
+                format!("X-QQSELF-ENTRY:{}", escape(&text)),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|v| fold(v) + "\r\n").collect()
+}
+
+/// Imports events as entries, recurring events are expanded for days between `from` and `to` inclusive. All the
+/// events are checked, so errors contain every failed event
+pub fn import_ics(
+    input: &str,
+    mapping: &TagMapping,
+    from: DateDay,
+    to: DateDay,
+) -> Result<Vec<Entry>, Vec<IcsError>> {
+    let events = read_events(input);
+    // Modified occurrences of recurring events are separate events, those replace the generated ones
+    let mut modified: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for event in &events {
+        if let (Some(uid), Some(id)) = (event.get("UID"), event.get("RECURRENCE-ID")) {
+            modified.entry(uid).or_default().insert(id.to_string());
+        }
+    }
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for event in &events {
+        let overridden = event
+            .get("UID")
+            .and_then(|uid| modified.get(uid))
+            .cloned()
+            .unwrap_or_default();
+        match event_entries(event, mapping, from, to, &overridden) {
+            Ok(v) => entries.extend(v),
+            Err(err) => errors.push(err),
+        }
+    }
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        Err(errors)
+    }
+}
+
+struct Event {
+    line: usize,
+    /// Properties with parameters removed, values are unescaped. Some properties could be repeated
+    props: Vec<(String, String)>,
+    all_day: bool,
+}
+
+impl Event {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.props
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.props
+            .iter()
+            .filter(move |(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn read_events(input: &str) -> Vec<Event> {
+    // Long lines are folded, continuation starts with a space or a tab
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (idx, line) in input.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ => lines.push((idx + 1, line.to_string())),
+        }
+    }
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    for (line_number, line) in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                current = Some(Event {
+                    line: line_number,
+                    props: vec![],
+                    all_day: false,
+                })
+            }
+            ("END", "VEVENT") => events.extend(current.take()),
+            _ => {
+                if let Some(event) = current.as_mut() {
+                    if name == "DTSTART"
+                        && (params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME")
+                            || value.len() == 8)
+                    {
+                        event.all_day = true;
+                    }
+                    event.props.push((name.to_string(), unescape(value)));
+                }
+            }
+        }
+    }
+    events
+}
+
+fn event_entries(
+    event: &Event,
+    mapping: &TagMapping,
+    from: DateDay,
+    to: DateDay,
+    overridden: &BTreeSet<String>,
+) -> Result<Vec<Entry>, IcsError> {
+    if event.all_day {
+        return Ok(vec![]);
+    }
+    let line = event.line;
+    let start = event.get("DTSTART").ok_or(IcsError::NoStart(line))?;
+    let start =
+        parse_datetime(start).ok_or_else(|| IcsError::BadDateTime(start.to_string(), line))?;
+    let duration = match (event.get("DTEND"), event.get("DURATION")) {
+        (Some(end), _) => {
+            parse_datetime(end).ok_or_else(|| IcsError::BadDateTime(end.to_string(), line))? - start
+        }
+        (None, Some(duration)) => parse_duration(duration)
+            .ok_or_else(|| IcsError::BadDuration(duration.to_string(), line))?,
+        (None, None) => Duration::new(0, 0),
+    };
+    let bad_duration = || {
+        let duration = event.get("DURATION").unwrap_or_default();
+        IcsError::BadDuration(duration.to_string(), line)
+    };
+
+    let starts = match event.get("RRULE") {
+        Some(rule) => {
+            let rule =
+                Rule::parse(rule).ok_or_else(|| IcsError::BadRule(rule.to_string(), line))?;
+            let mut excluded = BTreeSet::new();
+            for exdate in event
+                .get_all("EXDATE")
+                .chain(overridden.iter().map(|v| v.as_str()))
+            {
+                for v in exdate.split(',') {
+                    excluded.insert(
+                        parse_datetime(v)
+                            .ok_or_else(|| IcsError::BadDateTime(v.to_string(), line))?,
+                    );
+                }
+            }
+            rule.occurrences(start, from, to)
+                .into_iter()
+                .filter(|v| !excluded.contains(v))
+                .collect()
+        }
+        None => vec![start],
+    };
+
+    // Own exported events have full entry, use it to keep all the properties
+    if let Some(entry) = event
+        .get("X-QQSELF-ENTRY")
+        .filter(|_| starts == vec![start])
+    {
+        return Entry::parse(entry)
+            .map(|v| vec![v])
+            .map_err(|err| IcsError::BadEntry(err.diagnostic(entry, true).to_string(), line));
+    }
+    let summary = event.get("SUMMARY").filter(|v| !v.is_empty());
+    let categories: Vec<String> = event
+        .get_all("CATEGORIES")
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    let (tags, comment) = if categories.is_empty() {
+        (summary.into_iter().map(|v| v.to_string()).collect(), None)
+    } else {
+        (categories, summary.map(|v| v.to_string()))
+    };
+    starts
+        .into_iter()
+        .map(|start| {
+            ImportedEntry {
+                start,
+                end: start.checked_add(duration).ok_or_else(bad_duration)?,
+                tags: tags.clone(),
+                comment: comment.clone(),
+            }
+            .to_entry(mapping)
+            .map_err(|err| IcsError::BadEntry(err, line))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Rule {
+    frequency: Frequency,
+    interval: usize,
+    count: Option<usize>,
+    until: Option<DateTime>,
+    /// Days of the week for weekly events as number of days from Monday
+    days: Vec<u8>,
+}
+
+impl Rule {
+    fn parse(s: &str) -> Option<Self> {
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            days: vec![],
+        };
+        let mut frequency = None;
+        for part in s.split(';') {
+            let (k, v) = part.split_once('=')?;
+            match k {
+                "FREQ" => {
+                    frequency = Some(match v {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return None,
+                    })
+                }
+                "INTERVAL" => rule.interval = v.parse().ok().filter(|v| *v > 0)?,
+                "COUNT" => rule.count = Some(v.parse().ok()?),
+                "UNTIL" => rule.until = Some(parse_until(v)?),
+                "BYDAY" => {
+                    for day in v.split(',') {
+                        let idx = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"]
+                            .iter()
+                            .position(|v| *v == day)?;
+                        rule.days.push(idx as u8);
+                    }
+                    rule.days.sort();
+                }
+                "WKST" => {}
+                _ => return None, // Other BY* parts aren't supported
+            }
+        }
+        rule.frequency = frequency?;
+        if !rule.days.is_empty() && rule.frequency != Frequency::Weekly {
+            return None;
+        }
+        Some(rule)
+    }
+
+    /// Returns starts of all the occurrences within the window
+    fn occurrences(&self, start: DateTime, from: DateDay, to: DateDay) -> Vec<DateTime> {
+        let mut occurrences = Vec::new();
+        let mut generated = 0;
+        for period in 0..MAX_RECURRENCE_PERIODS {
+            let step = period * self.interval;
+            let days = match self.frequency {
+                Frequency::Daily => vec![start.date().add_days(step)],
+                Frequency::Weekly => {
+                    let week = start.date().as_start_of_week().add_days(step * 7);
+                    if self.days.is_empty() {
+                        vec![week.add_days(start.date().days_from_monday().into())]
+                    } else {
+                        self.days
+                            .iter()
+                            .map(|v| week.add_days((*v).into()))
+                            .collect()
+                    }
+                }
+                Frequency::Monthly => {
+                    let month = start.date().month() - 1 + step;
+                    let year = start.date().year() + month / 12;
+                    day(year, month % 12 + 1, start.date().day())
+                        .into_iter()
+                        .collect()
+                }
+                Frequency::Yearly => day(
+                    start.date().year() + step,
+                    start.date().month(),
+                    start.date().day(),
+                )
+                .into_iter()
+                .collect(),
+            };
+            for day in days.into_iter().filter(|v| *v >= start.date()) {
+                let occurrence = DateTime::new(day, start.time());
+                if day > to
+                    || self.until.is_some_and(|v| occurrence > v)
+                    || self.count.is_some_and(|v| generated >= v)
+                {
+                    return occurrences;
+                }
+                generated += 1;
+                if day >= from {
+                    occurrences.push(occurrence);
+                }
+            }
+        }
+        occurrences
+    }
+}
+
+/// Day for the date, None if there is no such day, e.g. 31st of the short month
+fn day(year: usize, month: usize, day: usize) -> Option<DateDay> {
+    format!("{year:04}-{month:02}-{day:02}").parse().ok()
+}
+
+/// Parses date time in format YYYYMMDDTHHMMSS with optional Z, seconds are ignored
+fn parse_datetime(s: &str) -> Option<DateTime> {
+    let s = s.trim();
+    let (date, time) = s.split_once('T')?;
+    if date.len() != 8 || time.len() < 4 {
+        return None;
+    }
+    format!(
+        "{}-{}-{} {}:{}",
+        date.get(0..4)?,
+        date.get(4..6)?,
+        date.get(6..8)?,
+        time.get(0..2)?,
+        time.get(2..4)?
+    )
+    .parse()
+    .ok()
+}
+
+/// UNTIL could be a date, then it includes the whole day
+fn parse_until(s: &str) -> Option<DateTime> {
+    if s.len() == 8 {
+        return parse_datetime(&format!("{s}T235900"));
+    }
+    parse_datetime(s)
+}
+
+fn format_datetime(datetime: DateTime) -> String {
+    let s = datetime.to_string().replace(['-', ':'], "");
+    format!("{}00", s.replace(' ', "T"))
+}
+
+/// Parses durations like PT1H30M, P1D or P1W, seconds are ignored
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.strip_prefix('P')?;
+    let mut minutes = 0;
+    let mut number = String::new();
+    let mut is_time = false;
+    for c in s.chars() {
+        match c {
+            'T' => is_time = true,
+            '0'..='9' => number.push(c),
+            _ => {
+                let value: u64 = std::mem::take(&mut number).parse().ok()?;
+                let multiplier = match (c, is_time) {
+                    ('W', false) => 7 * 24 * 60,
+                    ('D', false) => 24 * 60,
+                    ('H', true) => 60,
+                    ('M', true) => 1,
+                    ('S', true) => 0,
+                    _ => return None,
+                };
+                minutes = value
+                    .checked_mul(multiplier)
+                    .and_then(|v| v.checked_add(minutes))?;
+            }
+        }
+    }
+    // Duration keeps seconds as i64, bigger values cannot be represented
+    if !number.is_empty() || minutes > i64::MAX as u64 / 60 {
+        return None;
+    }
+    Some(Duration::new(minutes / 60, minutes % 60))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => output.push('\n'),
+            Some(c) => output.push(c),
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+/// Folds the line to fit the limit, continuation lines start with a space
+fn fold(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            output.push_str("\r\n ");
+            octets = 1;
+        }
+        octets += c.len_utf8();
+        output.push(c);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn import(input: &str, from: &str, to: &str) -> Vec<String> {
+        import_ics(
+            input,
+            &TagMapping::new(&["Standup=work meeting".to_string()]).unwrap(),
+            from.parse().unwrap(),
+            to.parse().unwrap(),
+        )
+        .unwrap()
+        .iter()
+        .map(|v| v.serialize(true, true))
+        .collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn round_trip() {
+        let records: Vec<_> = [
+            "2023-07-03 10:00 11:00 run distance=5. Morning, sunny; warm",
+            "2023-07-03 12:00 read book=\"War and peace\"",
+            "2023-07-03 23:00 - 2023-07-04 07:00 sleep",
+            "2023-07-04 10:00 - run",
+        ]
+        .iter()
+        .map(|v| Record::parse(v).unwrap())
+        .collect();
+        let ics = export_ics(&records);
+        assert!(ics.contains("SUMMARY:Morning\\, sunny\\; warm\r\n"));
+        assert!(ics.contains("CATEGORIES:run\r\n"));
+        assert!(ics.lines().all(|v| v.len() <= MAX_LINE_OCTETS));
+        assert_eq!(
+            import(&ics, "2023-07-01", "2023-07-10"),
+            vec![
+                "2023-07-03 10:00 11:00 run distance=5. Morning, sunny; warm",
+                "2023-07-03 12:00 read book=\"War and peace\"",
+                "2023-07-03 23:00 - 2023-07-04 07:00 sleep",
+            ]
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn events() {
+        let ics = "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:1
+DTSTART;TZID=Europe/Berlin:20230703T093000
+DURATION:PT15M
+SUMMARY:Standup
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=5
+EXDATE;TZID=Europe/Berlin:20230705T093000
+END:VEVENT
+BEGIN:VEVENT
+UID:1
+RECURRENCE-ID;TZID=Europe/Berlin:20230710T093000
+DTSTART;TZID=Europe/Berlin:20230710T100000
+DTEND;TZID=Europe/Berlin:20230710T101500
+SUMMARY:Standup
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20230704T180000Z
+DTEND:20230704T193000Z
+SUMMARY:heavy day
+CATEGORIES:Gym,Legs
+END:VEVENT
+BEGIN:VEVENT
+DTSTART;VALUE=DATE:20230704
+SUMMARY:Holiday
+END:VEVENT
+END:VCALENDAR
+";
+        assert_eq!(
+            import(ics, "2023-07-01", "2023-07-31"),
+            vec![
+                "2023-07-03 09:30 09:45 work meeting",
+                "2023-07-12 09:30 09:45 work meeting",
+                "2023-07-17 09:30 09:45 work meeting",
+                "2023-07-10 10:00 10:15 work meeting",
+                "2023-07-04 18:00 19:30 gym. legs. Heavy day",
+            ]
+        );
+        // Window limits recurring events
+        assert_eq!(import(ics, "2023-07-11", "2023-07-12").len(), 3);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn recurrence() {
+        let occurrences = |rule: &str, start: &str, from: &str, to: &str| -> Vec<String> {
+            Rule::parse(rule)
+                .unwrap()
+                .occurrences(
+                    start.parse().unwrap(),
+                    from.parse().unwrap(),
+                    to.parse().unwrap(),
+                )
+                .iter()
+                .map(|v| v.to_string())
+                .collect()
+        };
+        assert_eq!(
+            occurrences(
+                "FREQ=DAILY;INTERVAL=2",
+                "2023-07-03 10:00",
+                "2023-07-04",
+                "2023-07-09"
+            ),
+            vec!["2023-07-05 10:00", "2023-07-07 10:00", "2023-07-09 10:00"]
+        );
+        assert_eq!(
+            occurrences(
+                "FREQ=MONTHLY;UNTIL=20230601",
+                "2023-01-31 10:00",
+                "2023-01-01",
+                "2023-12-31"
+            ),
+            vec!["2023-01-31 10:00", "2023-03-31 10:00", "2023-05-31 10:00"]
+        );
+        assert_eq!(
+            occurrences(
+                "FREQ=YEARLY;COUNT=2",
+                "2023-07-03 10:00",
+                "2020-01-01",
+                "2030-01-01"
+            ),
+            vec!["2023-07-03 10:00", "2024-07-03 10:00"]
+        );
+        for rule in [
+            "FREQ=HOURLY",
+            "FREQ=MONTHLY;BYDAY=1MO",
+            "FREQ=DAILY;BYMONTH=1",
+            "INTERVAL=2",
+        ] {
+            assert_eq!(Rule::parse(rule), None, "rule {rule}");
+        }
+        assert_eq!(parse_duration("P1DT1H30M"), Some(Duration::new(25, 30)));
+        assert_eq!(parse_duration("PT1H30"), None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn bad_input() {
+        assert_eq!(parse_datetime("202é703T100000"), None);
+        assert_eq!(parse_duration("P99999999999999W"), None);
+        assert_eq!(parse_duration("PT9999999999999999H"), None);
+        let event = |props: &str| {
+            let ics = format!(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\n{props}\nSUMMARY:Run\nEND:VEVENT\nEND:VCALENDAR\n"
+            );
+            import_ics(
+                &ics,
+                &TagMapping::default(),
+                "2023-07-01".parse().unwrap(),
+                "2023-07-31".parse().unwrap(),
+            )
+        };
+        let cases = [
+            (
+                "DTSTART:202é703T100000",
+                IcsError::BadDateTime("202é703T100000".to_string(), 2),
+            ),
+            (
+                "DTSTART:20230703T100000\nDURATION:P99999999999999W",
+                IcsError::BadDuration("P99999999999999W".to_string(), 2),
+            ),
+            (
+                "DTSTART:20230703T100000\nDURATION:PT9999999999999999H",
+                IcsError::BadDuration("PT9999999999999999H".to_string(), 2),
+            ),
+            (
+                "DTSTART:20230703T100000\nDURATION:P999999999W",
+                IcsError::BadDuration("P999999999W".to_string(), 2),
+            ),
+        ];
+        for (props, want) in cases {
+            assert_eq!(event(props), Err(vec![want]), "{props}");
+        }
+    }
+}
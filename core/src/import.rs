@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    date_time::datetime::{DateTime, DateTimeRange},
+    parsing::tokenizer::is_name_char,
+    record::Entry,
+};
+
+/*
+Shared part of importing entries from other trackers and calendars. Importers convert their records to
+`ImportedEntry` and project or tag names there are mapped with user provided mapping, e.g. `Client work=work`.
+Unmapped names are converted to valid tag names as is
+*/
+
+/// Record of other tracker, tags are names of projects, tasks, categories or tags before the mapping
+#[derive(Debug, PartialEq)]
+pub struct ImportedEntry {
+    pub start: DateTime,
+    pub end: DateTime,
+    pub tags: Vec<String>,
+    pub comment: Option<String>,
+}
+
+impl ImportedEntry {
+    /// Converts to the entry with tags mapped, result is validated by parsing
+    pub fn to_entry(&self, mapping: &TagMapping) -> Result<Entry, String> {
+        let tags = self
+            .tags
+            .iter()
+            .map(|v| mapping.map(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        if tags.is_empty() {
+            return Err("No project or tags to use as a tag".to_string());
+        }
+        let date_range = DateTimeRange::new(self.start, self.end)?;
+        let mut text = format!("{date_range} {}", tags.join(". "));
+        if let Some(comment) = self.comment.as_deref().and_then(to_comment) {
+            text.push_str(". ");
+            text.push_str(&comment);
+        }
+        Entry::parse(&text).map_err(|err| err.diagnostic(&text, true).render(&text))
+    }
+}
+
+/// Maps names of other trackers to qqself tags. Mapped value could be any tags text, e.g. `run distance=5`
+#[derive(Default)]
+pub struct TagMapping(BTreeMap<String, String>);
+
+impl TagMapping {
+    /// Parses rules in format `Name in other tracker=qqself tags`
+    pub fn new(rules: &[String]) -> Result<Self, String> {
+        let mut mapping = BTreeMap::new();
+        for rule in rules {
+            let (from, to) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Mapping has to be in format from=to, got {rule}"))?;
+            mapping.insert(from.trim().to_string(), to.trim().to_string());
+        }
+        Ok(Self(mapping))
+    }
+
+    pub fn map(&self, name: &str) -> Result<String, String> {
+        if let Some(mapped) = self.0.get(name) {
+            return Ok(mapped.clone());
+        }
+        // Hierarchy of the source, e.g. `Music/Drums` project, is kept as a namespaced tag
+        let parts: Vec<String> = name
+            .split('/')
+            .map(|part| {
+                part.chars()
+                    .flat_map(|c| c.to_lowercase())
+                    .filter(|c| is_name_char(*c))
+                    .collect()
+            })
+            .filter(|v: &String| !v.is_empty())
+            .collect();
+        let tag = parts.join("/");
+        if tag.is_empty() {
+            return Err(format!(
+                "Cannot convert {name} to a tag, add mapping for it"
+            ));
+        }
+        Ok(tag)
+    }
+}
+
+/// Comments have to start with uppercase letter, otherwise those would be parsed as tags
+fn to_comment(s: &str) -> Option<String> {
+    let s = s.trim().replace('\n', " ");
+    let mut chars = s.chars();
+    let first = chars.next()?;
+    let upper: String = first.to_uppercase().collect();
+    if upper.starts_with(char::is_uppercase) {
+        Some(upper + chars.as_str())
+    } else {
+        Some(format!("Note: {s}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn converting() {
+        let mapping = TagMapping::new(&["Client work=work project=acme".to_string()]).unwrap();
+        let entry = |start: &str, end: &str, tags: &[&str], comment: Option<&str>| {
+            ImportedEntry {
+                start: start.parse().unwrap(),
+                end: end.parse().unwrap(),
+                tags: tags.iter().map(|v| v.to_string()).collect(),
+                comment: comment.map(|v| v.to_string()),
+            }
+            .to_entry(&mapping)
+            .map(|v| v.serialize(true, true))
+        };
+        assert_eq!(
+            entry(
+                "2023-07-03 10:00",
+                "2023-07-03 11:30",
+                &["Client work", "Deep Focus"],
+                Some("fixing bugs")
+            ),
+            Ok("2023-07-03 10:00 11:30 work project=acme. deepfocus. Fixing bugs".to_string())
+        );
+        assert_eq!(
+            entry(
+                "2023-07-03 23:00",
+                "2023-07-04 07:00",
+                &["Sleep"],
+                Some("8h")
+            ),
+            Ok("2023-07-03 23:00 - 2023-07-04 07:00 sleep. Note: 8h".to_string())
+        );
+        assert_eq!(
+            entry(
+                "2023-07-03 12:00",
+                "2023-07-03 13:00",
+                &["Music / Drums", "Спорт"],
+                None
+            ),
+            Ok("2023-07-03 12:00 13:00 music/drums. спорт".to_string())
+        );
+        assert!(entry("2023-07-03 10:00", "2023-07-03 11:00", &["!!!"], None).is_err());
+        assert!(entry("2023-07-03 10:00", "2023-07-03 11:00", &[], None).is_err());
+        assert!(entry("2023-07-03 11:00", "2023-07-03 10:00", &["Sleep"], None).is_err());
+    }
+}
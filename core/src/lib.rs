@@ -1,12 +1,23 @@
 pub mod api;
 pub mod binary_text;
+pub mod computed;
 pub mod data_views;
 pub mod date_time;
 pub mod db;
 pub mod encryption;
+#[cfg(feature = "serde")]
+pub mod export;
+pub mod history;
+pub mod ical;
+pub mod import;
 pub mod parsing;
 pub mod progress;
 pub mod record;
+pub mod rename;
+pub mod resolution;
+pub mod schema;
+pub mod units;
+pub mod version;
 
 /// Returns compile time build info
 pub fn build_info() -> String {
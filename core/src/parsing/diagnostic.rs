@@ -0,0 +1,202 @@
+use std::{fmt::Display, ops::Range};
+
+use super::tokenizer::{Token, TokenizingError};
+
+/// Detailed information about the parsing failure which could be shown to the user, e.g. by highlighting the
+/// failed part of the input and suggesting the fix
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    /// Byte range of the input diagnostic refers to. Empty range points to the position in the input
+    pub span: Range<usize>,
+    /// Tokens that were expected at the position, could be empty if anything else is unexpected
+    pub expected: Vec<Token>,
+    /// Human readable suggestion how to fix the input
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(message: String, span: Range<usize>) -> Self {
+        Self {
+            message,
+            span,
+            expected: vec![],
+            hint: None,
+        }
+    }
+
+    pub(crate) fn from_tokenizing(input: &str, err: &TokenizingError, expected: &[Token]) -> Self {
+        let (pos, hint) = match err {
+            TokenizingError::Expected(token, _, pos) => (*pos, suggest_fix(input, *token, *pos)),
+            TokenizingError::DateOrTimeExpected(pos) => (
+                *pos,
+                Some(
+                    "add end time like 10:00, duration like +1h, '-' for running entry or tags"
+                        .to_string(),
+                ),
+            ),
+            TokenizingError::TagsNotFound(pos) => (
+                *pos,
+                Some("tags start with lowercase letter or digit, e.g. 'run'".to_string()),
+            ),
+        };
+        Self {
+            message: err.to_string(),
+            span: word_span(input, pos),
+            expected: expected.to_vec(),
+            hint,
+        }
+    }
+
+    /// Renders the input with the diagnostic span underlined, useful for terminal output
+    pub fn render(&self, input: &str) -> String {
+        let prefix = input.get(..self.span.start).unwrap_or(input);
+        let underlined = input.get(self.span.clone()).unwrap_or_default();
+        let mut out = format!(
+            "{}\n{}{}",
+            input,
+            " ".repeat(prefix.chars().count()),
+            "^".repeat(underlined.chars().count().max(1))
+        );
+        out.push(' ');
+        out.push_str(&self.to_string());
+        out
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.hint {
+            Some(hint) => f.write_fmt(format_args!("{}, {}", self.message, hint)),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Converts char position to the byte position in the input
+pub(crate) fn byte_pos(input: &str, char_pos: usize) -> usize {
+    input
+        .char_indices()
+        .nth(char_pos)
+        .map(|(idx, _)| idx)
+        .unwrap_or(input.len())
+}
+
+/// Span of the word containing the char position, words are separated by spaces. Empty span at the end of input
+pub(crate) fn word_span(input: &str, char_pos: usize) -> Range<usize> {
+    let pos = byte_pos(input, char_pos);
+    if pos == input.len() {
+        return pos..pos;
+    }
+    let start = input[..pos]
+        .rfind(char::is_whitespace)
+        .map(|v| v + 1)
+        .unwrap_or(0);
+    let end = input[pos..]
+        .find(char::is_whitespace)
+        .map(|v| v + pos)
+        .unwrap_or(input.len());
+    start..end
+}
+
+/// Looks at the failed word and tries to guess what user actually meant
+fn suggest_fix(input: &str, token: Token, char_pos: usize) -> Option<String> {
+    let word = &input[word_span(input, char_pos)];
+    let digits: String = word.chars().filter(|c| c.is_ascii_digit()).collect();
+    let only_digits_and = |separators: &[char]| {
+        !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_digit() || separators.contains(&c))
+    };
+    let suggestion = match token {
+        Token::Time | Token::TimeSeparator if only_digits_and(&[':', '.', '-']) => {
+            match digits.len() {
+                1 | 2 if !word.contains([':', '.', '-']) => Some(format!("{:0>2}:00", digits)),
+                3 | 4 => {
+                    let (hours, minutes) = digits.split_at(digits.len() - 2);
+                    Some(format!("{:0>2}:{}", hours, minutes))
+                }
+                _ => None,
+            }
+        }
+        Token::Date | Token::DateSeparator if only_digits_and(&['-']) => {
+            let parts: Vec<_> = word.split('-').collect();
+            match parts.as_slice() {
+                [year, month, day] if year.len() == 4 && month.len() <= 2 && day.len() <= 2 => {
+                    Some(format!("{}-{:0>2}-{:0>2}", year, month, day))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    suggestion
+        .filter(|v| v != word)
+        .map(|v| format!("did you mean {v}?"))
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use crate::parsing::tokenizer::Tokenizer;
+
+    use super::*;
+
+    fn diagnose(input: &str) -> Diagnostic {
+        let tokens = Tokenizer::new(input, true);
+        Diagnostic::from_tokenizing(input, &tokens.error.unwrap(), &tokens.expected_next)
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn hints() {
+        let cases = vec![
+            (
+                "2023-07-03 9:00 11:00 run",
+                Some("did you mean 09:00?"),
+                11..15,
+            ),
+            (
+                "2023-07-03 10:00 1130 run",
+                Some("did you mean 11:30?"),
+                17..21,
+            ),
+            (
+                "2023-07-03 10:00 11 run",
+                Some("did you mean 11:00?"),
+                17..19,
+            ),
+            (
+                "2023-7-3 10:00 11:00 run",
+                Some("did you mean 2023-07-03?"),
+                0..8,
+            ),
+            (
+                "2023-07-03 10:00 11:00 Run",
+                Some("tags start with lowercase letter or digit, e.g. 'run'"),
+                23..26,
+            ),
+            ("2023-07-03 10:00 1x:00 run", None, 17..22),
+        ];
+        for (input, hint, span) in cases {
+            let got = diagnose(input);
+            assert_eq!(got.hint.as_deref(), hint, "input |{input}|");
+            assert_eq!(got.span, span, "input |{input}|");
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn render() {
+        let input = "2023-07-03 9:00 11:00 run";
+        assert_eq!(
+            diagnose(input).render(input),
+            "2023-07-03 9:00 11:00 run
+           ^^^^ Error parsing Time: expected 'digit', did you mean 09:00?"
+        );
+        let input = "2023-07-03 10:00 11:00";
+        assert_eq!(diagnose(input).span, 22..22);
+    }
+}
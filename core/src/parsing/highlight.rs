@@ -0,0 +1,263 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::{db::Query, record::Entry};
+
+use super::{diagnostic::Diagnostic, tokenizer::Token, tokenizer::Tokenizer};
+
+/// Kind of the highlighted part of the input. It's exposed by bindings, so it stays the same when tokenizer changes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HighlightKind {
+    Comment,
+    Date,
+    DateSeparator,
+    DateTimeSeparator,
+    Duration,
+    PropertyName,
+    PropertyOperator,
+    PropertyValue,
+    TagName,
+    TagSeparator,
+    Time,
+    TimeSeparator,
+    /// Part of the input that failed to parse
+    Error,
+}
+
+impl HighlightKind {
+    fn from_token(token: Token) -> Option<Self> {
+        Some(match token {
+            Token::Comment => HighlightKind::Comment,
+            Token::Date => HighlightKind::Date,
+            Token::DateSeparator => HighlightKind::DateSeparator,
+            Token::DateTimeSeparator => HighlightKind::DateTimeSeparator,
+            Token::Duration => HighlightKind::Duration,
+            Token::PropertyName => HighlightKind::PropertyName,
+            Token::PropertyOperator => HighlightKind::PropertyOperator,
+            Token::PropertyValue => HighlightKind::PropertyValue,
+            Token::Space => return None,
+            Token::TagName => HighlightKind::TagName,
+            Token::TagSeparator => HighlightKind::TagSeparator,
+            Token::Time => HighlightKind::Time,
+            Token::TimeSeparator => HighlightKind::TimeSeparator,
+        })
+    }
+}
+
+impl Display for HighlightKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+/// Kind of the token with the byte range of the input it covers
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TokenSpan {
+    pub kind: HighlightKind,
+    pub span: Range<usize>,
+}
+
+/// Span of the input for syntax highlighting in clients. Positions are in UTF-16 code units which JavaScript and
+/// Swift strings are using
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HighlightSpan {
+    pub kind: HighlightKind,
+    pub start: usize,
+    pub end: usize,
+    /// Error message with a hint for `Error` spans
+    pub message: Option<String>,
+}
+
+/// Token spans for coloring the input in editors as user types and an error to underline, if any
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Highlighting {
+    /// Consecutive characters of the same token are merged in one span, spaces are skipped
+    pub tokens: Vec<TokenSpan>,
+    pub error: Option<Diagnostic>,
+}
+
+impl Highlighting {
+    /// Highlights an entry which starts with a date
+    pub fn entry(input: &str) -> Self {
+        Self {
+            tokens: token_spans(input, true),
+            error: Entry::parse(input)
+                .err()
+                .map(|err| err.diagnostic(input, true)),
+        }
+    }
+
+    /// Highlights a query which has only tags and no date
+    pub fn query(input: &str) -> Self {
+        Self {
+            tokens: token_spans(input, false),
+            error: Query::new(input)
+                .err()
+                .map(|err| err.diagnostic(input, false)),
+        }
+    }
+
+    /// Returns token spans of the input with UTF-16 positions, error span is added at the end if input is invalid
+    pub fn spans(input: &str, is_entry: bool) -> Vec<HighlightSpan> {
+        let highlighting = match is_entry {
+            true => Highlighting::entry(input),
+            false => Highlighting::query(input),
+        };
+        let pos = |byte_pos: usize| utf16_pos(input, byte_pos);
+        let mut spans: Vec<_> = highlighting
+            .tokens
+            .iter()
+            .map(|v| HighlightSpan {
+                kind: v.kind,
+                start: pos(v.span.start),
+                end: pos(v.span.end),
+                message: None,
+            })
+            .collect();
+        if let Some(error) = highlighting.error {
+            spans.push(HighlightSpan {
+                kind: HighlightKind::Error,
+                start: pos(error.span.start),
+                end: pos(error.span.end),
+                message: Some(error.to_string()),
+            });
+        }
+        spans
+    }
+}
+
+fn token_spans(input: &str, with_date: bool) -> Vec<TokenSpan> {
+    let tokenizer = Tokenizer::new(input, with_date);
+    let mut spans: Vec<TokenSpan> = Vec::new();
+    for ((start, c), token) in input.char_indices().zip(tokenizer.tokens) {
+        let Some(kind) = HighlightKind::from_token(token) else {
+            continue;
+        };
+        let end = start + c.len_utf8();
+        match spans.last_mut() {
+            Some(last) if last.kind == kind && last.span.end == start => last.span.end = end,
+            _ => spans.push(TokenSpan {
+                kind,
+                span: start..end,
+            }),
+        }
+    }
+    spans
+}
+
+/// Converts byte position in the input to UTF-16 code units position which JavaScript and Swift strings are using
+pub fn utf16_pos(input: &str, byte_pos: usize) -> usize {
+    input
+        .get(..byte_pos)
+        .unwrap_or(input)
+        .encode_utf16()
+        .count()
+}
+
+/// Converts UTF-16 code units position to the byte position in the input
+pub fn utf16_to_byte_pos(input: &str, utf16_pos: usize) -> usize {
+    let mut units = 0;
+    for (idx, c) in input.char_indices() {
+        if units >= utf16_pos {
+            return idx;
+        }
+        units += c.len_utf16();
+    }
+    input.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn tokens(highlighting: &Highlighting, input: &str) -> Vec<String> {
+        highlighting
+            .tokens
+            .iter()
+            .map(|v| format!("{}:{}", v.kind, &input[v.span.clone()]))
+            .collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn entry() {
+        let input = "2023-07-03 10:00 11:00 run distance>5. Morning";
+        let got = Highlighting::entry(input);
+        assert_eq!(
+            tokens(&got, input),
+            vec![
+                "Date:2023",
+                "DateSeparator:-",
+                "Date:07",
+                "DateSeparator:-",
+                "Date:03",
+                "Time:10",
+                "TimeSeparator::",
+                "Time:00",
+                "Time:11",
+                "TimeSeparator::",
+                "Time:00",
+                "TagName:run",
+                "PropertyName:distance",
+                "PropertyOperator:>",
+                "PropertyValue:5",
+                "TagSeparator:.",
+                "Comment:Morning",
+            ]
+        );
+        assert_eq!(got.error, None);
+
+        // Tokens are available up to the error
+        let input = "2023-07-03 10:00 9:00 run";
+        let got = Highlighting::entry(input);
+        assert_eq!(got.tokens.last().unwrap().span, 17..18);
+        assert_eq!(got.error.unwrap().span, 17..21);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn query() {
+        let input = "run. filter after=2023";
+        let got = Highlighting::query(input);
+        assert_eq!(
+            tokens(&got, input),
+            vec![
+                "TagName:run",
+                "TagSeparator:.",
+                "TagName:filter",
+                "PropertyName:after",
+                "PropertyOperator:=",
+                "PropertyValue:2023",
+            ]
+        );
+        // Semantic errors are reported as well
+        assert!(got.error.is_some());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn spans() {
+        let input = "2023-07-03 10:00 9:00 ☕";
+        let got = Highlighting::spans(input, true);
+        let error = got.last().unwrap();
+        assert_eq!(error.kind, HighlightKind::Error);
+        assert_eq!((error.start, error.end), (17, 21));
+        assert!(error.message.is_some());
+
+        let got = Highlighting::spans("run. Go 🏃 now", false);
+        let comment = got.last().unwrap();
+        assert_eq!(comment.kind, HighlightKind::Comment);
+        assert_eq!((comment.start, comment.end), (5, 14));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn utf16() {
+        let input = "run. Café ☕ time";
+        let pos = input.find("time").unwrap();
+        assert_eq!(utf16_pos(input, pos), 12);
+        assert_eq!(utf16_to_byte_pos(input, 12), pos);
+        assert_eq!(utf16_to_byte_pos(input, 100), input.len());
+    }
+}
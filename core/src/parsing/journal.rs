@@ -0,0 +1,347 @@
+use std::collections::BTreeSet;
+
+use thiserror::Error;
+
+use crate::{
+    date_time::datetime::{DateDay, DateTimeRange},
+    db::Record,
+};
+
+/*
+Journal is a plain text file with one entry per line. Repeating the date on every line is noisy for hand
+written journals, so entries can be grouped under a day header and have only the time:
+
+2023-07-03
+08:00 weight kg=72.4
+10:00 11:00 run
+23:00 - 2023-07-04 07:00 sleep
+
+Entries with a full date are supported anywhere in the journal. Empty lines and `#` comments are skipped
+*/
+
+/// Length of the day header in format YYYY-MM-DD
+const DAY_HEADER_SIZE: usize = 10;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum JournalError {
+    #[error("Bad day header: {0} at line {1}")]
+    BadDay(String, usize),
+    #[error("Time only entry without preceding day header at line {0}")]
+    NoDay(usize),
+    #[error("Bad entry: {0} at line {1}")]
+    BadEntry(String, usize),
+}
+
+/// Reads the journal line by line and resolves time only entries to full entries using the last day header
+#[derive(Default)]
+pub struct JournalReader {
+    day: Option<DateDay>,
+    line: usize,
+}
+
+impl JournalReader {
+    /// Reads next line of the journal and returns full entry text if the line contains an entry
+    pub fn read_line(&mut self, line: &str) -> Result<Option<String>, JournalError> {
+        self.line += 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        if is_day_header(line) {
+            let day = line
+                .parse::<DateDay>()
+                .map_err(|err| JournalError::BadDay(err, self.line))?;
+            self.day = Some(day);
+            return Ok(None);
+        }
+        if !starts_with_time(line) {
+            return Ok(Some(line.to_string())); // Entry with a full date
+        }
+        match self.day {
+            Some(day) => Ok(Some(format!("{day} {line}"))),
+            None => Err(JournalError::NoDay(self.line)),
+        }
+    }
+
+    /// Number of the last read line, starting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+fn is_day_header(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes.len() == DAY_HEADER_SIZE && bytes[4] == b'-' && bytes[7] == b'-'
+}
+
+fn starts_with_time(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes.len() > 2 && bytes[0].is_ascii_digit() && bytes[1].is_ascii_digit() && bytes[2] == b':'
+}
+
+/// Writes records as a journal with time only entries grouped under day headers. Result can be read back
+/// with `JournalReader`
+pub fn write_journal<'a>(records: impl IntoIterator<Item = &'a Record>) -> String {
+    let mut output = String::new();
+    let mut prev_day = None;
+    for record in records {
+        let day = record.date_range().start().date();
+        if prev_day != Some(day) {
+            if prev_day.is_some() {
+                output.push('\n');
+            }
+            output.push_str(&format!("{day}\n"));
+            prev_day = Some(day);
+        }
+        output.push_str(&record.to_string(false, true));
+        output.push('\n');
+    }
+    output
+}
+
+/// Collapses repeated spaces outside of quoted values, hand written entries often have those
+fn collapse_spaces(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut quoted = false;
+    for c in line.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if !quoted && c.is_whitespace() && output.ends_with(' ') {
+            continue;
+        }
+        output.push(if !quoted && c.is_whitespace() { ' ' } else { c });
+    }
+    output
+}
+
+/// Journal rewritten in canonical form
+#[derive(Debug, PartialEq, Eq)]
+pub struct FormattedJournal {
+    pub text: String,
+    /// Numbers of the input lines, starting from 1, which were changed, moved or removed by formatting
+    pub changed_lines: Vec<usize>,
+}
+
+/// Line of the input with its number, starting from 1
+type Line = (usize, String);
+
+/// Entry with comments right above it, those are moved together when sorting
+struct FormatItem {
+    comments: Vec<Line>,
+    entry: Line,
+    date_range: DateTimeRange,
+}
+
+/// Formats the journal to the canonical form. Entries are serialized the same way `Entry::serialize` does and
+/// sorted by date range. Sorting happens only inside groups separated by blank lines or day headers, so the
+/// journal structure is preserved. Comments are kept above the entry they precede, repeated blank lines are
+/// collapsed, time only entries are used under day headers if possible
+pub fn format_journal(input: &str) -> Result<FormattedJournal, JournalError> {
+    let lines: Vec<_> = input.lines().collect();
+    let mut output: Vec<Line> = Vec::new();
+    let mut changed = BTreeSet::new();
+    let mut day: Option<DateDay> = None;
+    let mut group: Vec<FormatItem> = Vec::new();
+    let mut comments: Vec<Line> = Vec::new();
+
+    let flush = |group: &mut Vec<FormatItem>,
+                 comments: &mut Vec<Line>,
+                 output: &mut Vec<Line>,
+                 changed: &mut BTreeSet<usize>| {
+        let mut sorted: Vec<_> = group.drain(..).enumerate().collect();
+        sorted.sort_by_key(|(_, item)| item.date_range);
+        for (pos, (original_pos, item)) in sorted.into_iter().enumerate() {
+            if pos != original_pos {
+                changed.extend(item.comments.iter().map(|(line, _)| *line));
+                changed.insert(item.entry.0);
+            }
+            output.extend(item.comments);
+            output.push(item.entry);
+        }
+        output.append(comments);
+    };
+
+    for (idx, raw) in lines.iter().enumerate() {
+        let line_number = idx + 1;
+        let line = raw.trim();
+        if line.is_empty() {
+            flush(&mut group, &mut comments, &mut output, &mut changed);
+            match output.last() {
+                Some((_, prev)) if !prev.is_empty() => output.push((line_number, String::new())),
+                _ => {
+                    changed.insert(line_number); // Repeated or leading blank line
+                }
+            }
+        } else if line.starts_with('#') {
+            comments.push((line_number, line.to_string()));
+        } else if is_day_header(line) {
+            flush(&mut group, &mut comments, &mut output, &mut changed);
+            let parsed = line
+                .parse::<DateDay>()
+                .map_err(|err| JournalError::BadDay(err, line_number))?;
+            day = Some(parsed);
+            output.push((line_number, parsed.to_string()));
+        } else {
+            let full = if starts_with_time(line) {
+                match day {
+                    Some(day) => format!("{day} {line}"),
+                    None => return Err(JournalError::NoDay(line_number)),
+                }
+            } else {
+                line.to_string()
+            };
+            let record = Record::parse(&full)
+                .or_else(|err| Record::parse(&collapse_spaces(&full)).map_err(|_| err))
+                .map_err(|err| JournalError::BadEntry(err, line_number))?;
+            let date_range = *record.date_range();
+            let include_date = day != Some(date_range.start().date());
+            group.push(FormatItem {
+                comments: std::mem::take(&mut comments),
+                entry: (line_number, record.to_string(include_date, true)),
+                date_range,
+            });
+        }
+    }
+    flush(&mut group, &mut comments, &mut output, &mut changed);
+    while let Some((line_number, _)) = output.last().filter(|(_, v)| v.is_empty()) {
+        changed.insert(*line_number); // Trailing blank lines
+        output.pop();
+    }
+
+    for (line_number, text) in &output {
+        if lines[line_number - 1] != text {
+            changed.insert(*line_number);
+        }
+    }
+    Ok(FormattedJournal {
+        text: output.into_iter().map(|(_, v)| v + "\n").collect(),
+        changed_lines: changed.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn read(input: &str) -> Result<Vec<String>, JournalError> {
+        let mut reader = JournalReader::default();
+        let mut entries = Vec::new();
+        for line in input.lines() {
+            if let Some(entry) = reader.read_line(line)? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn reading() {
+        let journal = "
+# Comments and empty lines are skipped
+2023-07-03
+08:00 weight kg=72.4
+10:00 11:00 run
+23:00 - 2023-07-04 07:00 sleep
+2023-07-01 10:00 11:00 read. Full entries are fine as well
+
+2023-07-04
+09:00 09:30 run
+";
+        assert_eq!(
+            read(journal).unwrap(),
+            vec![
+                "2023-07-03 08:00 weight kg=72.4",
+                "2023-07-03 10:00 11:00 run",
+                "2023-07-03 23:00 - 2023-07-04 07:00 sleep",
+                "2023-07-01 10:00 11:00 read. Full entries are fine as well",
+                "2023-07-04 09:00 09:30 run",
+            ]
+        );
+
+        assert_eq!(read("10:00 11:00 run"), Err(JournalError::NoDay(1)));
+        assert!(matches!(
+            read("2023-07-03\n2023-13-01\n10:00 11:00 run"),
+            Err(JournalError::BadDay(_, 2))
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn round_trip() {
+        let records = vec![
+            Record::parse("2023-07-03 08:00 weight kg=72.4").unwrap(),
+            Record::parse("2023-07-03 10:00 11:00 run. entry revision=2").unwrap(),
+            Record::parse("2023-07-03 23:00 - 2023-07-04 07:00 sleep").unwrap(),
+            Record::parse("2023-07-04 09:00 09:30 run. Morning").unwrap(),
+        ];
+        let journal = write_journal(&records);
+        assert_eq!(
+            journal,
+            "2023-07-03
+08:00 weight kg=72.4
+10:00 11:00 run. entry revision=2
+23:00 - 2023-07-04 07:00 sleep
+
+2023-07-04
+09:00 09:30 run. Morning
+"
+        );
+        let got: Vec<_> = read(&journal)
+            .unwrap()
+            .iter()
+            .map(|v| Record::parse(v).unwrap())
+            .collect();
+        assert_eq!(got, records);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn formatting() {
+        let journal = "# Journal
+
+2023-07-03 12:00 13:00   read book=\"War and peace\"
+# Morning run
+2023-07-03 10:00 11:00 run   distance=5
+
+2023-07-04
+10:00 11:00 run
+09:00 09:30 run.  Warm up
+2023-07-04 12:00 13:00 read
+
+
+2023-07-01 10:00 11:00 rest
+";
+        let got = format_journal(journal).unwrap();
+        assert_eq!(
+            got.text,
+            "# Journal
+
+# Morning run
+2023-07-03 10:00 11:00 run distance=5
+2023-07-03 12:00 13:00 read book=\"War and peace\"
+
+2023-07-04
+09:00 09:30 run. Warm up
+10:00 11:00 run
+12:00 13:00 read
+
+2023-07-01 10:00 11:00 rest
+"
+        );
+        assert_eq!(got.changed_lines, vec![3, 4, 5, 8, 9, 10, 12]);
+
+        // Formatted journal stays the same
+        let again = format_journal(&got.text).unwrap();
+        assert_eq!(again.text, got.text);
+        assert!(again.changed_lines.is_empty());
+
+        assert!(matches!(
+            format_journal("2023-07-03\n10:00 9:00 run"),
+            Err(JournalError::BadEntry(_, 2))
+        ));
+    }
+}
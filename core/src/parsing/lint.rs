@@ -0,0 +1,281 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    date_time::datetime::{DateTime, DateTimeRange, Duration},
+    db::Record,
+    progress::skill::{Skill, SkillKind, SkillKinds},
+    record::Entry,
+    schema::Schemas,
+    version::Causality,
+};
+
+use super::journal::{JournalError, JournalReader};
+
+/*
+Linter checks the whole journal without stopping on the first failure, so all the issues could be fixed at once
+before uploading. Besides parsing errors it looks for entries which are valid, but most likely a mistake:
+- Different entries with the same date range and revision, those would become a `Record::Conflict`
+- Overlapping entries, e.g. finished run at 11:00, but started reading at 10:30
+- Entries not following schemas of their tags, e.g. missing required props or skills with unknown kind
+- Entries ending in the future
+- Entries longer than `LONG_DURATION`, e.g. forgotten timer or a typo in the time
+*/
+
+/// Entries longer than that are reported as suspicious
+const LONG_DURATION: Duration = Duration::new(16, 0);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LintIssue {
+    pub severity: Severity,
+    /// Line number, starting from 1
+    pub line: usize,
+    /// Column of the line, starting from 1, if the issue points to the specific part of the line
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn warning(line: usize, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            column: None,
+            message,
+        }
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.column {
+            Some(column) => f.write_fmt(format_args!(
+                "{}:{}: {}: {}",
+                self.line, column, self.severity, self.message
+            )),
+            None => f.write_fmt(format_args!(
+                "{}: {}: {}",
+                self.line, self.severity, self.message
+            )),
+        }
+    }
+}
+
+/// Checks every line of the journal and returns all the issues found sorted by line number
+pub fn lint_journal(input: &str, now: DateTime) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut reader = JournalReader::default();
+    let mut entries: Vec<(usize, Entry)> = Vec::new();
+    for raw in input.lines() {
+        let full = match reader.read_line(raw) {
+            Ok(Some(v)) => v,
+            Ok(None) => continue,
+            Err(err) => {
+                let message = match err {
+                    JournalError::BadDay(err, _) => format!("Bad day header: {err}"),
+                    JournalError::NoDay(_) => {
+                        "Time only entry without preceding day header".to_string()
+                    }
+                    JournalError::BadEntry(err, _) => err,
+                };
+                issues.push(LintIssue {
+                    severity: Severity::Error,
+                    line: reader.line(),
+                    column: Some(1 + leading_spaces(raw)),
+                    message,
+                });
+                continue;
+            }
+        };
+        match Entry::parse(&full) {
+            Ok(entry) => entries.push((reader.line(), entry)),
+            Err(err) => {
+                let diagnostic = err.diagnostic(&full, true);
+                // Time only entries got the day prepended, column has to point to the original line
+                let added = full.chars().count() - raw.trim().chars().count();
+                let pos = full[..diagnostic.span.start].chars().count();
+                issues.push(LintIssue {
+                    severity: Severity::Error,
+                    line: reader.line(),
+                    column: Some(1 + leading_spaces(raw) + pos.saturating_sub(added)),
+                    message: diagnostic.to_string(),
+                });
+            }
+        }
+    }
+
+    // Resolve revisions the same way DB does, only the latest revision of the entry matters
+    let mut latest: BTreeMap<DateTimeRange, (usize, Entry)> = BTreeMap::new();
+    for (line, entry) in entries {
+        if let Some((existing_line, existing)) = latest.get(&entry.date_range) {
+            let message = match entry.version().causality(&existing.version()) {
+                Causality::Before => continue,
+                Causality::After => None,
+                Causality::Equal if existing == &entry => None,
+                Causality::Equal => Some(format!(
+                    "Same date range and revision as the entry at line {existing_line}, entries would become a conflict"
+                )),
+                Causality::Concurrent => Some(format!(
+                    "Concurrent edit of the entry at line {existing_line}, entries would become a conflict"
+                )),
+            };
+            if let Some(message) = message {
+                issues.push(LintIssue::warning(line, message));
+                continue;
+            }
+        }
+        latest.insert(entry.date_range, (line, entry));
+    }
+
+    // Definitions are taken from resolved entries only, so deleted or edited ones don't apply
+    let mut kinds = SkillKinds::default();
+    let mut schemas = Schemas::default();
+    for (_, entry) in latest.values() {
+        if Record::Entry(entry.clone()).is_deleted_record() {
+            continue;
+        }
+        if let Some(kind) = SkillKind::from_record(entry) {
+            kinds.add(kind);
+        }
+        schemas.add(entry);
+    }
+
+    let mut prev_end: Option<(DateTime, usize)> = None;
+    for (date_range, (line, entry)) in &latest {
+        if Record::Entry(entry.clone()).is_deleted_record() {
+            continue;
+        }
+        for warning in schemas.validate(entry, &kinds) {
+            issues.push(LintIssue::warning(*line, warning.to_string()));
+        }
+        if Skill::from_record(entry).is_some() || SkillKind::from_record(entry).is_some() {
+            continue; // Definitions aren't activities, other checks don't apply
+        }
+        let end = if date_range.is_closed() {
+            date_range.end()
+        } else {
+            date_range.start()
+        };
+        if end > now {
+            issues.push(LintIssue::warning(
+                *line,
+                format!("Entry ends in the future, now is {now}"),
+            ));
+        }
+        if !date_range.is_closed() || date_range.start() == date_range.end() {
+            continue;
+        }
+        if date_range.duration() > LONG_DURATION {
+            issues.push(LintIssue::warning(
+                *line,
+                format!("Unusually long entry of {}", date_range.duration()),
+            ));
+        }
+        match prev_end {
+            Some((prev, prev_line)) if date_range.start() < prev => {
+                issues.push(LintIssue::warning(
+                    *line,
+                    format!("Overlaps with the entry at line {prev_line}"),
+                ));
+            }
+            _ => {}
+        }
+        if prev_end.is_none_or(|(prev, _)| date_range.end() > prev) {
+            prev_end = Some((date_range.end(), *line));
+        }
+    }
+    issues.sort_by_key(|v| (v.line, v.severity));
+    issues
+}
+
+fn leading_spaces(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn issues() {
+        let journal = "2023-07-03
+08:00 09:00 run
+08:30 10:00 read
+10:00 9:00 read
+  11:00 12:00 Run
+2023-07-03 12:00 13:00 rest
+2023-07-03 12:00 13:00 sleep
+2023-07-03 12:00 13:00 rest. entry revision=2 clock=laptop@1
+2023-07-03 12:00 13:00 nap. entry revision=2 clock=phone@1
+2023-07-03 23:00 - 2023-07-04 18:00 sleep
+2023-07-04 00:00 00:00 swim. skill kind=sport. Swimming
+2023-07-04 00:00 00:01 draw. skill kind=art. Drawing
+2023-07-04 00:01 00:01 skillkind name=art. Art
+2023-07-04 00:02 00:02 read. schema pages=number optional=pages
+2023-07-04 18:00 19:00 read pages=many
+2023-07-05 10:00 11:00 run
+2023-13-01
+";
+        let now = "2023-07-04 20:00".parse().unwrap();
+        let got: Vec<_> = lint_journal(journal, now)
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                "3: warning: Overlaps with the entry at line 2",
+                "4:7: error: Error parsing Time: expected 'digit', did you mean 09:00?",
+                "5:15: error: Tags were not found, tags start with lowercase letter or digit, e.g. 'run'",
+                "7: warning: Same date range and revision as the entry at line 6, entries would become a conflict",
+                "9: warning: Concurrent edit of the entry at line 8, entries would become a conflict",
+                "10: warning: Unusually long entry of 19:00",
+                "11: warning: Unknown skill kind 'sport', define it with 'skillkind name=sport'",
+                "15: warning: Prop 'pages' of 'read' should be a number, got 'many'",
+                "16: warning: Entry ends in the future, now is 2023-07-04 20:00",
+                "17:1: error: Bad day header: Value is out of range of 1..12, got 13",
+            ]
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn replaced_definitions() {
+        let journal = "2023-07-03 00:00 00:00 run. schema distance=length
+2023-07-03 00:00 00:00 entry revision=2 deleted
+2023-07-03 00:01 00:01 read. schema pages=number
+2023-07-03 00:01 00:01 read. schema pages=number optional=pages. entry revision=2
+2023-07-03 00:02 00:02 skillkind name=art. Art
+2023-07-03 00:02 00:02 entry revision=2 deleted
+2023-07-03 00:03 00:03 draw. skill kind=art. Drawing
+2023-07-03 10:00 11:00 run
+2023-07-03 11:00 12:00 read
+";
+        let now = "2023-07-04 00:00".parse().unwrap();
+        let got: Vec<_> = lint_journal(journal, now)
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            got,
+            vec!["7: warning: Unknown skill kind 'art', define it with 'skillkind name=art'"]
+        );
+    }
+}
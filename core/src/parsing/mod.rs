@@ -1,2 +1,6 @@
+pub mod diagnostic;
+pub mod highlight;
+pub mod journal;
+pub mod lint;
 pub mod parser;
 pub mod tokenizer;
@@ -11,7 +11,10 @@ use crate::{
     record::{Entry, Prop, PropOperator, PropVal, Tag},
 };
 
-use super::tokenizer::{Token, Tokenizer};
+use super::{
+    diagnostic::{byte_pos, word_span, Diagnostic},
+    tokenizer::{Token, Tokenizer, TokenizingError},
+};
 
 type InputIterator<'a> = Peekable<Zip<Chars<'a>, Iter<'a, Token>>>;
 
@@ -27,6 +30,56 @@ pub enum ParseError {
     BadOperator(String, usize),
     #[error("Unexpected: {0} at position {1}")]
     Unexpected(String, usize),
+    #[error("{0}")]
+    Syntax(Diagnostic),
+}
+
+impl ParseError {
+    /// Detailed information about the error with the span of the failed input and hints how to fix it. Entries start
+    /// with a date, queries don't
+    pub fn diagnostic(&self, input: &str, is_entry: bool) -> Diagnostic {
+        match self {
+            ParseError::Syntax(diagnostic) => diagnostic.clone(),
+            ParseError::NoTags => {
+                // Tokenizer knows the position where tags were expected
+                let tokens = Tokenizer::new(input, is_entry);
+                match &tokens.error {
+                    Some(err @ TokenizingError::TagsNotFound(_)) => {
+                        Diagnostic::from_tokenizing(input, err, &tokens.expected_next)
+                    }
+                    _ => {
+                        let mut diagnostic =
+                            Diagnostic::new(self.to_string(), input.len()..input.len());
+                        diagnostic.expected = vec![Token::TagName];
+                        diagnostic
+                    }
+                }
+            }
+            ParseError::Duplicate(_, pos) => {
+                // Position points to the end of duplicated name
+                let mut diagnostic =
+                    Diagnostic::new(self.to_string(), word_span(input, pos.saturating_sub(1)));
+                diagnostic.hint = Some("remove it or merge with the first one".to_string());
+                diagnostic
+            }
+            ParseError::BadDateTime(err, len) => {
+                let mut diagnostic = Diagnostic::new(
+                    self.to_string(),
+                    0..byte_pos(input, *len).min(input.trim_end().len()),
+                );
+                if err.contains("before the start") {
+                    diagnostic.hint = Some(
+                        "for entries ending on another day use 'YYYY-MM-DD HH:MM - YYYY-MM-DD HH:MM'"
+                            .to_string(),
+                    );
+                }
+                diagnostic
+            }
+            ParseError::BadOperator(_, pos) | ParseError::Unexpected(_, pos) => {
+                Diagnostic::new(self.to_string(), word_span(input, *pos))
+            }
+        }
+    }
 }
 
 pub struct Parser<'a> {
@@ -43,22 +96,16 @@ impl<'a> Parser<'a> {
 
     pub(crate) fn parse_date_record(&mut self) -> Result<Entry, ParseError> {
         let tokens = Tokenizer::new(self.input, true);
-        if let Some(err) = tokens.error {
-            match err {
-                super::tokenizer::TokenizingError::TagsNotFound(_) => {
-                    return Err(ParseError::NoTags)
-                }
-                super::tokenizer::TokenizingError::Expected(_, _, _) => {}
-                super::tokenizer::TokenizingError::DateOrTimeExpected(_) => {}
-            }
+        if let Some(err) = &tokens.error {
+            return Err(self.tokenizing_error(err, &tokens.expected_next));
         }
         self.entry_from_tokens(&tokens.tokens)
     }
 
     pub(crate) fn parse_record(&mut self) -> Result<(Vec<Tag>, Option<String>), ParseError> {
         let tokens = Tokenizer::new(self.input, false);
-        if let Some(err) = tokens.error {
-            todo!("How should we handle errors and what kind of errors may happen actually in query parsing? Err: {}", err.to_string());
+        if let Some(err) = &tokens.error {
+            return Err(self.tokenizing_error(err, &tokens.expected_next));
         }
         let mut iter = self.input.chars().zip(&tokens.tokens).peekable();
         let tags = self.parse_tags(&mut iter)?;
@@ -66,6 +113,13 @@ impl<'a> Parser<'a> {
         Ok((tags, comment))
     }
 
+    fn tokenizing_error(&self, err: &TokenizingError, expected: &[Token]) -> ParseError {
+        match err {
+            TokenizingError::TagsNotFound(_) => ParseError::NoTags,
+            _ => ParseError::Syntax(Diagnostic::from_tokenizing(self.input, err, expected)),
+        }
+    }
+
     fn entry_from_tokens(&mut self, tokens: &[Token]) -> Result<Entry, ParseError> {
         let mut iter = self.input.chars().zip(tokens).peekable();
         let date_range = self.parse_daterange(&mut iter)?;
@@ -83,11 +137,15 @@ impl<'a> Parser<'a> {
                 Token::Space,
                 Token::Time,
                 Token::TimeSeparator,
+                Token::Duration,
             ],
         );
         // Plus/Minus one is for the extra space at the end
         if date_range_input.len() != DateTimeRange::SIZE_LONG + 1
             && date_range_input.len() != DateTimeRange::SIZE_SHORT + 1
+            && date_range_input.len() != DateTimeRange::SIZE_INSTANT + 1
+            && date_range_input.len() != DateTimeRange::SIZE_RUNNING + 1
+            && !date_range_input.contains('+')
         {
             return Err(ParseError::BadDateTime(
                 "Failed to parse the date because of unexpected string length".to_string(),
@@ -147,13 +205,12 @@ impl<'a> Parser<'a> {
                 _ => PropOperator::Eq,
             };
             let val = self.read_while(iter, &[Token::PropertyValue]);
-            // Property value may be surrounded with the quotes, remove those as unnecessary noise
-            let val = val.trim_matches('"').to_string();
             props.push(Prop {
                 name,
                 val: PropVal::parse(val),
                 operator,
                 start_pos: 0, // TODO Remove
+                computed: false,
             })
         }
         Ok(props)
@@ -211,6 +268,7 @@ mod tests {
             val,
             operator: PropOperator::Eq,
             start_pos: 0,
+            computed: false,
         };
         let tag = |name: &str, props: Vec<Prop>| Tag::new(name.to_string(), props, 0);
 
@@ -225,6 +283,29 @@ mod tests {
                 "2000-01-01 01:01 - 2000-01-01 01:01 foo",
                 Entry::new(dr(1, 1), None, vec![tag("foo", vec![])]),
             ),
+            (
+                // Point in time entry
+                "2000-01-01 01:01 weight kg=72.4",
+                Entry::new(
+                    DateTimeRange::instant(DateTime::new(*BASE_DATE, Time::new(1, 1))),
+                    None,
+                    vec![tag("weight", vec![prop("kg", PropVal::Number(72.4f32))])],
+                ),
+            ),
+            (
+                // Start with a duration
+                "2000-01-01 01:01 +0h0m abc",
+                Entry::new(dr(1, 1), None, vec![tag("abc", vec![])]),
+            ),
+            (
+                // Running entry
+                "2000-01-01 01:01 - abc",
+                Entry::new(
+                    DateTimeRange::running(DateTime::new(*BASE_DATE, Time::new(1, 1))),
+                    None,
+                    vec![tag("abc", vec![])],
+                ),
+            ),
             (
                 // Simple tag, simple prop
                 "2000-01-01 01:01 01:01 tag1 prop1",
@@ -471,10 +552,12 @@ mod tests {
             ),
             (
                 "tag1 prop1=a prop1=b",
-                ParseError::BadDateTime(
-                    "Failed to parse the date because of unexpected string length".to_string(),
-                    0,
-                ),
+                ParseError::Syntax(Diagnostic {
+                    message: "Error parsing Date: expected 'digit'".to_string(),
+                    span: 0..4,
+                    expected: vec![Token::Date],
+                    hint: None,
+                }),
             ),
             (
                 "2010-01-01 10:00 09:00 tag1 prop1=a prop1=b",
@@ -487,4 +570,19 @@ mod tests {
             assert_eq!(got, expected);
         }
     }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn query_parsing_errors() {
+        // Tokenizing errors in queries are returned instead of panicking
+        for (input, span) in [("Comment only", 0..7), ("tag1 prop1=\"val", 15..15)] {
+            let mut parser = Parser::new(input);
+            let got = parser.parse_record().err().unwrap();
+            assert_eq!(got.diagnostic(input, false).span, span, "input |{input}|");
+        }
+        // Entries without tags point after the date
+        let input = "2010-01-01 01:01 01:01 Comment only";
+        let got = Parser::new(input).parse_date_record().err().unwrap();
+        assert_eq!(got.diagnostic(input, true).span, 23..30);
+    }
 }
@@ -4,18 +4,19 @@ use thiserror::Error;
 
 /* Parsing of entries like: `2021-11-31 21:00 23:00 tag1 prop1=val1. Comment text`. Grammar:
       INPUT -> DATES TAGS COMMENT?
-      DATES -> DATETIME ('-' DATETIME | TIME)
+      DATES -> DATETIME ('-' DATETIME? | TIME | '+' DURATION)?
       DATETIME -> DATE TIME
       DATE -> \d\d\d\d'-'\d\d'-'\d\d
       TIME -> \d\d':'\d\d
+      DURATION -> (\d+'h')?(\d+'m')? | \d+':'\d\d
       TAGS -> TAG ('.' TAGS)*
-      TAG -> TAGNAME (PROP)*
+      TAG -> TAGNAME ('/' TAGNAME)* (PROP)*
       PROP_OP -> '='|'<'|'>'
       PROP -> PROPNAME (PROP_OP? PROPVALUE)?
       COMMENT -> \W \w*
       TAGNAME -> \w+
       PROPNAME -> \w+
-      PROPVALUE -> \w+
+      PROPVALUE -> \w+ (',' \w+)*
 */
 
 /// Errors occurred during input tokenizing
@@ -40,6 +41,7 @@ pub enum Token {
     Date,              // YYYY-MM-DD
     DateSeparator,     // -
     DateTimeSeparator, // -
+    Duration,          // +1h30m
     PropertyName,      // Small letters
     PropertyOperator,  // =, <, >
     PropertyValue,     // Small letters
@@ -62,11 +64,15 @@ pub enum Char {
     Any,
     AnyNonSeparator,
     Colon,
+    Comma,
     Dash,
     Digit,
     Dot,
+    DurationPart,
     EqLessMore,
     LowercaseOrDigit,
+    Plus,
+    Slash,
     Quote,
     Space,
     Uppercase,
@@ -76,13 +82,17 @@ impl Display for Char {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
             Char::Any => "any letter",
-            Char::AnyNonSeparator => "anything non space or dot",
+            Char::AnyNonSeparator => "anything non space, dot or comma",
             Char::Colon => "colon",
+            Char::Comma => "comma",
             Char::Dash => "dash",
             Char::Digit => "digit",
             Char::Dot => "dot",
+            Char::DurationPart => "digit or h or m or colon",
             Char::EqLessMore => "equal or less or more",
             Char::LowercaseOrDigit => "lowercase letter or digit",
+            Char::Plus => "plus",
+            Char::Slash => "slash",
             Char::Quote => "quote",
             Char::Space => "space",
             Char::Uppercase => "uppercase letter",
@@ -94,13 +104,17 @@ impl Char {
     fn matches(&self, c: &char) -> bool {
         match self {
             Char::Any => true,
-            Char::AnyNonSeparator => !c.is_whitespace() && *c != '.',
+            Char::AnyNonSeparator => !c.is_whitespace() && *c != '.' && *c != ',',
             Char::Colon => *c == ':',
+            Char::Comma => *c == ',',
             Char::Dash => *c == '-',
             Char::Digit => c.is_ascii_digit(),
             Char::Dot => *c == '.',
+            Char::DurationPart => c.is_ascii_digit() || *c == 'h' || *c == 'm' || *c == ':',
             Char::EqLessMore => *c == '=' || *c == '<' || *c == '>',
-            Char::LowercaseOrDigit => c.is_lowercase() || c.is_ascii_digit(),
+            Char::LowercaseOrDigit => is_name_char(*c),
+            Char::Plus => *c == '+',
+            Char::Slash => *c == '/',
             Char::Quote => *c == '"',
             Char::Space => c.is_ascii_whitespace(),
             Char::Uppercase => c.is_uppercase(),
@@ -108,6 +122,12 @@ impl Char {
     }
 }
 
+/// Checks if char could be a part of tag or property name: lowercase letters of any script, letters of scripts
+/// without a case, e.g. Chinese or Arabic, and digits. Uppercase letters are reserved for the comment start
+pub fn is_name_char(c: char) -> bool {
+    c.is_lowercase() || c.is_numeric() || (c.is_alphabetic() && !c.is_uppercase())
+}
+
 /// Internal result structure. Not all of the values are errors but almost all tokenizer functions
 /// returns Result<(), TokenizingResult> for more convenient early returns via `?` operator
 enum TokenizingResult {
@@ -177,17 +197,31 @@ impl<'a> Tokenizer<'a> {
         // We are here now ↓ After first read datetime either we have another datetime (prefixed with datetime separator) or just time in short notation
         //  2020-01-11 23:23 - 2022-01:12 00:12 activity
         //  2022-01-11 15:00 18:00 activity
-        // If first case we would read [SPACE][DASH][SPACE][DATE][SPACE][TIME], in second [SPACE][TIME]
+        //  2022-01-11 15:00 weight kg=72.4
+        //  2022-01-11 15:00 +1h30m activity
+        //  2022-01-11 15:00 - activity
+        // If first case we would read [SPACE][DASH][SPACE][DATE][SPACE][TIME], in second [SPACE][TIME]. Third one is
+        // a point in time entry without an end, so tags follow right away. Fourth is a start with a duration and
+        // the last one is a running entry where the end is unknown yet
         self.read_one(Token::Space, Char::Space)?;
+        if self.input.peek() == Some(&'+') {
+            return self.tokenize_duration();
+        }
         match self.read(Token::DateSeparator, Char::Dash, 1..1, false) {
             Ok(_) => {
                 self.read_one(Token::Space, Char::Space)?;
+                if self.input.peek().is_some_and(|c| !c.is_ascii_digit()) {
+                    return Ok(()); // Not a date, so it's a running entry followed by tags
+                }
                 self.tokenize_datetime()
             }
             Err(TokenizingResult::EndOfLine(_)) => {
                 Err(TokenizingResult::DateOrTimeExpected(self.tokens.len()))
             }
             Err(TokenizingResult::Expected(..)) => {
+                if !self.input.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    return Ok(()); // Not a time, so it's an instant entry followed by tags
+                }
                 // TODO If [TIME] parsing failed we may improve error logging by suggesting
                 //      [DASH][SPACE][DATE][SPACE][TIME] as an alternative
                 self.tokenize_time()
@@ -212,6 +246,13 @@ impl<'a> Tokenizer<'a> {
         Ok(())
     }
 
+    /// Tokenize duration in format: [PLUS]{1}[DIGIT|h|m|COLON]+, exact format is checked by the parser
+    fn tokenize_duration(&mut self) -> Result<(), TokenizingResult> {
+        self.read_one(Token::Duration, Char::Plus)?;
+        self.read(Token::Duration, Char::DurationPart, 1..usize::MAX, false)?;
+        Ok(())
+    }
+
     /// Tokenize time in format: [DIGIT]{2}[COLON]{1}[DIGIT]{2}
     fn tokenize_time(&mut self) -> Result<(), TokenizingResult> {
         self.read(Token::Time, Char::Digit, 2..2, false)?;
@@ -241,6 +282,7 @@ impl<'a> Tokenizer<'a> {
                 if read > 0 {
                     self.expected_next = vec![Token::TagName];
                 }
+                self.tokenize_namespaces()?;
                 self.tokenize_properties()?;
                 Ok(Some(())) // Successfully read a tag
             }
@@ -253,6 +295,15 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Tag names may be namespaced with a slash, e.g. `music/drums`, each part has to be non empty
+    fn tokenize_namespaces(&mut self) -> Result<(), TokenizingResult> {
+        while self.input.peek() == Some(&'/') {
+            self.read_one(Token::TagName, Char::Slash)?;
+            self.read(Token::TagName, Char::LowercaseOrDigit, 1..usize::MAX, false)?;
+        }
+        Ok(())
+    }
+
     /// Optional comment that starts with the capital letter and follows until the end
     fn tokenize_comments(&mut self) -> Result<(), TokenizingResult> {
         if self
@@ -313,9 +364,34 @@ impl<'a> Tokenizer<'a> {
     }
 
     fn tokenize_property_value(&mut self) -> Result<Option<()>, TokenizingResult> {
+        let value_read = self.tokenize_value_item(true)?;
+        // Lists are values separated by commas without spaces, e.g. with=anna,bob
+        while value_read.is_some() && self.input.peek() == Some(&',') {
+            self.read_one(Token::PropertyValue, Char::Comma)?;
+            self.expected_next = vec![Token::PropertyValue];
+            if self.tokenize_value_item(false)?.is_none() {
+                return Err(TokenizingResult::Expected(
+                    Token::PropertyValue,
+                    Char::AnyNonSeparator,
+                    self.tokens.len(),
+                ));
+            }
+        }
+        Ok(value_read)
+    }
+
+    fn tokenize_value_item(
+        &mut self,
+        space_prefix_allowed: bool,
+    ) -> Result<Option<()>, TokenizingResult> {
         // Property values may be surrounded with quotes
         if self
-            .read(Token::PropertyValue, Char::Quote, 1..1, true)
+            .read(
+                Token::PropertyValue,
+                Char::Quote,
+                1..1,
+                space_prefix_allowed,
+            )
             .is_ok()
         {
             self.expected_next = vec![Token::PropertyValue];
@@ -331,7 +407,12 @@ impl<'a> Tokenizer<'a> {
         // way. It's safe to assume that no tags would start with a digit
         // TODO Kinda ugly, can we make it more pretty?
         if self
-            .read(Token::PropertyValue, Char::Digit, 1..usize::MAX, true)
+            .read(
+                Token::PropertyValue,
+                Char::Digit,
+                1..usize::MAX,
+                space_prefix_allowed,
+            )
             .is_ok()
         {
             self.expected_next = vec![Token::PropertyValue];
@@ -340,7 +421,8 @@ impl<'a> Tokenizer<'a> {
                     .read(Token::PropertyValue, Char::Digit, 1..usize::MAX, false)
                     .is_ok()
                 {
-                    return Ok(Some(()));
+                    // Float is read, but it could be followed by a unit, e.g. 72.4kg
+                    value_read = Some(())
                 } else {
                     // Failed to read digits after the dot, so dot was a tag separator instead, recover
                     if let Some(v) = self.tokens.last_mut() {
@@ -362,7 +444,7 @@ impl<'a> Tokenizer<'a> {
             Token::PropertyValue,
             Char::AnyNonSeparator,
             1..usize::MAX,
-            value_read.is_none(), // If have no property value read yet, then space prefix is allowed. Otherwise space is not valid
+            space_prefix_allowed && value_read.is_none(), // If have no property value read yet, then space prefix is allowed. Otherwise space is not valid
         ) {
             Ok(read) => {
                 if read > 0 {
@@ -457,6 +539,8 @@ mod tests {
         let s = Token::Space;
         let t = Token::Time;
         let ts = Token::TimeSeparator;
+        let tn = Token::TagName;
+        let du = Token::Duration;
 
         let cases = vec![
             (
@@ -524,6 +608,41 @@ mod tests {
                 vec![Token::Time],
                 Some(TokenizingError::Expected(Token::Time, Char::Digit, 18)),
             ),
+            (
+                // Point in time entry without an end
+                "2022-01-02 23:11 w",
+                vec![d, d, d, d, ds, d, d, ds, d, d, s, t, t, ts, t, t, s, tn],
+                vec![Token::TagName],
+                None,
+            ),
+            (
+                // Start with a duration
+                "2022-01-02 23:11 +1h w",
+                vec![
+                    d, d, d, d, ds, d, d, ds, d, d, s, t, t, ts, t, t, s, du, du, du, s, tn,
+                ],
+                vec![Token::TagName],
+                None,
+            ),
+            (
+                "2022-01-02 23:11 +",
+                vec![d, d, d, d, ds, d, d, ds, d, d, s, t, t, ts, t, t, s, du],
+                vec![Token::Duration],
+                Some(TokenizingError::Expected(
+                    Token::Duration,
+                    Char::DurationPart,
+                    18,
+                )),
+            ),
+            (
+                // Running entry without an end yet
+                "2022-01-02 23:11 - w",
+                vec![
+                    d, d, d, d, ds, d, d, ds, d, d, s, t, t, ts, t, t, s, ds, s, tn,
+                ],
+                vec![Token::TagName],
+                None,
+            ),
             (
                 "2022-01-02 23:11 - 2",
                 vec![
@@ -688,6 +807,22 @@ mod tests {
                 vec![Token::PropertyValue],
                 None,
             ),
+            (
+                "t p=a,1.5,\"C c\"",
+                vec![tn, s, pn, po, pv, pv, pv, pv, pv, pv, pv, pv, pv, pv, pv],
+                vec![Token::PropertyValue],
+                None,
+            ),
+            (
+                "t p=a, b",
+                vec![tn, s, pn, po, pv, pv],
+                vec![Token::PropertyValue],
+                Some(TokenizingError::Expected(
+                    Token::PropertyValue,
+                    Char::AnyNonSeparator,
+                    29,
+                )),
+            ),
             (
                 "t p=1. C",
                 vec![tn, s, pn, po, pv, ts, s, c],
@@ -718,6 +853,38 @@ mod tests {
                 vec![Token::PropertyName],
                 None,
             ),
+            (
+                "a/b p",
+                vec![tn, tn, tn, s, pn],
+                vec![Token::PropertyName],
+                None,
+            ),
+            (
+                "a/",
+                vec![tn, tn],
+                vec![Token::TagName],
+                Some(TokenizingError::Expected(
+                    Token::TagName,
+                    Char::LowercaseOrDigit,
+                    25,
+                )),
+            ),
+            (
+                "a/ b",
+                vec![tn, tn],
+                vec![Token::TagName],
+                Some(TokenizingError::Expected(
+                    Token::TagName,
+                    Char::LowercaseOrDigit,
+                    25,
+                )),
+            ),
+            (
+                "бег. 运动 距离=5. Cc",
+                vec![tn, tn, tn, ts, s, tn, tn, s, pn, pn, po, pv, ts, s, c, c],
+                vec![Token::Comment],
+                None,
+            ),
         ];
         for (input, tokens, next, error) in cases {
             let input = format!("{datetime_prefix}{input}");
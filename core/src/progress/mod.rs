@@ -1 +1,2 @@
+pub mod nudge;
 pub mod skill;
@@ -0,0 +1,220 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{data_views::week::WeekProgress, date_time::datetime::DateDay, progress::skill::Skill};
+
+/*
+Nudges - gentle reminders about the skills that are getting neglected. Unlike progress notifications
+which are emitted when a new entry is added, nudges are about absence of entries, so clients ask for
+them periodically, e.g. on app start.
+
+Nudges are calculated from already aggregated views data, so asking for them is cheap:
+- Skill wasn't practiced for N days, based on the day of the last practice tracked by `Skill`
+- Skill is behind its `perfect` weekly pace by mid-week, based on `WeekView` progress
+*/
+
+/// Day of the week starting from which pace checks are done, 0 is Monday
+const MID_WEEK: usize = 2;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum NudgeNotification {
+    /// Skill wasn't practiced for given number of days
+    Inactive { skill: String, days: usize },
+    /// Skill is behind its weekly target, all values are in minutes
+    BehindPace {
+        skill: String,
+        progress: u64,
+        expected: u64,
+        target: u64,
+    },
+}
+
+impl Display for NudgeNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NudgeNotification::Inactive { skill, days } => f.write_fmt(format_args!(
+                "You haven't practiced {} for {} days",
+                skill, days
+            )),
+            NudgeNotification::BehindPace {
+                skill,
+                progress,
+                expected,
+                ..
+            } => f.write_fmt(format_args!(
+                "{} is behind the weekly pace - practiced {} of expected {} hours",
+                skill,
+                progress / 60,
+                expected / 60
+            )),
+        }
+    }
+}
+
+/// Returns nudges for the skills that weren't practiced for `inactive_days` or are behind the weekly pace
+pub fn nudges(
+    skills: &BTreeMap<String, Skill>,
+    week: &BTreeMap<String, WeekProgress>,
+    week_start: Option<DateDay>,
+    now: DateDay,
+    inactive_days: usize,
+) -> Vec<NudgeNotification> {
+    let mut nudges = Vec::new();
+    for skill in skills.values() {
+        // Skills that were never practiced have nothing to compare with
+        let Some(last_practiced) = skill.last_practiced() else {
+            continue;
+        };
+        let days = now.days_since(last_practiced);
+        if days >= inactive_days {
+            nudges.push(NudgeNotification::Inactive {
+                skill: skill.title().to_string(),
+                days,
+            });
+        }
+    }
+
+    let days_passed = now.days_from_monday() as usize;
+    if days_passed < MID_WEEK {
+        return nudges; // Too early in the week to judge the pace
+    }
+    // Week progress may be calculated for a previous week if nothing was added since then
+    let same_week = week_start == Some(now.as_start_of_week());
+    for week_progress in week.values() {
+        let target = week_progress.target();
+        let progress = if same_week {
+            week_progress.progress()
+        } else {
+            0
+        };
+        let expected = target * days_passed as u64 / 7;
+        if progress < expected {
+            nudges.push(NudgeNotification::BehindPace {
+                skill: week_progress.skill().to_string(),
+                progress,
+                expected,
+                target,
+            });
+        }
+    }
+    nudges
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{Notification, Record, DB};
+
+    use super::*;
+
+    fn db(now: DateDay, entries: &[&str]) -> DB {
+        let mut db = DB::new();
+        for entry in entries {
+            db.add(Record::parse(entry).unwrap(), false, Some(now));
+        }
+        db
+    }
+
+    fn nudges(db: &DB, now: DateDay, inactive_days: usize) -> Vec<String> {
+        db.nudges(now, inactive_days)
+            .into_iter()
+            .map(|v| match v {
+                Notification::Nudge(v) => v.to_string(),
+                Notification::Skills(_) | Notification::Schema(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inactive() {
+        let now = DateDay::new(2023, 7, 17); // Monday, too early for pace checks
+        let db = db(
+            now,
+            &[
+                "2023-07-01 00:00 00:00 run. skill kind=physical. Running",
+                "2023-07-01 00:01 00:01 read. skill kind=intelligent. Reading",
+                "2023-07-01 00:02 00:02 draw. skill kind=creative. Drawing",
+                "2023-07-03 10:00 11:00 run",
+                "2023-07-15 10:00 11:00 read",
+            ],
+        );
+        // Drawing was never practiced, Reading was practiced recently
+        assert_eq!(
+            db.nudges(now, 7),
+            vec![Notification::Nudge(NudgeNotification::Inactive {
+                skill: "Running".to_string(),
+                days: 14
+            })]
+        );
+        assert_eq!(
+            nudges(&db, now, 2),
+            vec![
+                "You haven't practiced Reading for 2 days",
+                "You haven't practiced Running for 14 days"
+            ]
+        );
+    }
+
+    #[test]
+    fn inactive_after_delete() {
+        let now = DateDay::new(2023, 7, 17);
+        let mut db = db(
+            now,
+            &[
+                "2023-07-01 00:00 00:00 run. skill kind=physical. Running",
+                "2023-07-03 10:00 11:00 run",
+            ],
+        );
+        let last = Record::parse("2023-07-16 10:00 11:00 run").unwrap();
+        db.add(last.clone(), false, Some(now));
+        assert_eq!(nudges(&db, now, 7), Vec::<String>::new());
+
+        // Deleting the last practice falls back to the previous one
+        db.add(
+            Record::parse(&last.to_deleted_string()).unwrap(),
+            false,
+            Some(now),
+        );
+        assert_eq!(
+            nudges(&db, now, 7),
+            vec!["You haven't practiced Running for 14 days"]
+        );
+    }
+
+    #[test]
+    fn behind_pace() {
+        let now = DateDay::new(2023, 7, 20); // Thursday
+        let db = db(
+            now,
+            &[
+                "2023-07-01 00:00 00:00 run. skill kind=physical perfect=7. Running",
+                "2023-07-01 00:01 00:01 read. skill kind=intelligent perfect=7. Reading",
+                "2023-07-17 10:00 11:00 run",
+                "2023-07-18 10:00 14:00 read",
+            ],
+        );
+        // Three days passed, so 3 out of 7 hours are expected
+        assert_eq!(
+            db.nudges(now, 7),
+            vec![Notification::Nudge(NudgeNotification::BehindPace {
+                skill: "Running".to_string(),
+                progress: 60,
+                expected: 180,
+                target: 420
+            })]
+        );
+
+        // Too early in the week
+        assert_eq!(
+            nudges(&db, DateDay::new(2023, 7, 18), 7),
+            Vec::<String>::new()
+        );
+
+        // Nothing was added in the new week yet
+        assert_eq!(
+            nudges(&db, DateDay::new(2023, 7, 26), 10),
+            vec![
+                "Reading is behind the weekly pace - practiced 0 of expected 2 hours",
+                "Running is behind the weekly pace - practiced 0 of expected 2 hours",
+            ]
+        );
+    }
+}
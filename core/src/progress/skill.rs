@@ -1,7 +1,7 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 use crate::{
-    date_time::datetime::Duration,
+    date_time::datetime::{DateDay, DateTimeRange, Duration},
     db::Selector,
     record::{Entry, PropVal, Tag},
 };
@@ -27,33 +27,92 @@ pub struct Skill {
     title: String,
     duration_minutes: u64,
     perfect_week: u64,
+    last_practiced: Option<DateDay>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Ord, PartialOrd)]
-pub enum SkillKind {
+/// Kind of the skill which is used for grouping and ordering of skills. There are three built-in kinds, but
+/// users may define their own via `skillkind` records, e.g. `skillkind name=social order=3 icon=👥. Social`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SkillKind {
+    name: String,
+    title: String,
+    order: usize,
+    icon: Option<String>,
+}
+
+impl SkillKind {
+    /// Order of user defined kinds when no `order` prop is set, places those after the built-in ones
+    pub const DEFAULT_ORDER: usize = 100;
+
     /// Activities that challenges your brain: academic writing, solving problems, learning languages
-    Intelligent = 0,
+    pub fn intelligent() -> Self {
+        SkillKind::new("intelligent", "Intelligent", 0, None)
+    }
+
     /// Activities that mainly target your body: running, strength training, pilates, dancing
-    Physical = 1,
+    pub fn physical() -> Self {
+        SkillKind::new("physical", "Physical", 1, None)
+    }
+
     /// Activities where you express yourselves: art, music, novel writing, dancing
-    Creative = 2,
-}
+    pub fn creative() -> Self {
+        SkillKind::new("creative", "Creative", 2, None)
+    }
 
-#[derive(Default, Debug)]
-pub struct SkillProgress {
-    pub level: usize,
-    pub minutes_till_next: usize,
-    pub duration_minutes: usize,
-}
+    fn new(name: &str, title: &str, order: usize, icon: Option<String>) -> Self {
+        SkillKind {
+            name: name.to_string(),
+            title: title.to_string(),
+            order,
+            icon,
+        }
+    }
 
-impl SkillProgress {
-    pub fn new(duration_minutes: usize) -> Self {
-        let (level, minutes_till_next) = skill_level(duration_minutes);
-        SkillProgress {
-            level,
-            minutes_till_next,
-            duration_minutes,
+    /// Placeholder for the kind that is referenced by a skill but wasn't defined (yet)
+    fn unknown(name: &str) -> Self {
+        SkillKind::new(name, name, SkillKind::DEFAULT_ORDER, None)
+    }
+
+    /// Creates SkillKind from given record if it is a `skillkind` tag with a `name` prop
+    pub fn from_record(record: &Entry) -> Option<Self> {
+        let tag = record.tags.iter().find(|v| v.name == "skillkind")?;
+        let mut name = None;
+        let mut order = SkillKind::DEFAULT_ORDER;
+        let mut icon = None;
+        for prop in &tag.props {
+            match (prop.name.as_str(), &prop.val) {
+                ("name", PropVal::None) => {}
+                ("name", val) => name = Some(val.to_string()),
+                ("order", PropVal::Number(val)) => order = *val as usize,
+                ("icon", PropVal::None) => {}
+                ("icon", val) => icon = Some(val.to_string()),
+                _ => {}
+            }
         }
+        let name = name?;
+        let title = record.comment.clone().unwrap_or_else(|| name.clone());
+        Some(SkillKind {
+            name,
+            title,
+            order,
+            icon,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
     }
 }
 
@@ -63,20 +122,104 @@ impl FromStr for SkillKind {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // TODO Remove emoji support. Left from PoC phase and should not be used anymore by now
         match s {
-            "physical" | "💪" => Ok(Self::Physical),
-            "intelligent" | "🧠" => Ok(Self::Intelligent),
-            "creative" | "🫀" => Ok(Self::Creative),
-            _ => Err("Unknown skill kind".to_string()),
+            "physical" | "💪" => Ok(Self::physical()),
+            "intelligent" | "🧠" => Ok(Self::intelligent()),
+            "creative" | "🫀" => Ok(Self::creative()),
+            "" => Err("Empty skill kind".to_string()),
+            // Could be a user defined kind, actual definition is resolved later via `SkillKinds`
+            _ => Ok(Self::unknown(s)),
         }
     }
 }
 
-impl ToString for SkillKind {
-    fn to_string(&self) -> String {
-        match self {
-            SkillKind::Physical => "physical".to_string(),
-            SkillKind::Intelligent => "intelligent".to_string(),
-            SkillKind::Creative => "creative".to_string(),
+impl Display for SkillKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl Ord for SkillKind {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.order
+            .cmp(&other.order)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for SkillKind {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Set of all known skill kinds: built-in ones plus defined by the user
+#[derive(Debug, Clone)]
+pub struct SkillKinds {
+    data: BTreeMap<String, SkillKind>,
+}
+
+impl Default for SkillKinds {
+    fn default() -> Self {
+        let data = [
+            SkillKind::intelligent(),
+            SkillKind::physical(),
+            SkillKind::creative(),
+        ]
+        .into_iter()
+        .map(|v| (v.name.clone(), v))
+        .collect();
+        SkillKinds { data }
+    }
+}
+
+impl SkillKinds {
+    /// Adds new kind or replaces existing one with the same name
+    pub fn add(&mut self, kind: SkillKind) {
+        self.data.insert(kind.name.clone(), kind);
+    }
+
+    /// Removes user defined kind. Built-in kinds are restored to their defaults instead
+    pub fn remove(&mut self, name: &str) {
+        self.data.remove(name);
+        if let Some(builtin) = SkillKinds::default().data.remove(name) {
+            self.data.insert(builtin.name.clone(), builtin);
+        }
+    }
+
+    /// Returns kind definition by its name. Unknown kinds are returned as is with default order
+    pub fn get(&self, name: &str) -> SkillKind {
+        self.data
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| SkillKind::unknown(name))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.data.contains_key(name)
+    }
+
+    /// Returns all the known kinds sorted by its order
+    pub fn sorted(&self) -> Vec<&SkillKind> {
+        let mut kinds: Vec<_> = self.data.values().collect();
+        kinds.sort();
+        kinds
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct SkillProgress {
+    pub level: usize,
+    pub minutes_till_next: usize,
+    pub duration_minutes: usize,
+}
+
+impl SkillProgress {
+    pub fn new(duration_minutes: usize) -> Self {
+        let (level, minutes_till_next) = skill_level(duration_minutes);
+        SkillProgress {
+            level,
+            minutes_till_next,
+            duration_minutes,
         }
     }
 }
@@ -120,6 +263,7 @@ impl Skill {
             },
             duration_minutes: 0,
             perfect_week,
+            last_practiced: None,
         })
     }
 
@@ -140,6 +284,10 @@ impl Skill {
         &self.kind
     }
 
+    pub fn set_kind(&mut self, kind: SkillKind) {
+        self.kind = kind;
+    }
+
     pub fn perfect_week(&self) -> u64 {
         self.perfect_week
     }
@@ -152,6 +300,24 @@ impl Skill {
         self.duration_minutes -= duration.minutes();
     }
 
+    /// Adds practiced time and tracks the day of the latest practice
+    pub fn add_practice(&mut self, date_range: &DateTimeRange) {
+        self.add_duration(date_range.duration());
+        let day = date_range.end().date();
+        if self.last_practiced < Some(day) {
+            self.last_practiced = Some(day);
+        }
+    }
+
+    /// Day when the skill was practiced the last time, if ever
+    pub fn last_practiced(&self) -> Option<DateDay> {
+        self.last_practiced
+    }
+
+    pub fn set_last_practiced(&mut self, day: Option<DateDay>) {
+        self.last_practiced = day;
+    }
+
     pub fn merge_selector(&mut self, mut another: Skill) {
         self.selector
             .inclusive_tags
@@ -161,7 +327,7 @@ impl Skill {
 
 impl Ord for Skill {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // TODO Kind comparison depends on symbol code, better idea would be group skills
+        // TODO Kind comparison depends on kind order, better idea would be group skills
         //      by kind, but sort depending on best skill within the group. So that main
         //      group (with biggest skill) will be always on top
         self.kind
@@ -182,9 +348,7 @@ impl Display for Skill {
         let (level, _) = skill_level(self.duration_minutes as usize);
         f.write_fmt(format_args!(
             "{} {:015} {: >4}",
-            self.kind.to_string(),
-            self.title,
-            level
+            self.kind, self.title, level
         ))
     }
 }
@@ -226,6 +390,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn kinds() {
+        let kind = |s| SkillKind::from_record(&Entry::parse(s).unwrap());
+
+        // Full definition
+        let social =
+            kind("2023-07-13 00:00 00:00 skillkind name=social order=3 icon=👥. Social").unwrap();
+        assert_eq!(social.name(), "social");
+        assert_eq!(social.title(), "Social");
+        assert_eq!(social.order(), 3);
+        assert_eq!(social.icon(), Some("👥"));
+
+        // Title defaults to the name and order places it after built-ins
+        let work = kind("2023-07-13 00:00 00:00 skillkind name=work").unwrap();
+        assert_eq!(work.title(), "work");
+        assert_eq!(work.order(), SkillKind::DEFAULT_ORDER);
+
+        // Name is required
+        assert_eq!(kind("2023-07-13 00:00 00:00 skillkind order=3. Foo"), None);
+
+        // Ordering follows the order and then the name
+        let mut kinds = SkillKinds::default();
+        kinds.add(work);
+        kinds.add(social);
+        let names: Vec<_> = kinds.sorted().iter().map(|v| v.name()).collect();
+        assert_eq!(
+            names,
+            vec!["intelligent", "physical", "creative", "social", "work"]
+        );
+
+        // Built-ins may be overridden, but are restored on removal
+        kinds.add(kind("2023-07-13 00:00 00:00 skillkind name=physical order=10").unwrap());
+        assert_eq!(kinds.get("physical").order(), 10);
+        kinds.remove("physical");
+        assert_eq!(kinds.get("physical"), SkillKind::physical());
+        kinds.remove("work");
+        assert!(!kinds.contains("work"));
+        assert_eq!(kinds.get("work").order(), SkillKind::DEFAULT_ORDER);
+    }
+
     #[test]
     fn matching() {
         let matches = |skill, entry| {
@@ -1,8 +1,14 @@
 use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter, Write};
 
-use crate::date_time::datetime::{DateTimeRange, Duration};
+use crate::date_time::datetime::{DateDay, DateTime, DateTimeRange, Duration};
 use crate::parsing::parser::{ParseError, Parser};
+use crate::units::Quantity;
+use crate::version::Version;
+
+/// Tags used for DB internals and settings rather than activities: versioning of entries and definitions of skills,
+/// skill kinds, computed props and schemas
+pub(crate) const RESERVED_TAGS: [&str; 5] = ["entry", "skill", "skillkind", "compute", "schema"];
 
 #[derive(Clone, Eq, PartialEq)]
 pub struct Entry {
@@ -74,11 +80,67 @@ impl Entry {
                 val: PropVal::Number(revision as f32),
                 operator: PropOperator::Eq,
                 start_pos: 0,
+                computed: false,
             }],
             0,
         ))
     }
 
+    /// Version of the entry from its `revision` and `clock` props of the `entry` tag
+    pub fn version(&self) -> Version {
+        let clock = self
+            .tags
+            .iter()
+            .filter(|t| t.name == "entry")
+            .flat_map(|t| t.props.iter())
+            .find(|p| p.name == "clock")
+            .map(|p| Version::parse_clock(&p.val))
+            .unwrap_or_default();
+        Version::new(self.revision(), clock)
+    }
+
+    /// Updates the `entry` tag with the version, clock is removed if the version has none
+    pub fn set_version(&mut self, version: &Version) {
+        self.set_revision(version.revision());
+        let clock = version.clock_value();
+        if let Some(tag) = self.tags.iter_mut().find(|t| t.name == "entry") {
+            tag.props.retain(|p| p.name != "clock");
+            if let Some(clock) = clock {
+                tag.props.push(Prop {
+                    name: "clock".to_string(),
+                    val: clock,
+                    operator: PropOperator::Eq,
+                    start_pos: 0,
+                    computed: false,
+                });
+            }
+            return;
+        }
+        if let Some(clock) = clock {
+            self.tags.push(Tag::new(
+                "entry".to_string(),
+                vec![Prop {
+                    name: "clock".to_string(),
+                    val: clock,
+                    operator: PropOperator::Eq,
+                    start_pos: 0,
+                    computed: false,
+                }],
+                0,
+            ))
+        }
+    }
+
+    /// Finishes running entry, e.g. when timer is stopped. Returned entry has the resolved date range
+    pub fn stop(&self, end: DateTime) -> Result<Entry, String> {
+        let date_range = self.date_range.stop(end)?;
+        Ok(Entry::new(
+            date_range,
+            self.comment.clone(),
+            self.tags.clone(),
+        ))
+    }
+
     pub fn serialize(&self, include_date: bool, include_entry_tag: bool) -> String {
         let tags: Vec<String> = self
             .tags
@@ -89,6 +151,16 @@ impl Entry {
         let mut s = String::new();
         if include_date {
             s.push_str(&self.date_range().to_string());
+        } else if self.date_range.is_instant() {
+            s.push_str(&self.date_range.start().time().to_string());
+        } else if self.date_range.is_running() {
+            s.push_str(&self.date_range.start().time().to_string());
+            s.push_str(" -");
+        } else if self.date_range.start().date() != self.date_range.end().date() {
+            // Entry ends on another day, so the end date has to be present anyway
+            s.push_str(&self.date_range.start().time().to_string());
+            s.push_str(" - ");
+            s.push_str(&self.date_range.end().to_string());
         } else {
             s.push_str(&self.date_range.start().time().to_string());
             s.push(' ');
@@ -116,8 +188,9 @@ impl Ord for Entry {
     fn cmp(&self, other: &Self) -> Ordering {
         self.date_range
             .cmp(&other.date_range)
-            // TODO Hack for now, implement proper ordering for all intermediate objects
-            .then_with(|| self.serialize(true, true).cmp(&other.serialize(true, true)))
+            .then_with(|| self.version().cmp(&other.version()))
+            .then_with(|| self.comment.cmp(&other.comment))
+            .then_with(|| self.tags.cmp(&other.tags))
     }
 }
 
@@ -142,8 +215,10 @@ impl Tag {
             start_pos,
         }
     }
+    /// Checks if tag satisfies the query tag. Namespaced tags match the query of any of its parents, so
+    /// `music/drums` matches `music`, but not `mus`
     pub fn matches(&self, query: &Tag) -> bool {
-        if self.name != query.name {
+        if !self.is_in_namespace(&query.name) {
             return false;
         }
         if query.props.is_empty() {
@@ -160,16 +235,45 @@ impl Tag {
     }
 }
 
+impl Tag {
+    /// Checks if tag is the given one or one of its children, e.g. `music/drums` is in `music` namespace
+    pub fn is_in_namespace(&self, namespace: &str) -> bool {
+        match self.name.strip_prefix(namespace) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    /// Props as written in the entry, without the ones derived from computed prop rules
+    pub fn stored_props(&self) -> impl Iterator<Item = &Prop> {
+        self.props.iter().filter(|v| !v.computed)
+    }
+}
+
 impl PartialEq for Tag {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.props == other.props
+        self.name == other.name && self.stored_props().eq(other.stored_props())
+    }
+}
+
+impl Ord for Tag {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.stored_props().cmp(other.stored_props()))
+    }
+}
+
+impl PartialOrd for Tag {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 impl Display for Tag {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(&self.name)?;
-        for prop in &self.props {
+        for prop in self.stored_props() {
             f.write_char(' ')?;
             f.write_str(&prop.to_string())?;
         }
@@ -189,18 +293,30 @@ pub struct Prop {
     pub val: PropVal,
     pub operator: PropOperator,
     pub start_pos: usize,
+    pub computed: bool, // Derived from computed prop rules, such props are never serialized
 }
 
 impl Prop {
+    /// Checks if property satisfies the query property. Quantities are compared regardless of units, so
+    /// `distance>5km` matches `distance=8000m`. For lists it's enough if any element matches any of the query
+    /// elements, so `with=anna` matches `with=anna,bob`
     pub fn matches(&self, query: &Prop) -> bool {
         if self.name != query.name {
             return false;
         }
-        match query.operator {
-            PropOperator::Eq => self.val == query.val,
-            PropOperator::Less => self.val < query.val,
-            PropOperator::More => self.val > query.val,
+        if self.val == query.val && query.operator == PropOperator::Eq {
+            return true;
         }
+        self.val.items().iter().any(|val| {
+            query.val.items().iter().any(|query_val| {
+                let ordering = val.compare(query_val);
+                match query.operator {
+                    PropOperator::Eq => val == query_val || ordering == Some(Ordering::Equal),
+                    PropOperator::Less => ordering == Some(Ordering::Less),
+                    PropOperator::More => ordering == Some(Ordering::Greater),
+                }
+            })
+        })
     }
 }
 
@@ -210,6 +326,27 @@ impl PartialEq for Prop {
     }
 }
 
+impl Ord for Prop {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.operator.cmp(&other.operator))
+            .then_with(|| self.val.total_cmp(&other.val))
+    }
+}
+
+impl PartialOrd for Prop {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Debug for Prop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string())
+    }
+}
+
 impl Display for Prop {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(&self.name)?;
@@ -238,27 +375,110 @@ impl Display for PropOperator {
     }
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum PropVal {
-    None,           // No value for property
-    Number(f32),    // For simplicity we use f32 for both floats and integers
-    Time(Duration), // Time duration
-    String(String), // Anything else
+    None,               // No value for property
+    Number(f32),        // For simplicity we use f32 for both floats and integers
+    Quantity(Quantity), // Number with a unit, e.g. 10km
+    Time(Duration),     // Time duration
+    Date(DateDay),      // Date in format YYYY-MM-DD
+    Bool(bool),         // true or false
+    String(String),     // Anything else
+    List(Vec<PropVal>), // Values separated by commas, e.g. with=anna,bob
 }
 
 impl PropVal {
+    /// Parses value as written in the entry, values in quotes are taken as is and commas outside of quotes
+    /// separate list items
     pub(crate) fn parse(s: String) -> PropVal {
+        let items = split_list(&s);
+        if items.len() > 1 {
+            return PropVal::List(items.into_iter().map(PropVal::parse_item).collect());
+        }
+        PropVal::parse_item(&s)
+    }
+
+    fn parse_item(s: &str) -> PropVal {
+        // Property value may be surrounded with the quotes, remove those as unnecessary noise
+        let s = s.trim_matches('"').to_string();
         if s.is_empty() {
             return PropVal::None;
         }
         if let Ok(v) = s.parse::<f32>() {
             return PropVal::Number(v);
         }
-        if let Ok(time) = s.parse::<Duration>() {
-            return PropVal::Time(time);
+        match s.as_str() {
+            "true" => return PropVal::Bool(true),
+            "false" => return PropVal::Bool(false),
+            _ => {}
+        }
+        if let Ok(v) = s.parse::<Quantity>() {
+            return PropVal::Quantity(v);
+        }
+        if let Ok(v) = s.parse::<DateDay>() {
+            return PropVal::Date(v);
+        }
+        // Compact durations like `5m` are ambiguous with units, so only HH:MM is treated as time
+        if s.contains(':') {
+            if let Ok(time) = s.parse::<Duration>() {
+                return PropVal::Time(time);
+            }
         }
         PropVal::String(s)
     }
+
+    /// Order of all the values, unlike `partial_cmp` it's defined for any values and used only for determinism.
+    /// Values of different kinds are ordered by kind
+    pub(crate) fn total_cmp(&self, other: &PropVal) -> Ordering {
+        match (self, other) {
+            (PropVal::Number(n1), PropVal::Number(n2)) => n1.total_cmp(n2),
+            (PropVal::Quantity(q1), PropVal::Quantity(q2)) => q1
+                .value
+                .total_cmp(&q2.value)
+                .then_with(|| q1.unit.cmp(&q2.unit)),
+            (PropVal::Time(t1), PropVal::Time(t2)) => t1.cmp(t2),
+            (PropVal::Date(d1), PropVal::Date(d2)) => d1.cmp(d2),
+            (PropVal::Bool(b1), PropVal::Bool(b2)) => b1.cmp(b2),
+            (PropVal::String(s1), PropVal::String(s2)) => s1.cmp(s2),
+            (PropVal::List(l1), PropVal::List(l2)) => l1
+                .iter()
+                .zip(l2)
+                .map(|(v1, v2)| v1.total_cmp(v2))
+                .find(|v| v.is_ne())
+                .unwrap_or_else(|| l1.len().cmp(&l2.len())),
+            (v1, v2) => v1.kind_order().cmp(&v2.kind_order()),
+        }
+    }
+
+    fn kind_order(&self) -> u8 {
+        match self {
+            PropVal::None => 0,
+            PropVal::Number(_) => 1,
+            PropVal::Quantity(_) => 2,
+            PropVal::Time(_) => 3,
+            PropVal::Date(_) => 4,
+            PropVal::Bool(_) => 5,
+            PropVal::String(_) => 6,
+            PropVal::List(_) => 7,
+        }
+    }
+
+    /// Elements of the list, or the value itself if it's not a list
+    pub fn items(&self) -> &[PropVal] {
+        match self {
+            PropVal::List(items) => items,
+            val => std::slice::from_ref(val),
+        }
+    }
+
+    /// Compares values of the same type, quantities are compared regardless of units. None if values aren't
+    /// comparable, e.g. numbers and strings or lengths and weights
+    pub fn compare(&self, other: &PropVal) -> Option<Ordering> {
+        match (self, other) {
+            (PropVal::Quantity(q1), PropVal::Quantity(q2)) => q1.compare(q2),
+            _ => self.partial_cmp(other),
+        }
+    }
 }
 
 impl Display for PropVal {
@@ -266,15 +486,22 @@ impl Display for PropVal {
         match self {
             PropVal::None => std::fmt::Result::Ok(()),
             PropVal::Number(n) => f.write_fmt(format_args!("{}", n)),
+            PropVal::Quantity(q) => f.write_str(&q.to_string()),
             PropVal::Time(time) => f.write_str(&time.to_string()),
+            PropVal::Date(date) => f.write_str(&date.to_string()),
+            PropVal::Bool(b) => f.write_str(&b.to_string()),
             PropVal::String(s) => {
                 // If prop value contains any separator, then quotes are needed
-                if s.chars().any(|c| c.is_whitespace() || c == '.') {
+                if s.chars().any(|c| c.is_whitespace() || c == '.' || c == ',') {
                     f.write_fmt(format_args!("\"{}\"", s))
                 } else {
                     f.write_str(s)
                 }
             }
+            PropVal::List(items) => {
+                let items: Vec<_> = items.iter().map(|v| v.to_string()).collect();
+                f.write_str(&items.join(","))
+            }
         }
     }
 }
@@ -286,6 +513,14 @@ impl PartialOrd for PropVal {
             (PropVal::String(s1), PropVal::String(s2)) => s1.partial_cmp(s2),
             (PropVal::Number(n1), PropVal::Number(n2)) => n1.partial_cmp(n2),
             (PropVal::Time(time1), PropVal::Time(time2)) => time1.partial_cmp(time2),
+            (PropVal::Date(d1), PropVal::Date(d2)) => d1.partial_cmp(d2),
+            (PropVal::Bool(b1), PropVal::Bool(b2)) => b1.partial_cmp(b2),
+            // Lists have no order, those are only equal or not
+            (PropVal::List(l1), PropVal::List(l2)) => (l1 == l2).then_some(Ordering::Equal),
+            // Same quantities written in different units are ordered by unit to stay consistent with equality
+            (PropVal::Quantity(q1), PropVal::Quantity(q2)) => q1
+                .compare(q2)
+                .map(|v| v.then_with(|| q1.unit.cmp(&q2.unit))),
             (_, _) => None,
         }
     }
@@ -293,10 +528,94 @@ impl PartialOrd for PropVal {
 
 impl Eq for PropVal {}
 
+/// Splits value by commas outside of quotes
+fn split_list(s: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (idx, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                items.push(&s[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn version() {
+        let mut entry =
+            Entry::parse("2023-07-03 10:00 11:00 run. entry revision=3 clock=laptop@2,phone@1")
+                .unwrap();
+        assert_eq!(
+            entry.version().to_string(),
+            "revision=3 clock=laptop@2,phone@1"
+        );
+        entry.set_version(&entry.version().next("phone"));
+        assert_eq!(
+            entry.serialize(true, true),
+            "2023-07-03 10:00 11:00 run. entry revision=4 clock=laptop@2,phone@2"
+        );
+
+        // Entries without clocks
+        let mut entry = Entry::parse("2023-07-03 10:00 11:00 run").unwrap();
+        assert_eq!(entry.version(), Version::default());
+        entry.set_version(&Version::default().next("laptop"));
+        assert_eq!(
+            entry.serialize(true, true),
+            "2023-07-03 10:00 11:00 run. entry revision=2 clock=laptop@1"
+        );
+        entry.set_version(&Version::new(5, Default::default()));
+        assert_eq!(
+            entry.serialize(true, true),
+            "2023-07-03 10:00 11:00 run. entry revision=5"
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        // Entries are ordered by date range, version and then by content, regardless of how those are written
+        let entry = |s: &str| Entry::parse(s).unwrap();
+        let mut entries = [
+            entry("2023-07-03 10:00 11:00 run. entry revision=2"),
+            entry("2023-07-03 10:00 11:00 run distance=10"),
+            entry("2023-07-03 10:00 11:00 run distance=9.5"),
+            entry("2023-07-03 09:00 11:00 run"),
+        ];
+        entries.sort();
+        let got: Vec<_> = entries.iter().map(|v| v.serialize(false, true)).collect();
+        assert_eq!(
+            got,
+            vec![
+                "09:00 11:00 run",
+                "10:00 11:00 run distance=9.5",
+                "10:00 11:00 run distance=10",
+                "10:00 11:00 run. entry revision=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn namespaces() {
+        let tag = |s: &str| Entry::parse(&format!("2023-07-03 10:00 {s}")).unwrap().tags[0].clone();
+        assert!(tag("music/drums").matches(&tag("music")));
+        assert!(tag("music/drums").matches(&tag("music/drums")));
+        assert!(tag("music/drums/jazz bpm=120").matches(&tag("music/drums bpm>100")));
+        assert!(!tag("music").matches(&tag("music/drums")));
+        assert!(!tag("musical").matches(&tag("music")));
+        assert!(!tag("music/drums").matches(&tag("mus")));
+        assert!(tag("музыка/барабаны").matches(&tag("музыка")));
+    }
+
     #[test]
     fn revision() {
         // Default revision is 1
@@ -364,5 +683,75 @@ mod tests {
 
         // Omit date and entry
         assert_eq!(entry.serialize(false, false), "10:00 11:00 run distance=18");
+
+        // Point in time entry has no end
+        let entry = Entry::parse("2023-07-03 08:00 weight kg=72.4").unwrap();
+        assert_eq!(
+            entry.serialize(true, true),
+            "2023-07-03 08:00 weight kg=72.4"
+        );
+        assert_eq!(entry.serialize(false, true), "08:00 weight kg=72.4");
+
+        // Entry that ends on another day keeps the end date
+        let entry = Entry::parse("2023-07-03 23:00 - 2023-07-04 07:00 sleep").unwrap();
+        assert_eq!(
+            entry.serialize(false, true),
+            "23:00 - 2023-07-04 07:00 sleep"
+        );
+    }
+
+    #[test]
+    fn typed_values() {
+        let cases = vec![
+            ("18", PropVal::Number(18.0)),
+            ("10km", PropVal::Quantity("10km".parse().unwrap())),
+            ("01:30", PropVal::Time(Duration::new(1, 30))),
+            ("2023-07-03", PropVal::Date("2023-07-03".parse().unwrap())),
+            ("true", PropVal::Bool(true)),
+            ("10kmh", PropVal::String("10kmh".to_string())),
+            ("2023-13-01", PropVal::String("2023-13-01".to_string())),
+            (
+                "anna,\"Bob Smith\",5km",
+                PropVal::List(vec![
+                    PropVal::String("anna".to_string()),
+                    PropVal::String("Bob Smith".to_string()),
+                    PropVal::Quantity("5km".parse().unwrap()),
+                ]),
+            ),
+            ("\"a, b\"", PropVal::String("a, b".to_string())),
+        ];
+        for (input, want) in cases {
+            let got = PropVal::parse(input.to_string());
+            assert_eq!(got, want, "input {input}");
+            assert_eq!(got.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let prop = |s: &str| {
+            let entry = Entry::parse(&format!("2023-07-03 10:00 run {s}")).unwrap();
+            entry.tags[0].props[0].clone()
+        };
+        let cases = vec![
+            ("distance=8000m", "distance>5km", true),
+            ("distance=8000m", "distance<5km", false),
+            ("distance=8km", "distance=8000m", true),
+            ("distance=8km", "distance>5kg", false),
+            ("distance=8", "distance>5km", false),
+            ("race=2023-07-03", "race>2023-07-01", true),
+            ("race=2023-07-03", "race<2023-07-01", false),
+            ("outdoor=true", "outdoor=true", true),
+            ("outdoor=true", "outdoor=false", false),
+            ("with=anna,bob", "with=bob", true),
+            ("with=anna,bob", "with=anna,bob", true),
+            ("with=anna,bob", "with=carl,anna", true),
+            ("with=anna,bob", "with=carl", false),
+            ("laps=400m,1km", "laps>800m", true),
+            ("laps=400m,1km", "laps>2km", false),
+        ];
+        for (value, query, want) in cases {
+            assert_eq!(prop(value).matches(&prop(query)), want, "{value} {query}");
+        }
     }
 }
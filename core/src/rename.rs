@@ -0,0 +1,203 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    db::Record,
+    parsing::tokenizer::is_name_char,
+    record::{Entry, Tag, RESERVED_TAGS},
+};
+
+/// Rule for renaming a tag or a tag property in all the entries. Format is `jog=run` for tags and
+/// `run.dist=distance` for properties
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RenameRule {
+    /// If entry has both tags already those are merged into one, with properties of the target tag taking priority
+    Tag { from: String, to: String },
+    /// If the tag has both properties already, the target one is kept
+    Prop {
+        tag: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl FromStr for RenameRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Rename rule has to be in format from=to, got {s}"))?;
+        check_name(to)?;
+        let rule = match from.split_once('.') {
+            Some((tag, from)) => {
+                check_name(tag)?;
+                check_name(from)?;
+                RenameRule::Prop {
+                    tag: tag.to_string(),
+                    from: from.to_string(),
+                    to: to.to_string(),
+                }
+            }
+            None => {
+                check_name(from)?;
+                for name in [from, to] {
+                    // Renaming reserved tags would break the DB
+                    if RESERVED_TAGS.contains(&name) {
+                        return Err(format!("Special tag {name} cannot be renamed"));
+                    }
+                }
+                RenameRule::Tag {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                }
+            }
+        };
+        Ok(rule)
+    }
+}
+
+impl Display for RenameRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameRule::Tag { from, to } => f.write_fmt(format_args!("{from}={to}")),
+            RenameRule::Prop { tag, from, to } => f.write_fmt(format_args!("{tag}.{from}={to}")),
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(is_name_char)
+        && name
+            .chars()
+            .all(|c| !c.is_whitespace() && !matches!(c, '.' | '=' | '<' | '>' | '"'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Bad name for renaming: {name}"))
+    }
+}
+
+/// Record before and after renaming. New record has the next revision, so it replaces the old one when added
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Renamed {
+    pub from: Record,
+    pub to: Record,
+}
+
+/// Applies the rules to the entry and returns the new revision of it if anything got renamed
+pub fn rename_entry(entry: &Entry, rules: &[RenameRule]) -> Option<Entry> {
+    let mut tags = entry.tags.clone();
+    for rule in rules {
+        match rule {
+            RenameRule::Tag { from, to } => {
+                for tag in tags.iter_mut().filter(|v| &v.name == from) {
+                    tag.name = to.clone();
+                }
+                tags = merge_tags(tags);
+            }
+            RenameRule::Prop { tag, from, to } => {
+                for tag in tags.iter_mut().filter(|v| &v.name == tag) {
+                    if tag.props.iter().any(|v| &v.name == to) {
+                        tag.props.retain(|v| &v.name != from);
+                    }
+                    for prop in tag.props.iter_mut().filter(|v| &v.name == from) {
+                        prop.name = to.clone();
+                    }
+                }
+            }
+        }
+    }
+    if tags == entry.tags {
+        return None;
+    }
+    let mut renamed = Entry::new(entry.date_range, entry.comment.clone(), tags);
+    renamed.set_revision(entry.revision() + 1);
+    Some(renamed)
+}
+
+/// Merges tags with the same name into the first one, properties which already exist aren't duplicated
+fn merge_tags(tags: Vec<Tag>) -> Vec<Tag> {
+    let mut merged: Vec<Tag> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        match merged.iter_mut().find(|v| v.name == tag.name) {
+            Some(existing) => {
+                for prop in tag.props {
+                    if existing.props.iter().all(|v| v.name != prop.name) {
+                        existing.props.push(prop);
+                    }
+                }
+            }
+            None => merged.push(tag),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn rename(entry: &str, rules: &[&str]) -> Option<String> {
+        let rules: Vec<RenameRule> = rules.iter().map(|v| v.parse().unwrap()).collect();
+        rename_entry(&Entry::parse(entry).unwrap(), &rules).map(|v| v.serialize(true, true))
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn renaming() {
+        let cases = vec![
+            (
+                "2023-07-03 10:00 11:00 jog dist=5. Morning",
+                vec!["jog=run"],
+                Some("2023-07-03 10:00 11:00 run dist=5. entry revision=2. Morning"),
+            ),
+            (
+                "2023-07-03 10:00 11:00 jog dist=5. entry revision=3",
+                vec!["jog=run", "run.dist=distance"],
+                Some("2023-07-03 10:00 11:00 run distance=5. entry revision=4"),
+            ),
+            (
+                "2023-07-03 10:00 11:00 run place=park. jog dist=5 place=street",
+                vec!["jog=run"],
+                Some("2023-07-03 10:00 11:00 run place=park dist=5. entry revision=2"),
+            ),
+            (
+                "2023-07-03 10:00 11:00 run dist=5 distance=6",
+                vec!["run.dist=distance"],
+                Some("2023-07-03 10:00 11:00 run distance=6. entry revision=2"),
+            ),
+            (
+                "2023-07-03 10:00 11:00 read dist=5",
+                vec!["jog=run", "run.dist=distance"],
+                None,
+            ),
+        ];
+        for (entry, rules, want) in cases {
+            assert_eq!(rename(entry, &rules).as_deref(), want, "entry |{entry}|");
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn rules() {
+        assert_eq!(
+            "run.dist=distance"
+                .parse::<RenameRule>()
+                .unwrap()
+                .to_string(),
+            "run.dist=distance"
+        );
+        for rule in [
+            "jog",
+            "jog=",
+            "Jog=run",
+            "jog=my run",
+            "entry=foo",
+            "jog=skill",
+        ] {
+            assert!(rule.parse::<RenameRule>().is_err(), "rule {rule}");
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+use crate::{
+    db::{Record, Timestamps},
+    record::{Entry, Tag},
+};
+
+/*
+Policies for automatic resolution of conflicts, i.e. concurrent edits of the same entry. Resolution is a pure function
+of conflicting entries and timestamps of their payloads, so every device with the same policy converges to the same
+state. Resolved entry gets the version which knows about all the conflicting entries, so any further edit of it or
+a deletion supersedes the whole conflict. If policy cannot resolve the conflict it's left for the user.
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ResolutionPolicy {
+    /// Conflicts are always resolved by the user
+    #[default]
+    Manual,
+    /// Entry from the latest payload wins, conflict is kept until timestamps of all the entries are known
+    LastWriterWins,
+    /// Deletion wins over concurrent edits
+    PreferDeletion,
+    /// Tags, props and comment are combined if edits don't touch the same field differently.
+    /// There is no common ancestor, so removal of a tag or a prop on one side is undone by the other side
+    FieldMerge,
+}
+
+impl ResolutionPolicy {
+    /// Returns single entry for the conflicting entries or None if conflict has to be resolved by the user
+    pub fn resolve(&self, conflict: &BTreeSet<Entry>, timestamps: &Timestamps) -> Option<Entry> {
+        let mut resolved = match self {
+            ResolutionPolicy::Manual => None,
+            ResolutionPolicy::LastWriterWins => last_writer(conflict, timestamps).cloned(),
+            ResolutionPolicy::PreferDeletion => conflict.iter().rfind(|v| is_deleted(v)).cloned(),
+            ResolutionPolicy::FieldMerge => merge_fields(conflict),
+        }?;
+        let version = Record::Conflict(conflict.clone()).version();
+        resolved.set_version(&version);
+        Some(resolved)
+    }
+}
+
+fn is_deleted(entry: &Entry) -> bool {
+    Record::Entry(entry.clone()).is_deleted_record()
+}
+
+fn last_writer<'a>(conflict: &'a BTreeSet<Entry>, timestamps: &Timestamps) -> Option<&'a Entry> {
+    let mut latest = None;
+    for entry in conflict {
+        let timestamp = timestamps.get(entry)?;
+        // Entries are sorted, so ties are resolved deterministically as well
+        if latest.is_none_or(|(v, _)| timestamp >= v) {
+            latest = Some((timestamp, entry));
+        }
+    }
+    latest.map(|(_, entry)| entry)
+}
+
+fn merge_fields(conflict: &BTreeSet<Entry>) -> Option<Entry> {
+    if conflict.iter().any(is_deleted) {
+        // Deletion is not a field change, but all concurrent deletions are the same
+        return match conflict.iter().all(is_deleted) {
+            true => conflict.last().cloned(),
+            false => None,
+        };
+    }
+    let mut comment: Option<&String> = None;
+    for v in conflict.iter().filter_map(|v| v.comment.as_ref()) {
+        if comment.is_some_and(|comment| comment != v) {
+            return None;
+        }
+        comment = Some(v);
+    }
+    let mut tags: Vec<Tag> = Vec::new();
+    for entry in conflict {
+        let names: BTreeSet<_> = entry.tags.iter().map(|v| &v.name).collect();
+        if names.len() != entry.tags.len() {
+            return None; // Repeated tags cannot be matched between entries
+        }
+        for tag in &entry.tags {
+            let Some(merged) = tags.iter_mut().find(|v| v.name == tag.name) else {
+                tags.push(tag.clone());
+                continue;
+            };
+            if tag.name == "entry" {
+                continue; // Version is set after the merge
+            }
+            for prop in tag.stored_props() {
+                match merged.props.iter().find(|v| v.name == prop.name) {
+                    Some(existing) if existing != prop => return None,
+                    Some(_) => {}
+                    None => merged.props.push(prop.clone()),
+                }
+            }
+        }
+    }
+    let first = conflict.first()?;
+    Some(Entry::new(first.date_range, comment.cloned(), tags))
+}
+
+impl FromStr for ResolutionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(ResolutionPolicy::Manual),
+            "last-writer-wins" => Ok(ResolutionPolicy::LastWriterWins),
+            "prefer-deletion" => Ok(ResolutionPolicy::PreferDeletion),
+            "field-merge" => Ok(ResolutionPolicy::FieldMerge),
+            _ => Err(format!(
+                "Unknown resolution policy {s}, expected manual, last-writer-wins, prefer-deletion or field-merge"
+            )),
+        }
+    }
+}
+
+impl Display for ResolutionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ResolutionPolicy::Manual => "manual",
+            ResolutionPolicy::LastWriterWins => "last-writer-wins",
+            ResolutionPolicy::PreferDeletion => "prefer-deletion",
+            ResolutionPolicy::FieldMerge => "field-merge",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use crate::date_time::timestamp::Timestamp;
+
+    use super::*;
+
+    fn conflict(entries: &[&str]) -> BTreeSet<Entry> {
+        entries
+            .iter()
+            .map(|v| Entry::parse(&format!("2023-07-03 10:00 11:00 {v}")).unwrap())
+            .collect()
+    }
+
+    fn resolve(policy: ResolutionPolicy, entries: &[&str]) -> Option<String> {
+        let resolved = policy.resolve(&conflict(entries), &Timestamps::default())?;
+        Some(resolved.serialize(false, true))
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn field_merge() {
+        let policy = ResolutionPolicy::FieldMerge;
+        let cases = [
+            (
+                vec![
+                    "run dist=5. entry revision=2 clock=a@1",
+                    "run dist=5. entry revision=2 clock=b@1. Morning",
+                ],
+                Some("10:00 11:00 run dist=5. entry revision=2 clock=a@1,b@1. Morning"),
+            ),
+            (
+                vec![
+                    "run dist=5 place=park. entry revision=2 clock=a@1",
+                    "run dist=5. lunch. entry revision=2 clock=b@1",
+                ],
+                Some("10:00 11:00 run dist=5 place=park. entry revision=2 clock=a@1,b@1. lunch"),
+            ),
+            (
+                vec![
+                    "run dist=5. entry revision=2 clock=a@1",
+                    "run dist=6. entry revision=2 clock=b@1",
+                ],
+                None,
+            ),
+            (
+                vec![
+                    "run. entry revision=2 clock=a@1. Morning",
+                    "run. entry revision=2 clock=b@1. Evening",
+                ],
+                None,
+            ),
+            (
+                vec![
+                    "run. entry revision=2 clock=a@1",
+                    "entry revision=2 clock=b@1 deleted",
+                ],
+                None,
+            ),
+        ];
+        for (entries, want) in cases {
+            assert_eq!(resolve(policy, &entries).as_deref(), want, "{entries:?}");
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn prefer_deletion() {
+        let policy = ResolutionPolicy::PreferDeletion;
+        assert_eq!(
+            resolve(
+                policy,
+                &[
+                    "run. entry revision=3 clock=a@2",
+                    "entry revision=2 clock=b@1 deleted",
+                ]
+            )
+            .as_deref(),
+            Some("10:00 11:00 entry revision=3 deleted clock=a@2,b@1")
+        );
+        assert_eq!(resolve(policy, &["run", "walk"]), None);
+        assert_eq!(resolve(ResolutionPolicy::Manual, &["run", "walk"]), None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn last_writer_wins() {
+        let policy = ResolutionPolicy::LastWriterWins;
+        let entries = conflict(&["run. Morning", "run. Evening"]);
+        let mut timestamps = Timestamps::default();
+        assert_eq!(policy.resolve(&entries, &timestamps), None);
+        for (i, entry) in entries.iter().enumerate() {
+            timestamps.insert(entry, Timestamp::from_u64(10 - i as u64));
+        }
+        let resolved = policy.resolve(&entries, &timestamps).unwrap();
+        assert_eq!(resolved.serialize(false, true), "10:00 11:00 run. Evening");
+        // Earliest payload of the entry is kept
+        timestamps.insert(entries.first().unwrap(), Timestamp::from_u64(5));
+        let resolved = policy.resolve(&entries, &timestamps).unwrap();
+        assert_eq!(resolved.serialize(false, true), "10:00 11:00 run. Morning");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn parse() {
+        for policy in [
+            ResolutionPolicy::Manual,
+            ResolutionPolicy::LastWriterWins,
+            ResolutionPolicy::PreferDeletion,
+            ResolutionPolicy::FieldMerge,
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+        assert!("newest".parse::<ResolutionPolicy>().is_err());
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use thiserror::Error;
+
+use crate::{
+    date_time::datetime::DateTimeRange,
+    db::{ChangeEvent, Record},
+    progress::skill::SkillKinds,
+    record::{Entry, PropVal, Tag, RESERVED_TAGS},
+    units::{Dimension, Unit},
+};
+
+/*
+Tag schemas - declarations of props that entries with the tag are expected to have, so data stays consistent.
+
+Schemas are defined with `schema` records, every `schema` prop declares the type of the prop with the same name
+for the tags of the record, e.g. `run. schema distance=length kind=road,trail optional=kind. Running`. Props are
+required unless listed in `optional`, props not mentioned in the schema are allowed. Types:
+- `any` or no value at all, only checks that prop is present
+- `number`, `time`, `date`, `bool` and `text` for the values of the same kind
+- `quantity` for a number with any unit, `length`, `mass`, `volume`, `energy` or a unit symbol like `km` for
+  a number with a unit of specific dimension
+- List of values, e.g. `road,trail`, the value has to be one of those
+
+Built-in `skill` and `skillkind` tags have schemas as well, and kind of the skill has to be a known one.
+Schemas never reject entries, violations are reported as warnings by the linter and `DB::add` in interactive mode.
+*/
+
+/// Violation of the tag schema found in the entry
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum SchemaWarning {
+    #[error("Missing required prop '{prop}' of '{tag}'")]
+    MissingProp { tag: String, prop: String },
+    #[error("Prop '{prop}' of '{tag}' should be {expected}, got '{value}'")]
+    InvalidValue {
+        tag: String,
+        prop: String,
+        expected: String,
+        value: String,
+    },
+    #[error("Unknown type '{kind}' of prop '{prop}' in the schema")]
+    UnknownType { prop: String, kind: String },
+    #[error("Unknown skill kind '{0}', define it with 'skillkind name={0}'")]
+    UnknownSkillKind(String),
+}
+
+/// Type of the prop value declared in the schema
+#[derive(Debug, PartialEq, Clone)]
+pub enum PropType {
+    Any,
+    Number,
+    Quantity(Option<Dimension>),
+    Time,
+    Date,
+    Bool,
+    Text,
+    OneOf(Vec<String>),
+}
+
+impl PropType {
+    /// Parses type from the value of the schema prop
+    pub fn parse(val: &PropVal) -> Option<PropType> {
+        let s = match val {
+            PropVal::None => return Some(PropType::Any),
+            PropVal::List(items) => {
+                return Some(PropType::OneOf(
+                    items.iter().map(|v| v.to_string()).collect(),
+                ))
+            }
+            PropVal::String(s) => s.as_str(),
+            _ => return None,
+        };
+        let kind = match s {
+            "any" => PropType::Any,
+            "number" => PropType::Number,
+            "quantity" => PropType::Quantity(None),
+            "time" => PropType::Time,
+            "date" => PropType::Date,
+            "bool" => PropType::Bool,
+            "text" => PropType::Text,
+            s => {
+                let dimension = s
+                    .parse::<Dimension>()
+                    .or_else(|_| s.parse::<Unit>().map(|v| v.dimension()))
+                    .ok()?;
+                PropType::Quantity(Some(dimension))
+            }
+        };
+        Some(kind)
+    }
+
+    /// Checks if value is of this type. For lists every element has to match
+    pub fn matches(&self, val: &PropVal) -> bool {
+        if let PropVal::List(items) = val {
+            return items.iter().all(|v| self.matches(v));
+        }
+        match (self, val) {
+            (PropType::Any, _) => true,
+            (PropType::Number, PropVal::Number(_)) => true,
+            (PropType::Quantity(None), PropVal::Quantity(_)) => true,
+            (PropType::Quantity(Some(dimension)), PropVal::Quantity(quantity)) => {
+                quantity.unit.dimension() == *dimension
+            }
+            (PropType::Time, PropVal::Time(_)) => true,
+            (PropType::Date, PropVal::Date(_)) => true,
+            // Prop without a value is a flag, e.g. `done`
+            (PropType::Bool, PropVal::Bool(_) | PropVal::None) => true,
+            (PropType::Text, val) => val != &PropVal::None,
+            (PropType::OneOf(values), val) => values.contains(&val.to_string()),
+            _ => false,
+        }
+    }
+}
+
+impl Display for PropType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropType::Any => f.write_str("any value"),
+            PropType::Number => f.write_str("a number"),
+            PropType::Quantity(None) => f.write_str("a number with a unit"),
+            PropType::Quantity(Some(dimension)) => f.write_fmt(format_args!(
+                "a {dimension} like 10{}",
+                Unit::base(*dimension)
+            )),
+            PropType::Time => f.write_str("a time like 01:30"),
+            PropType::Date => f.write_str("a date like 2023-07-03"),
+            PropType::Bool => f.write_str("true or false"),
+            PropType::Text => f.write_str("a text"),
+            PropType::OneOf(values) => f.write_fmt(format_args!("one of {}", values.join(","))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PropSchema {
+    pub kind: PropType,
+    pub required: bool,
+}
+
+/// Props declared for the tag
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagSchema {
+    tag: String,
+    props: BTreeMap<String, PropSchema>,
+}
+
+impl TagSchema {
+    /// Creates schemas from given record if it's a `schema` record, one per each tag of the record. Props of
+    /// unknown types are skipped
+    pub fn from_record(record: &Entry) -> Vec<TagSchema> {
+        let Some(schema) = record.tags.iter().find(|v| v.name == "schema") else {
+            return vec![]; // Most of the records are not schemas, early return in this case
+        };
+        let optional: Vec<_> = schema
+            .props
+            .iter()
+            .filter(|v| v.name == "optional")
+            .flat_map(|v| v.val.items().iter().map(|v| v.to_string()))
+            .collect();
+        let props: BTreeMap<_, _> = schema
+            .props
+            .iter()
+            .filter(|v| v.name != "optional")
+            .filter_map(|prop| {
+                let schema = PropSchema {
+                    kind: PropType::parse(&prop.val)?,
+                    required: !optional.contains(&prop.name),
+                };
+                Some((prop.name.clone(), schema))
+            })
+            .collect();
+        record
+            .tags
+            .iter()
+            .filter(|v| v.name != "schema" && v.name != "entry")
+            .map(|v| TagSchema {
+                tag: v.name.clone(),
+                props: props.clone(),
+            })
+            .collect()
+    }
+
+    fn new(tag: &str, props: &[(&str, PropType, bool)]) -> Self {
+        TagSchema {
+            tag: tag.to_string(),
+            props: props
+                .iter()
+                .map(|(name, kind, required)| {
+                    let schema = PropSchema {
+                        kind: kind.clone(),
+                        required: *required,
+                    };
+                    (name.to_string(), schema)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn props(&self) -> &BTreeMap<String, PropSchema> {
+        &self.props
+    }
+
+    fn validate(&self, tag: &Tag, warnings: &mut Vec<SchemaWarning>) {
+        for (name, schema) in &self.props {
+            let Some(prop) = tag.stored_props().find(|v| &v.name == name) else {
+                if schema.required {
+                    warnings.push(SchemaWarning::MissingProp {
+                        tag: tag.name.clone(),
+                        prop: name.clone(),
+                    });
+                }
+                continue;
+            };
+            if !schema.kind.matches(&prop.val) {
+                warnings.push(SchemaWarning::InvalidValue {
+                    tag: tag.name.clone(),
+                    prop: name.clone(),
+                    expected: schema.kind.to_string(),
+                    value: prop.val.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Built-in and user defined schemas. User defined ones are keyed by the date range of the record where those
+/// were defined, later definitions of the same tag take precedence
+#[derive(Clone)]
+pub struct Schemas {
+    builtin: BTreeMap<String, TagSchema>,
+    defined: BTreeMap<DateTimeRange, Vec<TagSchema>>,
+}
+
+impl Default for Schemas {
+    fn default() -> Self {
+        let builtin = [
+            TagSchema::new(
+                "skill",
+                &[
+                    ("kind", PropType::Text, true),
+                    ("perfect", PropType::Number, false),
+                ],
+            ),
+            TagSchema::new(
+                "skillkind",
+                &[
+                    ("name", PropType::Text, true),
+                    ("order", PropType::Number, false),
+                    ("icon", PropType::Text, false),
+                ],
+            ),
+        ]
+        .into_iter()
+        .map(|v| (v.tag.clone(), v))
+        .collect();
+        Schemas {
+            builtin,
+            defined: BTreeMap::new(),
+        }
+    }
+}
+
+impl Schemas {
+    /// Updates schemas from the change event. Schemas in conflicts are ignored until those are resolved
+    pub fn update(&mut self, event: &ChangeEvent) {
+        let record = match event {
+            ChangeEvent::Added(record) => record,
+            ChangeEvent::Replaced { from, to } => {
+                self.defined.remove(from.date_range());
+                to
+            }
+        };
+        if let (Record::Entry(entry), false) = (record, record.is_deleted_record()) {
+            self.add(entry);
+        }
+    }
+
+    /// Adds schemas defined by the entry, if any
+    pub fn add(&mut self, entry: &Entry) {
+        let schemas = TagSchema::from_record(entry);
+        if !schemas.is_empty() {
+            self.defined.insert(entry.date_range, schemas);
+        }
+    }
+
+    /// Returns the latest schema defined for the tag
+    pub fn get(&self, tag: &str) -> Option<&TagSchema> {
+        self.defined
+            .values()
+            .rev()
+            .flatten()
+            .find(|v| v.tag == tag)
+            .or_else(|| self.builtin.get(tag))
+    }
+
+    /// Checks the entry against schemas of its tags. Schema definitions are checked for unknown types instead
+    pub fn validate(&self, entry: &Entry, kinds: &SkillKinds) -> Vec<SchemaWarning> {
+        let mut warnings = Vec::new();
+        if let Some(schema) = entry.tags.iter().find(|v| v.name == "schema") {
+            for prop in schema.props.iter().filter(|v| v.name != "optional") {
+                if PropType::parse(&prop.val).is_none() {
+                    warnings.push(SchemaWarning::UnknownType {
+                        prop: prop.name.clone(),
+                        kind: prop.val.to_string(),
+                    });
+                }
+            }
+            return warnings;
+        }
+        // Tags of definitions are selectors rather than activities, so only definition tags themselves are checked
+        let is_reserved = |tag: &Tag| RESERVED_TAGS.contains(&tag.name.as_str());
+        let is_definition = entry
+            .tags
+            .iter()
+            .any(|v| v.name != "entry" && is_reserved(v));
+        for tag in &entry.tags {
+            if is_definition && !is_reserved(tag) {
+                continue;
+            }
+            if let Some(schema) = self.get(&tag.name) {
+                schema.validate(tag, &mut warnings);
+            }
+            if tag.name != "skill" {
+                continue;
+            }
+            for prop in tag.props.iter().filter(|v| v.name == "kind") {
+                let kind = prop.val.to_string();
+                if !kind.is_empty() && !kinds.contains(&kind) {
+                    warnings.push(SchemaWarning::UnknownSkillKind(kind));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn validate(schemas: &[&str], s: &str) -> Vec<String> {
+        let mut all = Schemas::default();
+        for schema in schemas {
+            all.add(&Entry::parse(schema).unwrap());
+        }
+        all.validate(&Entry::parse(s).unwrap(), &SkillKinds::default())
+            .iter()
+            .map(|v| v.to_string())
+            .collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn prop_types() {
+        let cases = vec![
+            ("number", "5", true),
+            ("number", "5km", false),
+            ("quantity", "5km", true),
+            ("length", "5mi", true),
+            ("length", "5kg", false),
+            ("km", "500m", true),
+            ("time", "01:30", true),
+            ("date", "2023-07-03", true),
+            ("bool", "true", true),
+            ("bool", "", true),
+            ("bool", "yes", false),
+            ("text", "yes", true),
+            ("text", "", false),
+            ("any", "", true),
+            ("number", "1,2", true),
+            ("number", "1,b", false),
+        ];
+        for (kind, val, want) in cases {
+            let kind = PropType::parse(&PropVal::String(kind.to_string())).unwrap();
+            let val = PropVal::parse(val.to_string());
+            assert_eq!(kind.matches(&val), want, "{kind} {val}");
+        }
+        let one_of = PropType::parse(&PropVal::parse("road,trail".to_string())).unwrap();
+        assert!(one_of.matches(&PropVal::parse("trail".to_string())));
+        assert!(!one_of.matches(&PropVal::parse("track".to_string())));
+        assert_eq!(PropType::parse(&PropVal::String("speed".to_string())), None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn validation() {
+        let run = "2023-07-01 00:00 run. schema distance=length kind=road,trail optional=kind";
+        assert!(validate(&[run], "2023-07-03 10:00 11:00 run distance=10km").is_empty());
+        assert!(validate(&[], "2023-07-03 10:00 11:00 run").is_empty());
+        assert_eq!(
+            validate(&[run], "2023-07-03 10:00 11:00 run kind=track. read"),
+            vec![
+                "Missing required prop 'distance' of 'run'",
+                "Prop 'kind' of 'run' should be one of road,trail, got 'track'"
+            ]
+        );
+        assert_eq!(
+            validate(&[run], "2023-07-03 10:00 11:00 run distance=10"),
+            vec!["Prop 'distance' of 'run' should be a length like 10m, got '10'"]
+        );
+        // Selectors of definitions aren't checked
+        assert!(validate(&[run], "2023-07-01 00:00 run. skill kind=physical. Running").is_empty());
+        // Later definitions take precedence
+        let run_v2 = "2023-07-02 00:00 run. schema distance=number";
+        assert!(validate(&[run, run_v2], "2023-07-03 10:00 11:00 run distance=10").is_empty());
+
+        // Schema definitions and built-in schemas
+        assert_eq!(
+            validate(&[], "2023-07-01 00:00 run. schema distance=speed"),
+            vec!["Unknown type 'speed' of prop 'distance' in the schema"]
+        );
+        assert_eq!(
+            validate(&[], "2023-07-01 00:00 run. skill perfect=many. Running"),
+            vec![
+                "Missing required prop 'kind' of 'skill'",
+                "Prop 'perfect' of 'skill' should be a number, got 'many'"
+            ]
+        );
+        assert_eq!(
+            validate(&[], "2023-07-01 00:00 run. skill kind=sport. Running"),
+            vec!["Unknown skill kind 'sport', define it with 'skillkind name=sport'"]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/*
+Property values could have a unit right after the number, e.g. `distance=10km` or `weight=72.4kg`. Units of the
+same dimension are convertible, so `run distance>5km` matches `run distance=8000m` and totals of `10km` and
+`500m` give `10.5km`. Time is written as HH:MM duration instead, as `5m` would be ambiguous with meters
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Energy,
+}
+
+impl Dimension {
+    const ALL: [Dimension; 4] = [
+        Dimension::Length,
+        Dimension::Mass,
+        Dimension::Volume,
+        Dimension::Energy,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Mass => "mass",
+            Dimension::Volume => "volume",
+            Dimension::Energy => "energy",
+        }
+    }
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Dimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dimension::ALL
+            .into_iter()
+            .find(|v| v.name() == s)
+            .ok_or_else(|| format!("Unknown dimension {s}"))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Unit {
+    Millimeter,
+    Centimeter,
+    Meter,
+    Kilometer,
+    Mile,
+    Gram,
+    Kilogram,
+    Pound,
+    Milliliter,
+    Liter,
+    Kilocalorie,
+    Kilojoule,
+}
+
+impl Unit {
+    const ALL: [Unit; 12] = [
+        Unit::Millimeter,
+        Unit::Centimeter,
+        Unit::Meter,
+        Unit::Kilometer,
+        Unit::Mile,
+        Unit::Gram,
+        Unit::Kilogram,
+        Unit::Pound,
+        Unit::Milliliter,
+        Unit::Liter,
+        Unit::Kilocalorie,
+        Unit::Kilojoule,
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Millimeter => "mm",
+            Unit::Centimeter => "cm",
+            Unit::Meter => "m",
+            Unit::Kilometer => "km",
+            Unit::Mile => "mi",
+            Unit::Gram => "g",
+            Unit::Kilogram => "kg",
+            Unit::Pound => "lb",
+            Unit::Milliliter => "ml",
+            Unit::Liter => "l",
+            Unit::Kilocalorie => "kcal",
+            Unit::Kilojoule => "kj",
+        }
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            Unit::Millimeter | Unit::Centimeter | Unit::Meter | Unit::Kilometer | Unit::Mile => {
+                Dimension::Length
+            }
+            Unit::Gram | Unit::Kilogram | Unit::Pound => Dimension::Mass,
+            Unit::Milliliter | Unit::Liter => Dimension::Volume,
+            Unit::Kilocalorie | Unit::Kilojoule => Dimension::Energy,
+        }
+    }
+
+    /// Unit values of the dimension are normalized to
+    pub fn base(dimension: Dimension) -> Unit {
+        match dimension {
+            Dimension::Length => Unit::Meter,
+            Dimension::Mass => Unit::Kilogram,
+            Dimension::Volume => Unit::Liter,
+            Dimension::Energy => Unit::Kilocalorie,
+        }
+    }
+
+    /// How many base units are in this unit
+    fn factor(&self) -> f32 {
+        match self {
+            Unit::Millimeter => 0.001,
+            Unit::Centimeter => 0.01,
+            Unit::Meter => 1.0,
+            Unit::Kilometer => 1000.0,
+            Unit::Mile => 1609.344,
+            Unit::Gram => 0.001,
+            Unit::Kilogram => 1.0,
+            Unit::Pound => 0.453_592_37,
+            Unit::Milliliter => 0.001,
+            Unit::Liter => 1.0,
+            Unit::Kilocalorie => 1.0,
+            Unit::Kilojoule => 0.239_005_74,
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Unit::ALL
+            .into_iter()
+            .find(|v| v.symbol() == s)
+            .ok_or_else(|| format!("Unknown unit {s}"))
+    }
+}
+
+/// Number with a unit, format `10km`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Quantity {
+    pub value: f32,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: f32, unit: Unit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Converts to another unit of the same dimension
+    pub fn convert(&self, unit: Unit) -> Option<Quantity> {
+        if unit.dimension() != self.unit.dimension() {
+            return None;
+        }
+        Some(Quantity::new(
+            self.value * self.unit.factor() / unit.factor(),
+            unit,
+        ))
+    }
+
+    /// Converts to the base unit of the dimension, e.g. meters for lengths
+    pub fn normalize(&self) -> Quantity {
+        self.convert(Unit::base(self.unit.dimension()))
+            .expect("base unit has the same dimension")
+    }
+
+    /// Sum in the unit of this quantity, None if units aren't compatible
+    pub fn checked_add(&self, other: &Quantity) -> Option<Quantity> {
+        let other = other.convert(self.unit)?;
+        Some(Quantity::new(self.value + other.value, self.unit))
+    }
+
+    /// Compares values regardless of units, None if units aren't compatible
+    pub fn compare(&self, other: &Quantity) -> Option<Ordering> {
+        let other = other.convert(self.unit)?;
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}{}", self.value, self.unit))
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pos = s
+            .find(|c: char| c.is_alphabetic())
+            .ok_or_else(|| format!("Unit is missing in {s}"))?;
+        let (value, unit) = s.split_at(pos);
+        let value = value
+            .parse::<f32>()
+            .map_err(|_| format!("Bad number in {s}"))?;
+        Ok(Quantity::new(value, unit.parse()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn parsing() {
+        let cases = vec![
+            ("10km", Some(Quantity::new(10.0, Unit::Kilometer))),
+            ("72.4kg", Some(Quantity::new(72.4, Unit::Kilogram))),
+            ("-0.5l", Some(Quantity::new(-0.5, Unit::Liter))),
+            ("500kcal", Some(Quantity::new(500.0, Unit::Kilocalorie))),
+            ("10", None),
+            ("km", None),
+            ("10kmh", None),
+            ("10 km", None),
+        ];
+        for (input, want) in cases {
+            assert_eq!(input.parse::<Quantity>().ok(), want, "input {input}");
+            if let Some(want) = want {
+                assert_eq!(want.to_string(), input);
+            }
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn conversion() {
+        let km = Quantity::new(10.0, Unit::Kilometer);
+        let m = Quantity::new(500.0, Unit::Meter);
+        assert_eq!(km.normalize(), Quantity::new(10000.0, Unit::Meter));
+        assert_eq!(
+            km.checked_add(&m),
+            Some(Quantity::new(10.5, Unit::Kilometer))
+        );
+        assert_eq!(km.compare(&m), Some(Ordering::Greater));
+        assert_eq!(
+            km.compare(&Quantity::new(10000.0, Unit::Meter)),
+            Some(Ordering::Equal)
+        );
+        let kg = Quantity::new(1.0, Unit::Kilogram);
+        assert_eq!(km.checked_add(&kg), None);
+        assert_eq!(km.compare(&kg), None);
+        let pounds = kg.convert(Unit::Pound).unwrap();
+        assert!((pounds.value - 2.2046).abs() < 0.001);
+    }
+}
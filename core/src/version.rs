@@ -0,0 +1,275 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::record::PropVal;
+
+/*
+Versioning of entries for syncing between devices. Every entry has a version stored in the `entry` tag:
+- `revision` is a Lamport clock, every edit gets revision higher than any revision of the replaced entries
+- `clock` is a vector clock with number of edits per device, e.g. `entry revision=3 clock=laptop@2,phone@1`
+
+Edit supersedes another one only if it was made with knowledge of it, i.e. its clock dominates. Edits made
+independently on different devices are concurrent and both are kept as a conflict until user resolves it.
+Entries without a clock, e.g. created before clocks were introduced or by older clients, are compared by
+revisions only: higher revision wins and edits with the same revision are concurrent. Entry without a clock
+doesn't tell which edits it knows about, so it never supersedes an entry with a clock, but it's superseded by
+an entry with a clock and higher revision. This keeps causality transitive, so merge converges in any order.
+*/
+
+/// How two versions relate to each other
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Causality {
+    /// Same version, entries with different content but equal version are concurrent edits
+    Equal,
+    /// Version happened before the other one, so it's superseded
+    Before,
+    /// Version happened after the other one and supersedes it
+    After,
+    /// Versions were created independently
+    Concurrent,
+}
+
+/// Version of the entry, ordering is deterministic, but has nothing to do with causality, use `causality` for that
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
+pub struct Version {
+    revision: usize,
+    clock: BTreeMap<String, usize>,
+}
+
+impl Default for Version {
+    fn default() -> Self {
+        Version {
+            revision: 1,
+            clock: BTreeMap::new(),
+        }
+    }
+}
+
+impl Version {
+    pub fn new(revision: usize, clock: BTreeMap<String, usize>) -> Self {
+        Version { revision, clock }
+    }
+
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn clock(&self) -> &BTreeMap<String, usize> {
+        &self.clock
+    }
+
+    /// Version of the edit made on the device. Device id could contain only lowercase letters, digits and dashes,
+    /// other chars are dropped
+    pub fn next(&self, device: &str) -> Version {
+        let device: String = device
+            .chars()
+            .flat_map(|c| c.to_lowercase())
+            .filter(|c| c.is_alphanumeric() || *c == '-')
+            .collect();
+        let mut next = self.clone();
+        next.revision += 1;
+        *next.clock.entry(device).or_default() += 1;
+        next
+    }
+
+    /// Version which knows about both versions, edit based on it supersedes both of them
+    pub fn join(&self, other: &Version) -> Version {
+        let mut joined = self.clone();
+        joined.revision = self.revision.max(other.revision);
+        for (device, count) in &other.clock {
+            let v = joined.clock.entry(device.clone()).or_default();
+            *v = (*v).max(*count);
+        }
+        joined
+    }
+
+    pub fn causality(&self, other: &Version) -> Causality {
+        let by_revision = match self.revision.cmp(&other.revision) {
+            std::cmp::Ordering::Less => Causality::Before,
+            std::cmp::Ordering::Equal => Causality::Equal,
+            std::cmp::Ordering::Greater => Causality::After,
+        };
+        match (self.clock.is_empty(), other.clock.is_empty()) {
+            (true, true) => return by_revision,
+            (true, false) if by_revision == Causality::Before => return Causality::Before,
+            (false, true) if by_revision == Causality::After => return Causality::After,
+            (true, false) | (false, true) => return Causality::Concurrent,
+            (false, false) => {}
+        }
+        let count = |clock: &BTreeMap<String, usize>, device: &String| -> usize {
+            clock.get(device).copied().unwrap_or_default()
+        };
+        let devices = self.clock.keys().chain(other.clock.keys());
+        let (mut less, mut greater) = (false, false);
+        for device in devices {
+            let (a, b) = (count(&self.clock, device), count(&other.clock, device));
+            less |= a < b;
+            greater |= a > b;
+        }
+        match (less, greater) {
+            // Same clocks, but revisions may differ if older client edited the entry and kept the clock as is
+            (false, false) => by_revision,
+            // Causal successor always has higher revision, otherwise one of the edits ignored another one
+            (false, true) if by_revision == Causality::After => Causality::After,
+            (true, false) if by_revision == Causality::Before => Causality::Before,
+            _ => Causality::Concurrent,
+        }
+    }
+
+    /// Reads the clock from the value of `clock` prop, malformed items are skipped
+    pub(crate) fn parse_clock(val: &PropVal) -> BTreeMap<String, usize> {
+        val.items()
+            .iter()
+            .filter_map(|v| {
+                let v = v.to_string();
+                let (device, count) = v.split_once('@')?;
+                Some((device.to_string(), count.parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// Value for the `clock` prop, `None` if the clock is empty
+    pub(crate) fn clock_value(&self) -> Option<PropVal> {
+        let mut items: Vec<_> = self
+            .clock
+            .iter()
+            .map(|(device, count)| PropVal::String(format!("{device}@{count}")))
+            .collect();
+        match items.len() {
+            0 => None,
+            1 => items.pop(),
+            _ => Some(PropVal::List(items)),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("revision={}", self.revision))?;
+        if let Some(clock) = self.clock_value() {
+            f.write_fmt(format_args!(" clock={clock}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn version(revision: usize, clock: &[(&str, usize)]) -> Version {
+        let clock = clock.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        Version::new(revision, clock)
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn causality() {
+        let cases = vec![
+            // Revisions only
+            (version(1, &[]), version(1, &[]), Causality::Equal),
+            (version(2, &[]), version(1, &[]), Causality::After),
+            (version(1, &[]), version(2, &[]), Causality::Before),
+            // Clocks
+            (version(2, &[("a", 1)]), version(1, &[]), Causality::After),
+            (
+                version(2, &[("a", 1)]),
+                version(2, &[]),
+                Causality::Concurrent,
+            ),
+            // Entry without a clock never supersedes an entry with a clock
+            (version(1, &[]), version(2, &[("a", 1)]), Causality::Before),
+            (
+                version(3, &[]),
+                version(2, &[("a", 1)]),
+                Causality::Concurrent,
+            ),
+            (
+                version(3, &[("a", 2)]),
+                version(2, &[("a", 1)]),
+                Causality::After,
+            ),
+            (
+                version(3, &[("a", 1), ("b", 1)]),
+                version(2, &[("a", 1)]),
+                Causality::After,
+            ),
+            (
+                version(2, &[("a", 1)]),
+                version(2, &[("b", 1)]),
+                Causality::Concurrent,
+            ),
+            (
+                version(4, &[("a", 3)]),
+                version(2, &[("b", 1)]),
+                Causality::Concurrent,
+            ),
+            // Older client edited the entry keeping the clock
+            (
+                version(3, &[("a", 1)]),
+                version(2, &[("a", 1)]),
+                Causality::After,
+            ),
+            // Clock dominates, but revision doesn't, so one of the edits ignored the other one
+            (
+                version(2, &[("a", 1), ("b", 1)]),
+                version(2, &[("a", 1)]),
+                Causality::Concurrent,
+            ),
+        ];
+        for (a, b, want) in cases {
+            assert_eq!(a.causality(&b), want, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn transitivity() {
+        let versions = [
+            version(1, &[]),
+            version(2, &[]),
+            version(3, &[]),
+            version(2, &[("a", 1)]),
+            version(2, &[("b", 1)]),
+            version(3, &[("a", 1)]),
+            version(3, &[("a", 2)]),
+            version(4, &[("a", 1)]),
+            version(4, &[("a", 1), ("b", 1)]),
+        ];
+        for a in &versions {
+            for b in &versions {
+                let ab = a.causality(b);
+                let reversed = match ab {
+                    Causality::Before => Causality::After,
+                    Causality::After => Causality::Before,
+                    v => v,
+                };
+                assert_eq!(b.causality(a), reversed, "{a} vs {b}");
+                if ab != Causality::After {
+                    continue;
+                }
+                for c in versions
+                    .iter()
+                    .filter(|c| b.causality(c) == Causality::After)
+                {
+                    assert_eq!(a.causality(c), Causality::After, "{a} > {b} > {c}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn next_and_join() {
+        let v = Version::default().next("Laptop");
+        assert_eq!(v.to_string(), "revision=2 clock=laptop@1");
+        let a = v.next("laptop");
+        let b = v.next("phone");
+        assert_eq!(a.causality(&b), Causality::Concurrent);
+        let resolved = a.join(&b).next("phone");
+        assert_eq!(resolved.to_string(), "revision=4 clock=laptop@2,phone@2");
+        assert_eq!(resolved.causality(&a), Causality::After);
+        assert_eq!(resolved.causality(&b), Causality::After);
+    }
+}